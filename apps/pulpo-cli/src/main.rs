@@ -1,13 +1,16 @@
 use anyhow::{Context, Result};
 use async_trait::async_trait;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use console::style;
 use dialoguer::{Confirm, Input, Select, theme::ColorfulTheme};
 use pulpo_engine::{
     agents::cli_client::ShellCliClient,
     config::{self, IclConfig},
+    git::GitOptions,
+    graph::{DependencyGraph, FailurePolicy, ONTOLOGY_FILES, TimeoutConfig, find_ontology_file},
     interaction::UserInteraction,
     orchestrator::{IterationInfo, Orchestrator},
+    usage::{Budget, BudgetAction},
 };
 use serde_json::Value;
 use std::path::{Path, PathBuf};
@@ -52,9 +55,85 @@ struct Args {
     #[arg(long, default_value = "100")]
     max_iterations: usize,
 
+    /// Maximum number of independent actions dispatched in parallel (default: 1)
+    #[arg(long, default_value = "1")]
+    concurrency: usize,
+
+    /// Rounds of questions an agent may ask the user for one action; 0 disables questions (default: 3)
+    #[arg(long, default_value = "3")]
+    max_clarifications: usize,
+
+    /// Default failure policy for edges without `onFailure`: abort, skip, pause, retry or retry:<n>
+    #[arg(long, default_value = "abort")]
+    on_failure: FailurePolicy,
+
+    /// Kill an action's AI CLI after this many seconds (overrides the ontology)
+    #[arg(long)]
+    timeout: Option<u64>,
+
+    /// Kill an action's AI CLI after this many seconds without output (overrides the ontology)
+    #[arg(long)]
+    stall_timeout: Option<u64>,
+
+    /// Stop dispatching once the iteration used this many tokens (needs --output-format json)
+    #[arg(long)]
+    max_tokens: Option<u64>,
+
+    /// Stop dispatching once this run has taken this many seconds
+    #[arg(long)]
+    max_wall_time: Option<u64>,
+
+    /// Stop dispatching once the iteration made this many agent dispatches
+    #[arg(long)]
+    max_dispatches: Option<usize>,
+
+    /// What to do when a budget is exceeded: pause (resumable) or stop (fail the run)
+    #[arg(long, default_value = "pause")]
+    on_budget: BudgetAction,
+
+    /// Print the predicted execution order, context and prompts without calling any AI
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Do not commit produced artifacts (only done when the work dir is a git repository)
+    #[arg(long)]
+    no_git: bool,
+
+    /// Commit each iteration on its own pulpo/<iteration id> branch
+    #[arg(long)]
+    git_branch: bool,
+
+    /// Refuse to resume an iteration whose ontology changed since it started, instead of warning
+    #[arg(long)]
+    locked: bool,
+
     /// Path to search for ontologies (default: current directory)
     #[arg(long)]
     ontology_path: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum Command {
+    /// Invalidate an entity and everything that depends on it, then re-run that part of the graph
+    Invalidate {
+        /// Entity to invalidate (e.g. DesignSpec)
+        entity: String,
+
+        /// Iteration to invalidate in (prompts for one if omitted)
+        #[arg(long)]
+        iteration: Option<String>,
+    },
+    /// Reset the artifacts and iteration state to right after a node ran, then continue from there
+    Rollback {
+        /// Iteration to roll back (e.g. 20260214_0001)
+        iteration: String,
+
+        /// Node whose commit to roll back to (e.g. DesignSpec)
+        node: String,
+    },
 }
 
 struct CliInteraction {
@@ -200,8 +279,14 @@ async fn discover_ontologies_recursive(dir: &Path, results: &mut Vec<PathBuf>) -
 
     while let Some(entry) = entries.next_entry().await? {
         let path = entry.path();
-        if path.is_file() && path.file_name().and_then(|n| n.to_str()) == Some("ontology.json") {
-            results.push(dir.to_path_buf());
+        let is_ontology_file = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|name| ONTOLOGY_FILES.contains(&name));
+        if path.is_file() && is_ontology_file {
+            if !results.iter().any(|found| found == dir) {
+                results.push(dir.to_path_buf());
+            }
         } else if path.is_dir() {
             let dir_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
             // Skip hidden dirs, node_modules, target, dist, .git
@@ -236,6 +321,11 @@ async fn main() -> Result<()> {
     println!("{}", style("---------------------------").dim());
 
     let (ontology_dir, ontology_content) = select_ontology(&args).await?;
+    check_ontology(&ontology_dir, &ontology_content)?;
+
+    if args.dry_run {
+        return run_dry_run(&args, &base_work_dir, &ontology_dir, &ontology_content).await;
+    }
 
     let (final_work_dir, app_name, final_app_id, docs_folder) =
         select_or_create_project(&base_work_dir, &args).await?;
@@ -250,6 +340,7 @@ async fn main() -> Result<()> {
     println!("{}", style("Running in LIVE MODE (calling AI CLI)").green());
 
     let category_defaults = map_models_to_categories(&args)?;
+    let (cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
 
     let client = ShellCliClient::new("gemini", final_work_dir.to_string_lossy().to_string())
         .with_yolo(args.yolo)
@@ -271,14 +362,146 @@ async fn main() -> Result<()> {
     )
     .await?
     .with_max_iterations(args.max_iterations)
+    .with_concurrency(args.concurrency)
+    .with_max_clarification_rounds(args.max_clarifications)
+    .with_failure_policy(args.on_failure)
+    .with_timeouts(TimeoutConfig {
+        seconds: args.timeout,
+        stall_seconds: args.stall_timeout,
+    })
+    .with_budget(Budget {
+        max_tokens: args.max_tokens,
+        max_wall_secs: args.max_wall_time,
+        max_dispatches: args.max_dispatches,
+        on_exceeded: args.on_budget,
+    })
+    .with_git(GitOptions {
+        commit: !args.no_git,
+        branch_per_iteration: args.git_branch,
+    })
+    .with_locked(args.locked)
     .with_docs_folder(docs_folder)
-    .with_category_defaults(category_defaults);
+    .with_category_defaults(category_defaults)
+    .with_cancellation(cancel_rx);
 
     let ui = CliInteraction::new(args.clone());
-    handle_iteration_resumption(&mut orchestrator, &ui, &final_work_dir).await?;
+    match &args.command {
+        Some(Command::Invalidate { entity, iteration }) => {
+            invalidate_in_iteration(
+                &mut orchestrator,
+                &ui,
+                &final_work_dir,
+                entity,
+                iteration.as_deref(),
+            )
+            .await?
+        }
+        Some(Command::Rollback { iteration, node }) => {
+            let sha = orchestrator
+                .rollback(iteration, node)
+                .await
+                .with_context(|| format!("Failed to roll back iteration {}", iteration))?;
+            println!(
+                "\n{} {} to {} ({})\n",
+                style("Rolled back").bold().yellow(),
+                iteration,
+                node,
+                &sha[..sha.len().min(12)]
+            );
+        }
+        None => handle_iteration_resumption(&mut orchestrator, &ui, &final_work_dir).await?,
+    }
+
+    // Ctrl-C stops the run cleanly: AI CLI processes are killed and the
+    // iteration is checkpointed so it can be resumed.
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            eprintln!("\n{}", style("Interrupt received, stopping...").yellow());
+            let _ = cancel_tx.send(true);
+        }
+    });
+
+    let result = orchestrator.run(&ui).await;
 
-    orchestrator.run(&ui).await?;
+    let usage = &orchestrator.usage.iteration;
+    println!(
+        "{}",
+        style(format!(
+            "Usage: {} dispatch(es), {} tokens ({} in / {} out), {:.1}s in agents",
+            usage.dispatches,
+            usage.total_tokens,
+            usage.input_tokens,
+            usage.output_tokens,
+            usage.duration_ms as f64 / 1000.0
+        ))
+        .dim()
+    );
+
+    result
+}
+
+async fn run_dry_run(
+    args: &Args,
+    base_work_dir: &Path,
+    ontology_dir: &Path,
+    ontology_content: &str,
+) -> Result<()> {
+    println!(
+        "{}",
+        style("Running in DRY-RUN MODE (no AI calls)").yellow()
+    );
+
+    let goal = match &args.query {
+        Some(q) => q.clone(),
+        None => Input::with_theme(&ColorfulTheme::default())
+            .with_prompt("What feature do you want to build?")
+            .interact_text()?,
+    };
+    let app_id = args.app_id.clone().unwrap_or_else(|| "dry-run".to_string());
+
+    // The client is never invoked; it only satisfies the orchestrator's type.
+    let client = ShellCliClient::new("gemini", base_work_dir.to_string_lossy().to_string());
+    let orchestrator = Orchestrator::new_with_metamodel(
+        client,
+        app_id.clone(),
+        app_id,
+        base_work_dir.to_path_buf(),
+        ontology_content,
+        Some(ontology_dir),
+    )
+    .await?
+    .with_max_iterations(args.max_iterations)
+    .with_concurrency(args.concurrency);
 
+    let steps = orchestrator.dry_run(&goal);
+    println!("\n{}", style("PREDICTED EXECUTION ORDER:").bold().yellow());
+    for step in &steps {
+        println!(
+            "\n{:02}. {} {} {} ({:?})",
+            step.step,
+            style(&step.action.agent).bold().blue(),
+            style(&step.action.relation).dim(),
+            style(&step.action.target).bold().magenta(),
+            step.action.category
+        );
+        println!(
+            "    {} {}",
+            style("Context:").dim(),
+            if step.context.is_empty() {
+                "-".to_string()
+            } else {
+                step.context.join(", ")
+            }
+        );
+        println!("    {}", style("Prompt:").dim());
+        for line in step.prompt.lines() {
+            println!("      {}", line);
+        }
+    }
+    println!(
+        "\n{}",
+        style(format!("{} predicted steps.", steps.len())).bold()
+    );
     Ok(())
 }
 
@@ -314,7 +537,8 @@ async fn select_ontology(args: &Args) -> Result<(PathBuf, String)> {
     let ontology_dir = match discovered.len() {
         0 => {
             anyhow::bail!(
-                "No ontology.json found under '{}'.",
+                "No ontology ({}) found under '{}'.",
+                ONTOLOGY_FILES.join(", "),
                 ontology_search_dir.display()
             );
         }
@@ -350,10 +574,35 @@ async fn select_ontology(args: &Args) -> Result<(PathBuf, String)> {
         }
     };
 
-    let ontology_content = tokio::fs::read_to_string(ontology_dir.join("ontology.json")).await?;
+    let ontology_file =
+        find_ontology_file(&ontology_dir).context("The ontology directory has no ontology file")?;
+    let ontology_content = tokio::fs::read_to_string(ontology_file).await?;
     Ok((ontology_dir, ontology_content))
 }
 
+/// Prints what is wrong with the ontology and refuses to go on if any of it
+/// is an error.
+fn check_ontology(ontology_dir: &Path, ontology_content: &str) -> Result<()> {
+    let diagnostics = DependencyGraph::diagnose(ontology_content, Some(ontology_dir));
+    let file =
+        find_ontology_file(ontology_dir).unwrap_or_else(|| ontology_dir.join("ontology.json"));
+    for diagnostic in &diagnostics {
+        if diagnostic.is_error() {
+            println!("{}", style(diagnostic.render(&file)).red());
+        } else {
+            println!("{}", style(diagnostic.render(&file)).yellow().dim());
+        }
+    }
+    let errors = diagnostics.iter().filter(|d| d.is_error()).count();
+    if errors > 0 {
+        anyhow::bail!(
+            "The ontology has {} error(s); fix them before running it",
+            errors
+        );
+    }
+    Ok(())
+}
+
 async fn select_or_create_project(
     base_work_dir: &Path,
     args: &Args,
@@ -465,6 +714,7 @@ fn map_models_to_categories(
                 model_type: Some(category.to_string()),
                 model: Some(model_for_cat.clone()),
                 ai_cli: Some("gemini".to_string()),
+                ..Default::default()
             },
         );
         println!(
@@ -532,6 +782,52 @@ async fn handle_iteration_resumption<
     Ok(())
 }
 
+/// Loads an existing iteration and invalidates `entity` and its dependents in
+/// it, so the following run regenerates only that part of the graph.
+async fn invalidate_in_iteration<
+    C: pulpo_engine::agents::cli_client::AiCliClient + Clone + Send + Sync + 'static,
+>(
+    orchestrator: &mut Orchestrator<C>,
+    ui: &CliInteraction,
+    work_dir: &Path,
+    entity: &str,
+    iteration: Option<&str>,
+) -> Result<()> {
+    let iteration_id = match iteration {
+        Some(id) => id.to_string(),
+        None => {
+            let iterations = list_iterations(work_dir).await?;
+            if iterations.is_empty() {
+                anyhow::bail!("No iterations found to invalidate '{}' in", entity);
+            }
+            let options: Vec<String> = iterations
+                .iter()
+                .map(|(id, name)| format!("{} ({})", name, id))
+                .collect();
+            let selection = ui
+                .select_option(
+                    &format!("Invalidate '{}' in which iteration?", entity),
+                    &options,
+                )
+                .await?;
+            iterations[selection].0.clone()
+        }
+    };
+
+    orchestrator
+        .load_iteration(&iteration_id)
+        .await
+        .with_context(|| format!("Failed to load iteration {}", iteration_id))?;
+    let invalidated = orchestrator.invalidate(entity).await?;
+
+    println!("\n{}:", style("Invalidated").bold().yellow());
+    for node in invalidated {
+        println!("  {} {}", style("✗").yellow(), node);
+    }
+    println!();
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -607,6 +903,22 @@ mod tests {
         assert_eq!(config.version, "1.0.0");
     }
 
+    #[test]
+    fn test_check_ontology_refuses_errors() {
+        let tmp = tempfile::tempdir().unwrap();
+        let valid = r#"[
+            { "source": { "name": "Dev", "type": "Agent" }, "target": { "name": "Code", "type": "Other" }, "type": { "name": "implements", "verbType": "Creation" } }
+        ]"#;
+        assert!(check_ontology(tmp.path(), valid).is_ok());
+
+        let invalid = valid.replace(
+            r#""name": "Code", "type": "Other""#,
+            r#""name": "Reviewer", "type": "Agent""#,
+        );
+        let err = check_ontology(tmp.path(), &invalid).unwrap_err();
+        assert!(err.to_string().contains("The ontology has 1 error(s)"));
+    }
+
     #[tokio::test]
     async fn test_discover_ontologies_empty_dir() {
         let tmp = tempfile::tempdir().unwrap();
@@ -663,6 +975,27 @@ mod tests {
         assert!(result[1].ends_with("beta"));
     }
 
+    #[tokio::test]
+    async fn test_discover_ontologies_in_any_format() {
+        let tmp = tempfile::tempdir().unwrap();
+        let yaml = tmp.path().join("yaml");
+        let triples = tmp.path().join("triples");
+        tokio::fs::create_dir_all(&yaml).await.unwrap();
+        tokio::fs::create_dir_all(&triples).await.unwrap();
+        tokio::fs::write(yaml.join("ontology.yaml"), "[]")
+            .await
+            .unwrap();
+        tokio::fs::write(yaml.join("ontology.json"), "[]")
+            .await
+            .unwrap();
+        tokio::fs::write(triples.join("ontology.triples"), "")
+            .await
+            .unwrap();
+
+        let result = discover_ontologies(tmp.path()).await.unwrap();
+        assert_eq!(result, vec![triples, yaml]);
+    }
+
     #[tokio::test]
     async fn test_discover_ontologies_skips_hidden_and_dist() {
         let tmp = tempfile::tempdir().unwrap();
@@ -1,6 +1,8 @@
+use crate::usage::parse_cli_json_output;
 use anyhow::Result;
 use std::io::Write;
 use std::process::Stdio;
+use std::time::Duration;
 use tokio::io::AsyncReadExt;
 use tokio::process::Command;
 
@@ -126,15 +128,47 @@ impl ShellCliClient {
 
         cmd.stdout(Stdio::piped());
         cmd.stderr(Stdio::piped());
+        // If the caller drops this future (e.g. on Ctrl-C), do not leave the CLI running
+        cmd.kill_on_drop(true);
 
         let mut child = cmd.spawn()?;
         let stdout = child.stdout.take().unwrap();
         let stderr = child.stderr.take().unwrap();
-
-        let (full_stdout, full_stderr) = self.read_output_streams(stdout, stderr).await?;
-        let status = child.wait().await?;
+        let stall = options.stall_timeout_secs.map(Duration::from_secs);
+
+        let run = async {
+            let output = self.read_output_streams(stdout, stderr, stall).await?;
+            let status = child.wait().await?;
+            Ok::<_, anyhow::Error>((output, status))
+        };
+        let outcome = match options.timeout_secs {
+            Some(secs) => tokio::time::timeout(Duration::from_secs(secs), run)
+                .await
+                .unwrap_or_else(|_| Err(anyhow::anyhow!("AI CLI timed out after {}s", secs))),
+            None => run.await,
+        };
+
+        let ((full_stdout, full_stderr), status) = match outcome {
+            Ok(result) => result,
+            Err(e) => {
+                let _ = child.kill().await;
+                eprintln!("{}: {}", console::style("AI CLI KILLED").bold().red(), e);
+                return Err(e);
+            }
+        };
 
         self.handle_command_exit(status, &full_stderr)?;
+
+        // With `--output-format json` the answer is wrapped in an envelope that
+        // also carries the token counts.
+        if self.output_format.as_deref() == Some("json")
+            && let Some((answer, tokens)) = parse_cli_json_output(&full_stdout)
+        {
+            if let Some(tokens) = tokens {
+                options.usage.record(tokens);
+            }
+            return Ok(answer);
+        }
         Ok(full_stdout)
     }
 
@@ -181,6 +215,7 @@ impl ShellCliClient {
         &self,
         mut stdout: tokio::process::ChildStdout,
         mut stderr: tokio::process::ChildStderr,
+        stall: Option<Duration>,
     ) -> Result<(String, String)> {
         let mut full_stdout = String::new();
         let mut full_stderr = String::new();
@@ -217,6 +252,13 @@ impl ShellCliClient {
                         Err(e) => return Err(e.into()),
                     }
                 }
+                // Recreated on every loop turn, so any output resets it
+                _ = tokio::time::sleep(stall.unwrap_or_default()), if stall.is_some() => {
+                    return Err(anyhow::anyhow!(
+                        "AI CLI produced no output for {}s",
+                        stall.unwrap_or_default().as_secs()
+                    ));
+                }
             }
         }
         Ok((full_stdout, full_stderr))
@@ -332,4 +374,78 @@ mod tests {
         let result = client.prompt("hello", options).await;
         assert!(result.is_err());
     }
+
+    #[cfg(unix)]
+    fn write_script(dir: &std::path::Path, body: &str) -> String {
+        use std::os::unix::fs::PermissionsExt;
+        let path = dir.join("fake-cli.sh");
+        std::fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_timeout_kills_the_cli() {
+        let dir = tempfile::tempdir().unwrap();
+        let pid_file = dir.path().join("pid");
+        let script = write_script(
+            dir.path(),
+            &format!("echo $$ > {}\nexec sleep 30", pid_file.display()),
+        );
+        let client = ShellCliClient::new(&script, dir.path().to_string_lossy().to_string());
+        let options = crate::graph::executor::ExecutionOptions {
+            timeout_secs: Some(1),
+            ..Default::default()
+        };
+
+        let started = std::time::Instant::now();
+        let err = client.prompt("hello", options).await.unwrap_err();
+        assert!(err.to_string().contains("timed out after 1s"));
+        assert!(started.elapsed() < Duration::from_secs(10));
+
+        let pid = std::fs::read_to_string(&pid_file).unwrap();
+        assert!(!std::path::Path::new(&format!("/proc/{}", pid.trim())).exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stall_timeout_resets_on_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = write_script(
+            dir.path(),
+            "for i in 1 2 3; do echo tick; sleep 0.5; done\necho done\nexec sleep 30",
+        );
+        let client = ShellCliClient::new(&script, dir.path().to_string_lossy().to_string());
+        let options = crate::graph::executor::ExecutionOptions {
+            stall_timeout_secs: Some(1),
+            ..Default::default()
+        };
+
+        // Ticks every 0.5s keep it alive; the final silent sleep trips the stall timeout.
+        let started = std::time::Instant::now();
+        let err = client.prompt("hello", options).await.unwrap_err();
+        assert!(err.to_string().contains("no output for 1s"));
+        assert!(started.elapsed() >= Duration::from_millis(1500));
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_json_output_is_unwrapped_and_tokens_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = write_script(
+            dir.path(),
+            r#"echo '{"response": "{\"ok\": true}", "stats": {"models": {"m": {"tokens": {"prompt": 12, "candidates": 3, "total": 15}}}}}'"#,
+        );
+        let client = ShellCliClient::new(&script, dir.path().to_string_lossy().to_string())
+            .with_output_format("json".to_string());
+        let options = crate::graph::executor::ExecutionOptions::default();
+        let usage = options.usage.clone();
+
+        let answer = client.prompt("hello", options).await.unwrap();
+        assert_eq!(answer, r#"{"ok": true}"#);
+        let tokens = usage.take().unwrap();
+        assert_eq!((tokens.input, tokens.output, tokens.total), (12, 3, 15));
+    }
 }
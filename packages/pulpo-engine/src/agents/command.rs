@@ -0,0 +1,139 @@
+use crate::agents::Agent;
+use crate::domain::types::AgentRole;
+use crate::graph::COMMAND_RUNNER;
+use crate::graph::executor::Task;
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+
+// Longer outputs keep only their tail, which is where errors usually are
+const MAX_OUTPUT_CHARS: usize = 20_000;
+
+/// Runs the command line of `Command` entities in the work dir, without any
+/// AI involved. The task's prompt is the command line; the result records the
+/// exit code and the captured output. A non-zero exit code is a result, not a
+/// failure: failing tests are useful context for the next edges.
+pub struct CommandAgent {
+    work_dir: PathBuf,
+}
+
+impl CommandAgent {
+    pub fn new(work_dir: PathBuf) -> Self {
+        Self { work_dir }
+    }
+
+    fn shell(command_line: &str) -> Command {
+        if cfg!(windows) {
+            let mut cmd = Command::new("cmd");
+            cmd.arg("/C").arg(command_line);
+            cmd
+        } else {
+            let mut cmd = Command::new("sh");
+            cmd.arg("-c").arg(command_line);
+            cmd
+        }
+    }
+}
+
+fn tail(output: &[u8]) -> String {
+    let text = String::from_utf8_lossy(output);
+    let count = text.chars().count();
+    if count <= MAX_OUTPUT_CHARS {
+        return text.into_owned();
+    }
+    let skipped: String = text.chars().skip(count - MAX_OUTPUT_CHARS).collect();
+    format!(
+        "[... {} chars omitted]\n{}",
+        count - MAX_OUTPUT_CHARS,
+        skipped
+    )
+}
+
+#[async_trait]
+impl Agent for CommandAgent {
+    fn role(&self) -> AgentRole {
+        AgentRole::from(COMMAND_RUNNER.to_string())
+    }
+
+    async fn execute(&self, task: Task) -> Result<Value> {
+        let command_line = task
+            .prompt
+            .ok_or_else(|| anyhow::anyhow!("CommandAgent requires the command line as prompt"))?;
+
+        let mut cmd = Self::shell(&command_line);
+        cmd.current_dir(&self.work_dir);
+        cmd.stdin(Stdio::null());
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::piped());
+        // If the caller drops this future (e.g. on Ctrl-C), do not leave the command running
+        cmd.kill_on_drop(true);
+
+        let started = Instant::now();
+        let child = cmd
+            .spawn()
+            .map_err(|e| anyhow::anyhow!("Failed to run '{}': {}", command_line, e))?;
+        let output = match task.options.timeout_secs {
+            Some(secs) => tokio::time::timeout(Duration::from_secs(secs), child.wait_with_output())
+                .await
+                .map_err(|_| {
+                    anyhow::anyhow!("Command '{}' timed out after {}s", command_line, secs)
+                })?,
+            None => child.wait_with_output().await,
+        }?;
+
+        Ok(serde_json::json!({
+            "command": command_line,
+            "exitCode": output.status.code(),
+            "success": output.status.success(),
+            "stdout": tail(&output.stdout),
+            "stderr": tail(&output.stderr),
+            "durationMs": started.elapsed().as_millis() as u64,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::executor::ExecutionOptions;
+
+    fn task(command_line: &str, timeout_secs: Option<u64>) -> Task {
+        Task {
+            id: "task_runs_Check".to_string(),
+            description: "runs Check".to_string(),
+            inputs: vec![],
+            prompt: Some(command_line.to_string()),
+            options: ExecutionOptions {
+                timeout_secs,
+                ..Default::default()
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_command_result_is_captured() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(dir.path().join("marker.txt"), "here")?;
+        let agent = CommandAgent::new(dir.path().to_path_buf());
+
+        let result = agent
+            .execute(task("cat marker.txt; echo oops >&2; exit 3", None))
+            .await?;
+        assert_eq!(result["exitCode"], 3);
+        assert_eq!(result["success"], false);
+        assert_eq!(result["stdout"], "here");
+        assert_eq!(result["stderr"], "oops\n");
+
+        let err = agent.execute(task("sleep 5", Some(1))).await.unwrap_err();
+        assert!(err.to_string().contains("timed out after 1s"));
+
+        assert!(
+            tail("x".repeat(MAX_OUTPUT_CHARS + 5).as_bytes()).starts_with("[... 5 chars omitted]")
+        );
+        Ok(())
+    }
+}
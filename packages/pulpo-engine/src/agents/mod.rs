@@ -5,6 +5,7 @@ use async_trait::async_trait;
 use serde_json::Value;
 
 pub mod cli_client;
+pub mod command;
 pub mod generic;
 
 #[async_trait]
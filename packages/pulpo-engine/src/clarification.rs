@@ -0,0 +1,127 @@
+use crate::interaction::UserInteraction;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Suffix of the artifact holding the answers given for a node, e.g.
+/// `DesignSpec_clarifications`.
+pub const CLARIFICATIONS_SUFFIX: &str = "_clarifications";
+
+// Offered next to a question's options when free text is allowed
+const OTHER_OPTION: &str = "Other (type an answer)";
+
+/// Question an agent asks the user instead of producing its artifact.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Question {
+    pub question: String,
+    #[serde(default)]
+    pub options: Vec<String>,
+    #[serde(
+        rename = "allowFreeText",
+        default = "Question::default_allow_free_text"
+    )]
+    pub allow_free_text: bool,
+}
+
+impl Question {
+    fn default_allow_free_text() -> bool {
+        true
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Answer {
+    pub question: String,
+    pub answer: String,
+}
+
+/// Name of the artifact holding the answers given for `key`.
+pub fn clarifications_key(key: &str) -> String {
+    format!("{}{}", key, CLARIFICATIONS_SUFFIX)
+}
+
+/// Questions of an agent's response, if it is a clarification request: an
+/// object whose only key is `clarification`, holding a non-empty `questions`
+/// array. Anything else, including artifacts that have a `questions` field of
+/// their own, is an artifact.
+pub fn parse_questions(value: &Value) -> Option<Vec<Question>> {
+    let object = value.as_object()?;
+    if object.len() != 1 {
+        return None;
+    }
+    let request = object.get("clarification")?;
+    let questions: Vec<Question> =
+        serde_json::from_value(request.get("questions")?.clone()).ok()?;
+    (!questions.is_empty()).then_some(questions)
+}
+
+/// Asks the questions of `agent` through the UI: questions with options are
+/// a selection (plus "Other" when free text is allowed), the others are open.
+pub async fn ask(
+    ui: &impl UserInteraction,
+    agent: &str,
+    questions: &[Question],
+) -> Result<Vec<Answer>> {
+    let mut answers = Vec::with_capacity(questions.len());
+    for question in questions {
+        let prompt = format!("{} asks: {}", agent, question.question);
+        let answer = if question.options.is_empty() {
+            ui.ask_user(&prompt).await?
+        } else {
+            let mut options = question.options.clone();
+            if question.allow_free_text {
+                options.push(OTHER_OPTION.to_string());
+            }
+            let selection = ui.select_option(&prompt, &options).await?;
+            match question.options.get(selection) {
+                Some(option) => option.clone(),
+                None => ui.ask_user(&prompt).await?,
+            }
+        };
+        answers.push(Answer {
+            question: question.question.clone(),
+            answer,
+        });
+    }
+    Ok(answers)
+}
+
+/// Appended to the prompts of agents that may ask questions.
+pub const INSTRUCTIONS: &str = "\n\n**Open Questions**:\nIf you cannot proceed without decisions from the user, do not guess. Reply instead with ONLY a JSON code block of the form `{\"clarification\": {\"questions\": [{\"question\": \"...\", \"options\": [\"...\"], \"allowFreeText\": true}]}}`.";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_questions_payloads_are_told_apart_from_artifacts() {
+        let payload = serde_json::json!({"clarification": {"questions": [
+            {"question": "Which database?", "options": ["Postgres", "SQLite"]},
+            {"question": "Any naming rules?", "allowFreeText": true}
+        ]}});
+        let questions = parse_questions(&payload).unwrap();
+        assert_eq!(questions.len(), 2);
+        assert_eq!(questions[0].options, vec!["Postgres", "SQLite"]);
+        assert!(questions[0].allow_free_text);
+
+        assert!(
+            parse_questions(&serde_json::json!({"clarification": {"questions": []}})).is_none()
+        );
+        // A FAQ or questionnaire artifact is not a clarification request
+        assert!(
+            parse_questions(&serde_json::json!({"questions": [{"question": "Why?"}]})).is_none()
+        );
+        assert!(
+            parse_questions(&serde_json::json!({
+                "clarification": {"questions": [{"question": "Why?"}]},
+                "title": "FAQ"
+            }))
+            .is_none()
+        );
+        assert!(parse_questions(&serde_json::json!([{"question": "Why?"}])).is_none());
+        assert_eq!(
+            clarifications_key("Design[F1]"),
+            "Design[F1]_clarifications"
+        );
+    }
+}
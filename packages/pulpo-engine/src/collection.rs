@@ -0,0 +1,108 @@
+use serde_json::Value;
+
+/// Key of one instance of a node produced per item of a collection (its
+/// Creation edge declares `forEach`), e.g. `UserStory[F1]`.
+pub fn instance_key(node: &str, id: &str) -> String {
+    format!("{}[{}]", node, id)
+}
+
+/// Splits `UserStory[F1]` into `("UserStory", Some("F1"))`; plain node names
+/// are returned unchanged.
+pub fn split_instance(key: &str) -> (&str, Option<&str>) {
+    match key.strip_suffix(']').and_then(|k| k.split_once('[')) {
+        Some((node, id)) => (node, Some(id)),
+        None => (key, None),
+    }
+}
+
+/// Items of a collection artifact with their IDs. An array yields one item per
+/// element, anything else is a single item. IDs come from the item's `id`
+/// field, falling back to its 1-based position, and are made unique.
+pub fn collection_items(value: &Value) -> Vec<(String, Value)> {
+    let items: Vec<&Value> = match value {
+        Value::Array(items) => items.iter().collect(),
+        other => vec![other],
+    };
+
+    let mut result: Vec<(String, Value)> = Vec::with_capacity(items.len());
+    for (index, item) in items.into_iter().enumerate() {
+        let mut id = match item.get("id") {
+            Some(Value::String(s)) if !s.is_empty() => s.clone(),
+            Some(Value::Number(n)) => n.to_string(),
+            _ => (index + 1).to_string(),
+        };
+        if result.iter().any(|(existing, _)| *existing == id) {
+            id = format!("{}-{}", id, index + 1);
+        }
+        result.push((id, item.clone()));
+    }
+    result
+}
+
+/// Aggregates instance artifacts, in item order, into the collection consumed
+/// downstream. Instances that are themselves arrays are flattened.
+pub fn aggregate<'a>(instances: impl IntoIterator<Item = &'a Value>) -> Value {
+    let mut items = Vec::new();
+    for value in instances {
+        match value {
+            Value::Array(inner) => items.extend(inner.iter().cloned()),
+            other => items.push(other.clone()),
+        }
+    }
+    Value::Array(items)
+}
+
+/// File stem of an artifact in the docs folder: `UserStory` -> `userstory`,
+/// `UserStory[F1]_verification` -> `userstory-f1_verification`.
+pub fn file_stem(name: &str) -> String {
+    name.to_lowercase()
+        .chars()
+        .filter(|c| *c != ']')
+        .map(|c| match c {
+            '[' => '-',
+            c if c.is_ascii_alphanumeric() || c == '_' || c == '-' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instance_keys_and_items() {
+        assert_eq!(instance_key("UserStory", "F1"), "UserStory[F1]");
+        assert_eq!(split_instance("UserStory[F1]"), ("UserStory", Some("F1")));
+        assert_eq!(split_instance("UserStory"), ("UserStory", None));
+        assert_eq!(
+            file_stem("UserStory[F1]_verification"),
+            "userstory-f1_verification"
+        );
+        assert_eq!(file_stem("DesignSpec"), "designspec");
+
+        let features = serde_json::json!([
+            {"id": "F1", "name": "Login"},
+            {"name": "Search"},
+            {"id": "F1", "name": "Duplicate"}
+        ]);
+        let ids: Vec<String> = collection_items(&features)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(ids, vec!["F1", "2", "F1-3"]);
+        assert_eq!(
+            collection_items(&serde_json::json!({"name": "Only"}))[0].0,
+            "1"
+        );
+
+        let stories = [
+            serde_json::json!([{"id": "US1"}, {"id": "US2"}]),
+            serde_json::json!({"id": "US3"}),
+        ];
+        assert_eq!(
+            aggregate(&stories),
+            serde_json::json!([{"id": "US1"}, {"id": "US2"}, {"id": "US3"}])
+        );
+    }
+}
@@ -0,0 +1,339 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Stdio;
+use tokio::process::Command;
+
+// Identity of the engine's commits when git has none configured
+const FALLBACK_NAME: &str = "Pulpo";
+const FALLBACK_EMAIL: &str = "pulpo@localhost";
+
+/// How the engine uses git in the work dir.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GitOptions {
+    /// Commit the artifacts and files written by every action that creates or
+    /// refines one. Only done when the work dir is already inside a git repository.
+    pub commit: bool,
+    /// Commit each iteration on its own `pulpo/<iteration id>` branch.
+    pub branch_per_iteration: bool,
+}
+
+impl Default for GitOptions {
+    fn default() -> Self {
+        Self {
+            commit: true,
+            branch_per_iteration: false,
+        }
+    }
+}
+
+/// Branch the commits of an iteration go to when `branch_per_iteration` is set.
+pub fn iteration_branch(iteration_id: &str) -> String {
+    format!("pulpo/{}", iteration_id)
+}
+
+/// Commit message made of a subject line and `Key: value` trailers, which
+/// `git interpret-trailers` and `git log --format=%(trailers)` can read back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitMessage {
+    pub subject: String,
+    pub trailers: Vec<(String, String)>,
+}
+
+impl CommitMessage {
+    pub fn new(subject: impl Into<String>) -> Self {
+        Self {
+            subject: subject.into(),
+            trailers: Vec::new(),
+        }
+    }
+
+    pub fn with_trailer(mut self, key: &str, value: impl Into<String>) -> Self {
+        // A trailer value must stay on one line
+        let value = value.into().replace('\n', " ");
+        self.trailers.push((key.to_string(), value));
+        self
+    }
+
+    pub fn render(&self) -> String {
+        let mut message = self.subject.clone();
+        if !self.trailers.is_empty() {
+            message.push_str("\n\n");
+            let lines: Vec<String> = self
+                .trailers
+                .iter()
+                .map(|(key, value)| format!("{}: {}", key, value))
+                .collect();
+            message.push_str(&lines.join("\n"));
+        }
+        message
+    }
+}
+
+/// Git operations on the work dir, run through the `git` CLI. Only the paths
+/// the engine names are staged and committed; everything else in the
+/// repository is left alone.
+#[derive(Debug, Clone)]
+pub struct GitRepo {
+    work_dir: PathBuf,
+}
+
+impl GitRepo {
+    pub fn new(work_dir: PathBuf) -> Self {
+        Self { work_dir }
+    }
+
+    /// Runs git in the work dir and returns its stdout, or its stderr as the
+    /// error when it exits with a non-zero code.
+    async fn git(&self, args: &[&str]) -> Result<String> {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(&self.work_dir)
+            .stdin(Stdio::null())
+            .output()
+            .await
+            .context("Failed to run git")?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "git {} failed: {}",
+                args.first().copied().unwrap_or_default(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Whether the work dir is inside a git repository. The engine never
+    /// creates one itself.
+    pub async fn is_repo(&self) -> bool {
+        self.git(&["rev-parse", "--is-inside-work-tree"])
+            .await
+            .is_ok()
+    }
+
+    /// Switches to `branch`, creating it from the current HEAD if needed.
+    /// Uncommitted changes are carried over.
+    pub async fn switch_branch(&self, branch: &str) -> Result<()> {
+        let reference = format!("refs/heads/{}", branch);
+        if self
+            .git(&["rev-parse", "--verify", "--quiet", &reference])
+            .await
+            .is_ok()
+        {
+            self.git(&["checkout", "--quiet", branch]).await?;
+        } else {
+            self.git(&["checkout", "--quiet", "-b", branch]).await?;
+        }
+        Ok(())
+    }
+
+    /// Name of the checked out branch.
+    pub async fn current_branch(&self) -> Result<String> {
+        self.git(&["symbolic-ref", "--short", "HEAD"]).await
+    }
+
+    /// Files of the work dir that differ from the index or are untracked (and
+    /// not ignored), relative to the work dir, with the hash of their content
+    /// or `None` when deleted. Comparing two snapshots tells which files
+    /// changed in between.
+    pub async fn dirty_files(&self) -> Result<HashMap<String, Option<String>>> {
+        let listed = self
+            .git(&[
+                "ls-files",
+                "-z",
+                "--modified",
+                "--others",
+                "--exclude-standard",
+            ])
+            .await?;
+        let mut files: HashMap<String, Option<String>> = listed
+            .split('\0')
+            .filter(|path| !path.is_empty())
+            .map(|path| (path.to_string(), None))
+            .collect();
+        let existing: Vec<&str> = files
+            .keys()
+            .map(String::as_str)
+            .filter(|path| self.work_dir.join(path).is_file())
+            .collect();
+        if existing.is_empty() {
+            return Ok(files);
+        }
+        let mut args = vec!["hash-object", "--"];
+        args.extend(&existing);
+        let hashes = self.git(&args).await?;
+        let hashes: Vec<(String, String)> = existing
+            .iter()
+            .map(|path| path.to_string())
+            .zip(hashes.lines().map(str::to_string))
+            .collect();
+        for (path, hash) in hashes {
+            files.insert(path, Some(hash));
+        }
+        Ok(files)
+    }
+
+    /// Files (relative to the work dir) changed by the commits made since
+    /// `sha`.
+    pub async fn changed_since(&self, sha: &str) -> Result<Vec<String>> {
+        let changed = self
+            .git(&[
+                "diff",
+                "-z",
+                "--name-only",
+                "--no-renames",
+                "--relative",
+                sha,
+                "HEAD",
+            ])
+            .await?;
+        Ok(changed
+            .split('\0')
+            .filter(|path| !path.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Makes the given files (relative to the work dir) look exactly like
+    /// they did in commit `sha`: changed and deleted files are restored, files
+    /// that did not exist then are removed. Nothing else in the work dir is
+    /// touched and HEAD does not move.
+    pub async fn restore(&self, sha: &str, paths: &[String]) -> Result<()> {
+        if paths.is_empty() {
+            return Ok(());
+        }
+        let mut args = vec!["ls-tree", "-r", "--name-only", sha, "--"];
+        args.extend(paths.iter().map(String::as_str));
+        let at_sha = self.git(&args).await?;
+        let at_sha: Vec<&str> = at_sha.lines().collect();
+
+        if !at_sha.is_empty() {
+            let source = format!("--source={}", sha);
+            let mut args = vec!["restore", source.as_str(), "--staged", "--worktree", "--"];
+            args.extend(&at_sha);
+            self.git(&args).await?;
+        }
+        for path in paths.iter().filter(|p| !at_sha.contains(&p.as_str())) {
+            let file = self.work_dir.join(path);
+            if file.exists() {
+                tokio::fs::remove_file(&file)
+                    .await
+                    .with_context(|| format!("Failed to remove {}", file.display()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Stages and commits the given paths (relative to the work dir) and
+    /// nothing else, leaving any other staged or untracked change alone.
+    /// Returns the SHA of the new commit, or `None` when there was nothing to
+    /// commit.
+    pub async fn commit_paths(
+        &self,
+        message: &CommitMessage,
+        paths: &[String],
+    ) -> Result<Option<String>> {
+        // Deleted paths can only be staged if git knows them, in the index
+        // or at HEAD (there is no HEAD before the first commit)
+        let mut args = vec!["ls-files", "--"];
+        args.extend(paths.iter().map(String::as_str));
+        let indexed = self.git(&args).await?;
+        let indexed: Vec<&str> = indexed.lines().collect();
+        let mut args = vec!["ls-tree", "-r", "--name-only", "HEAD", "--"];
+        args.extend(paths.iter().map(String::as_str));
+        let at_head = self.git(&args).await.unwrap_or_default();
+        let at_head: Vec<&str> = at_head.lines().collect();
+
+        let staged: Vec<&str> = paths
+            .iter()
+            .map(String::as_str)
+            .filter(|path| self.work_dir.join(path).exists() || indexed.contains(path))
+            .collect();
+        let paths: Vec<&str> = paths
+            .iter()
+            .map(String::as_str)
+            .filter(|path| staged.contains(path) || at_head.contains(path))
+            .collect();
+        if paths.is_empty() {
+            return Ok(None);
+        }
+
+        if !staged.is_empty() {
+            let mut args = vec!["add", "--all", "--"];
+            args.extend(&staged);
+            self.git(&args).await?;
+        }
+        let mut args = vec!["diff", "--cached", "--quiet", "--"];
+        args.extend(&paths);
+        if self.git(&args).await.is_ok() {
+            return Ok(None);
+        }
+
+        let rendered = message.render();
+        let mut args: Vec<&str> = Vec::new();
+        let has_identity = self.git(&["config", "user.email"]).await.is_ok();
+        let name = format!("user.name={}", FALLBACK_NAME);
+        let email = format!("user.email={}", FALLBACK_EMAIL);
+        if !has_identity {
+            args.extend(["-c", &name, "-c", &email]);
+        }
+        args.extend(["commit", "--quiet", "-m", &rendered, "--"]);
+        args.extend(&paths);
+        self.git(&args).await?;
+        self.git(&["rev-parse", "HEAD"]).await.map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_commits_carry_trailers() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let repo = GitRepo::new(dir.path().to_path_buf());
+        assert!(!repo.is_repo().await);
+        repo.git(&["init", "--quiet"]).await?;
+        assert!(repo.is_repo().await);
+
+        let message = CommitMessage::new("Dev implements Code")
+            .with_trailer("Pulpo-Iteration", "20260101_0001")
+            .with_trailer("Pulpo-Agent", "Dev");
+        assert_eq!(
+            message.render(),
+            "Dev implements Code\n\nPulpo-Iteration: 20260101_0001\nPulpo-Agent: Dev"
+        );
+        let paths = vec!["main.rs".to_string()];
+        assert_eq!(repo.commit_paths(&message, &paths).await?, None);
+
+        repo.switch_branch(&iteration_branch("20260101_0001"))
+            .await?;
+        std::fs::write(dir.path().join("main.rs"), "fn main() {}")?;
+        std::fs::write(dir.path().join("secrets.env"), "TOKEN=1")?;
+        let sha = repo
+            .commit_paths(&message, &paths)
+            .await?
+            .expect("a commit");
+        assert_eq!(repo.current_branch().await?, "pulpo/20260101_0001");
+        assert_eq!(
+            repo.git(&[
+                "log",
+                "-1",
+                "--format=%(trailers:key=Pulpo-Agent,valueonly)%H"
+            ])
+            .await?,
+            format!("Dev\n{}", sha)
+        );
+        // Only the named paths are committed
+        assert_eq!(repo.git(&["ls-files"]).await?, "main.rs");
+        let before = repo.dirty_files().await?;
+        assert_eq!(before.keys().collect::<Vec<_>>(), vec!["secrets.env"]);
+        std::fs::write(dir.path().join("secrets.env"), "TOKEN=2")?;
+        assert_ne!(repo.dirty_files().await?, before);
+
+        std::fs::remove_file(dir.path().join("main.rs"))?;
+        assert!(repo.commit_paths(&message, &paths).await?.is_some());
+        assert_eq!(repo.git(&["ls-files"]).await?, "");
+        Ok(())
+    }
+}
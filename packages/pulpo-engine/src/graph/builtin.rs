@@ -0,0 +1,399 @@
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+/// The software-engineering ontology compiled into the engine, as `(path
+/// under its root, content)`, so that an installed binary runs it with its
+/// prompts, agents and artifact schemas.
+const FILES: &[(&str, &str)] = &[
+    (
+        "ontology.json",
+        include_str!("../../../../pulpo-ontologies/software-engineering/ontology.json"),
+    ),
+    (
+        "ontology.lock",
+        include_str!("../../../../pulpo-ontologies/software-engineering/ontology.lock"),
+    ),
+    (
+        "package.json",
+        include_str!("../../../../pulpo-ontologies/software-engineering/package.json"),
+    ),
+    (
+        "agent/system_prompt/Architect.md",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/agent/system_prompt/Architect.md"
+        ),
+    ),
+    (
+        "agent/system_prompt/BusinessAnalyst.md",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/agent/system_prompt/BusinessAnalyst.md"
+        ),
+    ),
+    (
+        "agent/system_prompt/DevOps.md",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/agent/system_prompt/DevOps.md"
+        ),
+    ),
+    (
+        "agent/system_prompt/Engineer.md",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/agent/system_prompt/Engineer.md"
+        ),
+    ),
+    (
+        "agent/system_prompt/ProductManager.md",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/agent/system_prompt/ProductManager.md"
+        ),
+    ),
+    (
+        "agent/system_prompt/ProjectManager.md",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/agent/system_prompt/ProjectManager.md"
+        ),
+    ),
+    (
+        "agent/system_prompt/QA.md",
+        include_str!("../../../../pulpo-ontologies/software-engineering/agent/system_prompt/QA.md"),
+    ),
+    (
+        "agent/system_prompt/Tester.md",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/agent/system_prompt/Tester.md"
+        ),
+    ),
+    (
+        "artifact/schema/acceptance_criteria.schema.json",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/artifact/schema/acceptance_criteria.schema.json"
+        ),
+    ),
+    (
+        "artifact/schema/architecture_component.schema.json",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/artifact/schema/architecture_component.schema.json"
+        ),
+    ),
+    (
+        "artifact/schema/architecture_pattern.schema.json",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/artifact/schema/architecture_pattern.schema.json"
+        ),
+    ),
+    (
+        "artifact/schema/artifact.schema.json",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/artifact/schema/artifact.schema.json"
+        ),
+    ),
+    (
+        "artifact/schema/change_request.schema.json",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/artifact/schema/change_request.schema.json"
+        ),
+    ),
+    (
+        "artifact/schema/code.schema.json",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/artifact/schema/code.schema.json"
+        ),
+    ),
+    (
+        "artifact/schema/command.schema.json",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/artifact/schema/command.schema.json"
+        ),
+    ),
+    (
+        "artifact/schema/data_model.schema.json",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/artifact/schema/data_model.schema.json"
+        ),
+    ),
+    (
+        "artifact/schema/design_spec.schema.json",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/artifact/schema/design_spec.schema.json"
+        ),
+    ),
+    (
+        "artifact/schema/domain_entity.schema.json",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/artifact/schema/domain_entity.schema.json"
+        ),
+    ),
+    (
+        "artifact/schema/environment.schema.json",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/artifact/schema/environment.schema.json"
+        ),
+    ),
+    (
+        "artifact/schema/feature.schema.json",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/artifact/schema/feature.schema.json"
+        ),
+    ),
+    (
+        "artifact/schema/implementation_plan.schema.json",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/artifact/schema/implementation_plan.schema.json"
+        ),
+    ),
+    (
+        "artifact/schema/observation.schema.json",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/artifact/schema/observation.schema.json"
+        ),
+    ),
+    (
+        "artifact/schema/persona.schema.json",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/artifact/schema/persona.schema.json"
+        ),
+    ),
+    (
+        "artifact/schema/plan.schema.json",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/artifact/schema/plan.schema.json"
+        ),
+    ),
+    (
+        "artifact/schema/project_structure.schema.json",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/artifact/schema/project_structure.schema.json"
+        ),
+    ),
+    (
+        "artifact/schema/requirement.schema.json",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/artifact/schema/requirement.schema.json"
+        ),
+    ),
+    (
+        "artifact/schema/risk.schema.json",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/artifact/schema/risk.schema.json"
+        ),
+    ),
+    (
+        "artifact/schema/software_application.schema.json",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/artifact/schema/software_application.schema.json"
+        ),
+    ),
+    (
+        "artifact/schema/source_file.schema.json",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/artifact/schema/source_file.schema.json"
+        ),
+    ),
+    (
+        "artifact/schema/standard.schema.json",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/artifact/schema/standard.schema.json"
+        ),
+    ),
+    (
+        "artifact/schema/taxonomy.schema.json",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/artifact/schema/taxonomy.schema.json"
+        ),
+    ),
+    (
+        "artifact/schema/technology_stack.schema.json",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/artifact/schema/technology_stack.schema.json"
+        ),
+    ),
+    (
+        "artifact/schema/test_case.schema.json",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/artifact/schema/test_case.schema.json"
+        ),
+    ),
+    (
+        "artifact/schema/test_result.schema.json",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/artifact/schema/test_result.schema.json"
+        ),
+    ),
+    (
+        "artifact/schema/tool.schema.json",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/artifact/schema/tool.schema.json"
+        ),
+    ),
+    (
+        "artifact/schema/unit_test.schema.json",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/artifact/schema/unit_test.schema.json"
+        ),
+    ),
+    (
+        "artifact/schema/user_story.schema.json",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/artifact/schema/user_story.schema.json"
+        ),
+    ),
+    (
+        "relationship/prompt/Architect_creates_DesignSpec.md",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/relationship/prompt/Architect_creates_DesignSpec.md"
+        ),
+    ),
+    (
+        "relationship/prompt/Architect_creates_ProjectStructure.md",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/relationship/prompt/Architect_creates_ProjectStructure.md"
+        ),
+    ),
+    (
+        "relationship/prompt/Architect_defines_ArchitectureStyle.md",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/relationship/prompt/Architect_defines_ArchitectureStyle.md"
+        ),
+    ),
+    (
+        "relationship/prompt/Architect_defines_Microservices.md",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/relationship/prompt/Architect_defines_Microservices.md"
+        ),
+    ),
+    (
+        "relationship/prompt/Engineer_creates_ImplementationPlan.md",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/relationship/prompt/Engineer_creates_ImplementationPlan.md"
+        ),
+    ),
+    (
+        "relationship/prompt/Engineer_defines_TDD.md",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/relationship/prompt/Engineer_defines_TDD.md"
+        ),
+    ),
+    (
+        "relationship/prompt/Engineer_implements_Code.md",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/relationship/prompt/Engineer_implements_Code.md"
+        ),
+    ),
+    (
+        "relationship/prompt/Engineer_verifies_Code.md",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/relationship/prompt/Engineer_verifies_Code.md"
+        ),
+    ),
+    (
+        "relationship/prompt/ProductManager_creates_AcceptanceCriteria.md",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/relationship/prompt/ProductManager_creates_AcceptanceCriteria.md"
+        ),
+    ),
+    (
+        "relationship/prompt/ProductManager_creates_Feature.md",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/relationship/prompt/ProductManager_creates_Feature.md"
+        ),
+    ),
+    (
+        "relationship/prompt/ProductManager_creates_Requirement.md",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/relationship/prompt/ProductManager_creates_Requirement.md"
+        ),
+    ),
+    (
+        "relationship/prompt/ProductManager_creates_UserStory.md",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/relationship/prompt/ProductManager_creates_UserStory.md"
+        ),
+    ),
+    (
+        "relationship/prompt/QA_verifies_Requirement.md",
+        include_str!(
+            "../../../../pulpo-ontologies/software-engineering/relationship/prompt/QA_verifies_Requirement.md"
+        ),
+    ),
+];
+
+/// Relationships of the built-in ontology.
+pub const BUILTIN_ONTOLOGY: &str = FILES[0].1;
+
+/// Root of the built-in ontology, unpacked on first use into
+/// `$XDG_CACHE_HOME/pulpo/ontologies` (else `~/.cache`, else the temp dir),
+/// in a directory named after its content.
+pub fn builtin_ontology_root() -> Result<PathBuf> {
+    let mut hasher = Sha256::new();
+    for (path, content) in FILES {
+        hasher.update(path.as_bytes());
+        hasher.update(content.as_bytes());
+    }
+    let digest: String = hasher.finalize()[..8]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    let root = cache_dir().join(format!("software-engineering-{}", digest));
+    unpack(&root)?;
+    Ok(root)
+}
+
+fn cache_dir() -> PathBuf {
+    std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .unwrap_or_else(std::env::temp_dir)
+        .join("pulpo/ontologies")
+}
+
+/// Writes the files missing from `root`. Each is written aside and renamed,
+/// so that a concurrent run never reads half a file.
+fn unpack(root: &Path) -> Result<()> {
+    for (path, content) in FILES {
+        let target = root.join(path);
+        if target.is_file() {
+            continue;
+        }
+        let dir = target.parent().unwrap_or(root);
+        std::fs::create_dir_all(dir).with_context(|| {
+            format!(
+                "Failed to unpack the built-in ontology to {}",
+                dir.display()
+            )
+        })?;
+        let partial = target.with_extension(format!("partial-{}", std::process::id()));
+        std::fs::write(&partial, content)
+            .and_then(|()| std::fs::rename(&partial, &target))
+            .with_context(|| format!("Failed to unpack {}", target.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_file_of_the_builtin_ontology_is_embedded() {
+        let source = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../../pulpo-ontologies/software-engineering");
+        let mut on_disk = Vec::new();
+        let mut dirs = vec![source.clone()];
+        while let Some(dir) = dirs.pop() {
+            for entry in std::fs::read_dir(dir).unwrap().flatten() {
+                let path = entry.path();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if path.extension().is_none_or(|ext| ext != "ttl") {
+                    let relative = path.strip_prefix(&source).unwrap();
+                    on_disk.push(relative.to_string_lossy().replace('\\', "/"));
+                }
+            }
+        }
+        on_disk.sort();
+        let mut embedded: Vec<String> = FILES.iter().map(|(path, _)| path.to_string()).collect();
+        embedded.sort();
+        assert_eq!(embedded, on_disk);
+    }
+}
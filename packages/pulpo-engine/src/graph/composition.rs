@@ -0,0 +1,301 @@
+use super::{COMMAND_RUNNER, DependencyGraph, MetaRelationship, find_ontology_file};
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+/// Separates the namespace of an imported ontology from the names of its
+/// entities, e.g. `ProjectInit.CodingStyle`.
+pub const NAMESPACE_SEPARATOR: char = '.';
+
+/// Entity type of a node that references another ontology directory.
+pub const ONTOLOGY_TYPE: &str = "Ontology";
+
+fn namespaced(namespace: &str, name: &str) -> String {
+    format!("{}{}{}", namespace, NAMESPACE_SEPARATOR, name)
+}
+
+impl DependencyGraph {
+    /// Whether `name` belongs to an imported ontology.
+    pub fn is_imported(&self, name: &str) -> bool {
+        name.split_once(NAMESPACE_SEPARATOR)
+            .is_some_and(|(namespace, _)| self.imports.contains_key(namespace))
+    }
+
+    /// Loads every ontology referenced by an `Ontology` entity and merges it in
+    /// under the entity's name. Relationships that declare the references are
+    /// consumed; the remaining ones are returned.
+    pub(super) fn load_imports(
+        &mut self,
+        root: &Path,
+        relationships: Vec<MetaRelationship>,
+        loading: &mut Vec<PathBuf>,
+    ) -> Result<Vec<MetaRelationship>> {
+        let mut references = Vec::new();
+        let mut remaining = Vec::new();
+        for rel in relationships {
+            let mut declares = false;
+            for entity in [&rel.source, &rel.target] {
+                if entity.entity_type.as_deref() == Some(ONTOLOGY_TYPE) {
+                    declares = true;
+                    references.push(entity.clone());
+                }
+            }
+            if !declares {
+                remaining.push(rel);
+            }
+        }
+
+        let this = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        loading.push(this);
+        for entity in references {
+            if self.imports.contains_key(&entity.name) {
+                continue;
+            }
+            if entity.name.contains(NAMESPACE_SEPARATOR) {
+                anyhow::bail!(
+                    "Ontology reference '{}' cannot contain '{}'",
+                    entity.name,
+                    NAMESPACE_SEPARATOR
+                );
+            }
+            let path = entity.path.as_deref().with_context(|| {
+                format!(
+                    "Ontology reference '{}' does not declare a path",
+                    entity.name
+                )
+            })?;
+            let dir = root.join(path);
+            let canonical = dir.canonicalize().unwrap_or_else(|_| dir.clone());
+            if loading.contains(&canonical) {
+                anyhow::bail!(
+                    "Ontology '{}' ({}) imports itself",
+                    entity.name,
+                    dir.display()
+                );
+            }
+
+            let file = find_ontology_file(&dir).unwrap_or_else(|| dir.join("ontology.json"));
+            let content = std::fs::read_to_string(&file).with_context(|| {
+                format!(
+                    "Failed to read ontology '{}' from {}",
+                    entity.name,
+                    dir.display()
+                )
+            })?;
+            let imported = Self::load_composed(&content, Some(&dir), loading)
+                .with_context(|| format!("Invalid ontology '{}'", entity.name))?;
+            self.merge_namespaced(&entity.name, imported, dir);
+        }
+        loading.pop();
+
+        // Edges crossing into an imported ontology must name one of its entities
+        for rel in &remaining {
+            for entity in [&rel.source, &rel.target] {
+                if self.is_imported(&entity.name) && !self.kind_map.contains_key(&entity.name) {
+                    anyhow::bail!("Unknown imported entity '{}'", entity.name);
+                }
+            }
+        }
+        Ok(remaining)
+    }
+
+    /// Adds `other` as a subgraph whose entities are prefixed with `namespace`,
+    /// together with its agents, schemas and prompts.
+    fn merge_namespaced(&mut self, namespace: &str, other: DependencyGraph, dir: PathBuf) {
+        // The built-in command runner is shared by all ontologies
+        let ns = |name: &str| {
+            if name == COMMAND_RUNNER {
+                name.to_string()
+            } else {
+                namespaced(namespace, name)
+            }
+        };
+        let ns_key = |(s, r, t): &(String, String, String)| (ns(s), r.clone(), ns(t));
+
+        for node_idx in other.graph.node_indices() {
+            let name = &other.graph[node_idx];
+            self.get_or_create_node(&ns(name));
+            if let Some(schema) = other.find_schema(name) {
+                self.schemas.insert(ns(name), schema.clone());
+            }
+        }
+        for edge in other.graph.edge_indices() {
+            let (s, t) = other.graph.edge_endpoints(edge).unwrap();
+            let (source, target) = (&other.graph[s], &other.graph[t]);
+            let relation = other.graph[edge].clone();
+            let s_idx = self.get_or_create_node(&ns(source));
+            let t_idx = self.get_or_create_node(&ns(target));
+            self.graph.add_edge(s_idx, t_idx, relation.clone());
+
+            // Resolve the imported ontology's prompts now, so that the
+            // defaults of this ontology do not shadow them
+            let key = (source.clone(), relation.clone(), target.clone());
+            if let Some(template) = other
+                .prompt_templates
+                .get(&key)
+                .or_else(|| other.relationship_prompts.get(&relation))
+            {
+                self.prompt_templates.insert(ns_key(&key), template.clone());
+            }
+        }
+
+        // Schemas referenced by URL are shared
+        for (id, content) in other.schemas {
+            if id.starts_with("http") {
+                self.schemas.entry(id).or_insert(content);
+            }
+        }
+        for (role, config) in other.loaded_agents {
+            self.loaded_agents.insert(ns(&role), config);
+        }
+        self.agent_roles
+            .extend(other.agent_roles.iter().map(|role| ns(role)));
+        self.node_types
+            .extend(other.node_types.iter().map(|(k, v)| (ns(k), v.clone())));
+        self.edge_categories
+            .extend(other.edge_categories.iter().map(|(k, v)| (ns_key(k), *v)));
+        self.loop_configs.extend(
+            other
+                .loop_configs
+                .iter()
+                .map(|(k, v)| (ns_key(k), v.clone())),
+        );
+        self.failure_policies
+            .extend(other.failure_policies.iter().map(|(k, v)| (ns_key(k), *v)));
+        self.timeouts
+            .extend(other.timeouts.iter().map(|(k, v)| (ns_key(k), *v)));
+        self.verifiers
+            .extend(other.verifiers.iter().map(|(k, v)| (ns_key(k), v.clone())));
+        self.for_each
+            .extend(other.for_each.iter().map(|(k, v)| (ns(k), ns(v))));
+        self.scopes
+            .extend(other.scopes.iter().map(|(k, v)| (ns(k), *v)));
+        self.node_configs
+            .extend(other.node_configs.iter().map(|(k, config)| {
+                let mut config = config.clone();
+                config.name = ns(k);
+                (ns(k), config)
+            }));
+        self.imports
+            .extend(other.imports.iter().map(|(k, v)| (ns(k), v.clone())));
+        self.imports.insert(namespace.to_string(), dir);
+        self.hooks.extend(other.hooks.into_iter().map(|mut hook| {
+            hook.matcher.entity = hook.matcher.entity.map(|entity| ns(&entity));
+            hook
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::{RelationCategory, Scope};
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn write_ontology(dir: &Path, ontology: serde_json::Value) {
+        fs::create_dir_all(dir.join("agent/system_prompt")).unwrap();
+        fs::write(dir.join("ontology.json"), ontology.to_string()).unwrap();
+    }
+
+    #[test]
+    fn test_ontology_composition() {
+        let tmp = tempdir().unwrap();
+        let init = tmp.path().join("project-init");
+        let feature = tmp.path().join("feature");
+        write_ontology(
+            &init,
+            serde_json::json!([
+                { "source": { "name": "Engineer", "type": "Agent" }, "target": { "name": "CodingStyle", "type": "Other", "scope": "application" }, "type": { "name": "defines", "verbType": "Creation" } }
+            ]),
+        );
+        fs::write(
+            init.join("agent/system_prompt/Engineer.md"),
+            "You set standards.",
+        )
+        .unwrap();
+        fs::create_dir_all(init.join("relationship/prompt")).unwrap();
+        fs::write(
+            init.join("relationship/prompt/defines.md"),
+            "Define the {{target}}.",
+        )
+        .unwrap();
+        write_ontology(
+            &feature,
+            serde_json::json!([
+                { "source": { "name": "SoftwareApplication" }, "target": { "name": "ProjectInit", "type": "Ontology", "path": "../project-init" }, "type": { "name": "imports", "verbType": "Context" } },
+                { "source": { "name": "Engineer", "type": "Agent" }, "target": { "name": "Code", "type": "Other" }, "type": { "name": "implements", "verbType": "Creation" } },
+                { "source": { "name": "Code" }, "target": { "name": "ProjectInit.CodingStyle" }, "type": { "name": "requires", "verbType": "Dependency" } }
+            ]),
+        );
+
+        // The referenced ontology still loads on its own.
+        let standalone = fs::read_to_string(init.join("ontology.json")).unwrap();
+        assert!(DependencyGraph::load_from_metamodel(&standalone, Some(&init)).is_ok());
+
+        let content = fs::read_to_string(feature.join("ontology.json")).unwrap();
+        let graph = DependencyGraph::load_from_metamodel(&content, Some(&feature)).unwrap();
+        assert!(!graph.kind_map.contains_key("ProjectInit"));
+        assert!(graph.kind_map.contains_key("ProjectInit.CodingStyle"));
+        assert!(graph.is_agent("ProjectInit.Engineer"));
+        assert!(graph.is_agent("Engineer"));
+        assert!(graph.is_imported("ProjectInit.CodingStyle"));
+        assert!(!graph.is_imported("Code"));
+        assert_eq!(
+            graph.scope_of("ProjectInit.CodingStyle"),
+            Scope::Application
+        );
+        assert_eq!(
+            graph.edge_categories.get(&(
+                "ProjectInit.Engineer".to_string(),
+                "defines".to_string(),
+                "ProjectInit.CodingStyle".to_string()
+            )),
+            Some(&RelationCategory::Creation)
+        );
+        assert_eq!(
+            graph.get_prompt_template("ProjectInit.Engineer", "defines", "ProjectInit.CodingStyle"),
+            Some("Define the {{target}}.".to_string())
+        );
+        assert!(graph.loaded_agents["ProjectInit.Engineer"].contains("You set standards."));
+        assert_eq!(graph.dependents("ProjectInit.CodingStyle"), vec!["Code"]);
+    }
+
+    #[test]
+    fn test_invalid_compositions_are_rejected() {
+        let tmp = tempdir().unwrap();
+        let a = tmp.path().join("a");
+        let b = tmp.path().join("b");
+        write_ontology(
+            &a,
+            serde_json::json!([
+                { "source": { "name": "SoftwareApplication" }, "target": { "name": "B", "type": "Ontology", "path": "../b" }, "type": { "name": "imports", "verbType": "Context" } }
+            ]),
+        );
+        write_ontology(
+            &b,
+            serde_json::json!([
+                { "source": { "name": "SoftwareApplication" }, "target": { "name": "A", "type": "Ontology", "path": "../a" }, "type": { "name": "imports", "verbType": "Context" } }
+            ]),
+        );
+        let content = fs::read_to_string(a.join("ontology.json")).unwrap();
+        let err = DependencyGraph::load_from_metamodel(&content, Some(&a)).unwrap_err();
+        assert!(format!("{:#}", err).contains("imports itself"));
+
+        write_ontology(
+            &b,
+            serde_json::json!([
+                { "source": { "name": "Engineer", "type": "Agent" }, "target": { "name": "CodingStyle", "type": "Other" }, "type": { "name": "defines", "verbType": "Creation" } }
+            ]),
+        );
+        let crossing = serde_json::json!([
+            { "source": { "name": "SoftwareApplication" }, "target": { "name": "B", "type": "Ontology", "path": "../b" }, "type": { "name": "imports", "verbType": "Context" } },
+            { "source": { "name": "Code" }, "target": { "name": "B.Style" }, "type": { "name": "requires", "verbType": "Dependency" } }
+        ]);
+        let err =
+            DependencyGraph::load_from_metamodel(&crossing.to_string(), Some(&a)).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Unknown imported entity 'B.Style'")
+        );
+    }
+}
@@ -0,0 +1,519 @@
+use super::{
+    COMMAND_RUNNER, DependencyGraph, OntologyFormat, RelationCategory, SyntaxError,
+    find_ontology_file,
+};
+use crate::lock::OntologyLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The ontology cannot be run.
+    Error,
+    /// The ontology runs, but probably not as intended.
+    Warning,
+}
+
+/// Stable identifier of a kind of finding, e.g. `missing-creator`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DiagnosticCode {
+    InvalidJson,
+    InvalidSyntax,
+    SchemaViolation,
+    InvalidImport,
+    InvalidEdge,
+    InvalidInheritance,
+    InvalidForEach,
+    InvalidVerifier,
+    ScopeViolation,
+    MissingCommandLine,
+    MissingCreator,
+    UnreachableNode,
+    UnknownSchema,
+    UnresolvedReference,
+    MissingPromptFile,
+    DuplicateEdge,
+    VerificationWithoutRefinement,
+    InvalidVersion,
+}
+
+impl DiagnosticCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::InvalidJson => "invalid-json",
+            Self::InvalidSyntax => "invalid-syntax",
+            Self::SchemaViolation => "schema-violation",
+            Self::InvalidImport => "invalid-import",
+            Self::InvalidEdge => "invalid-edge",
+            Self::InvalidInheritance => "invalid-inheritance",
+            Self::InvalidForEach => "invalid-for-each",
+            Self::InvalidVerifier => "invalid-verifier",
+            Self::ScopeViolation => "scope-violation",
+            Self::MissingCommandLine => "missing-command-line",
+            Self::MissingCreator => "missing-creator",
+            Self::UnreachableNode => "unreachable-node",
+            Self::UnknownSchema => "unknown-schema",
+            Self::UnresolvedReference => "unresolved-reference",
+            Self::MissingPromptFile => "missing-prompt-file",
+            Self::DuplicateEdge => "duplicate-edge",
+            Self::VerificationWithoutRefinement => "verification-without-refinement",
+            Self::InvalidVersion => "invalid-version",
+        }
+    }
+}
+
+/// A problem found in an ontology, located by a JSON pointer into its
+/// `ontology.json` and, once resolved against the file, a line and column.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: DiagnosticCode,
+    pub message: String,
+    pub pointer: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+}
+
+impl Diagnostic {
+    pub fn error(code: DiagnosticCode, message: impl Into<String>, pointer: &str) -> Self {
+        Self::new(Severity::Error, code, message, pointer)
+    }
+
+    pub fn warning(code: DiagnosticCode, message: impl Into<String>, pointer: &str) -> Self {
+        Self::new(Severity::Warning, code, message, pointer)
+    }
+
+    fn new(
+        severity: Severity,
+        code: DiagnosticCode,
+        message: impl Into<String>,
+        pointer: &str,
+    ) -> Self {
+        Self {
+            severity,
+            code,
+            message: message.into(),
+            pointer: pointer.to_string(),
+            line: None,
+            column: None,
+        }
+    }
+
+    /// Fills in the line and column of the pointer in `content`, an ontology
+    /// in `format`.
+    pub fn locate(&mut self, format: OntologyFormat, content: &str) {
+        if self.line.is_some() {
+            return;
+        }
+        if let Some((line, column)) = format.locate(content, &self.pointer) {
+            self.line = Some(line);
+            self.column = Some(column);
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// One-line rendering prefixed with the file, e.g.
+    /// `ontology.json:12:5: warning[duplicate-edge]: ...`.
+    pub fn render(&self, file: &Path) -> String {
+        let position = match (self.line, self.column) {
+            (Some(line), Some(column)) => format!(":{}:{}", line, column),
+            _ => String::new(),
+        };
+        format!("{}{}: {}", file.display(), position, self)
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}[{}]: {}", severity, self.code.as_str(), self.message)?;
+        if !self.pointer.is_empty() {
+            write!(f, " (at {})", self.pointer)?;
+        }
+        Ok(())
+    }
+}
+
+impl DependencyGraph {
+    /// Loads an ontology and reports everything found wrong with it, instead
+    /// of failing at the first error: the problems that prevent loading it
+    /// plus the findings of [`DependencyGraph::lint`]. Sorted by position.
+    pub fn diagnose(json_content: &str, base_path: Option<&Path>) -> Vec<Diagnostic> {
+        let file = base_path.and_then(find_ontology_file);
+        let format = OntologyFormat::of(file.as_deref(), json_content);
+        let mut diagnostics = match Self::analyze(json_content, base_path, &mut Vec::new()) {
+            Ok(graph) => {
+                let mut diagnostics = graph.diagnostics.clone();
+                if let Some(root) = base_path {
+                    let (_, problems) = OntologyLock::scan(root, &graph.imports);
+                    diagnostics.extend(problems.into_iter().map(|problem| {
+                        Diagnostic::error(DiagnosticCode::InvalidVersion, problem, "")
+                    }));
+                }
+                if !diagnostics.iter().any(Diagnostic::is_error) {
+                    diagnostics.extend(graph.lint());
+                }
+                diagnostics
+            }
+            Err(e) => {
+                if let Some(syntax) = e.downcast_ref::<SyntaxError>() {
+                    let mut diagnostic = Diagnostic::error(
+                        DiagnosticCode::InvalidSyntax,
+                        syntax.message.clone(),
+                        "",
+                    );
+                    diagnostic.line = Some(syntax.line);
+                    diagnostic.column = Some(syntax.column);
+                    return vec![diagnostic];
+                }
+                let mut diagnostic = match e.downcast_ref::<serde_json::Error>() {
+                    Some(json) if json.is_syntax() || json.is_eof() => {
+                        let mut diagnostic =
+                            Diagnostic::error(DiagnosticCode::InvalidJson, json.to_string(), "");
+                        diagnostic.line = Some(json.line());
+                        diagnostic.column = Some(json.column());
+                        diagnostic
+                    }
+                    Some(json) => {
+                        Diagnostic::error(DiagnosticCode::SchemaViolation, json.to_string(), "")
+                    }
+                    None => {
+                        Diagnostic::error(DiagnosticCode::InvalidImport, format!("{:#}", e), "")
+                    }
+                };
+                diagnostic.locate(format, json_content);
+                vec![diagnostic]
+            }
+        };
+        for diagnostic in &mut diagnostics {
+            diagnostic.locate(format, json_content);
+        }
+        diagnostics.sort_by_key(|d| (d.line, d.column, d.severity));
+        diagnostics
+    }
+
+    /// Findings about an ontology that loaded: schemas whose references do
+    /// not resolve, artifacts nothing creates or that can never be produced,
+    /// edges without a prompt, and verifications whose failures nothing can
+    /// fix.
+    pub fn lint(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+
+        let mut nodes: Vec<&String> = self.kind_map.keys().collect();
+        nodes.sort();
+        for node in nodes {
+            if let Some(schema) = self.find_schema(node)
+                && let Err(e) = self.compile_validator(schema)
+            {
+                diagnostics.push(Diagnostic::error(
+                    DiagnosticCode::UnresolvedReference,
+                    format!("The schema of '{}' does not compile: {}", node, e),
+                    self.node_pointer(node),
+                ));
+            }
+        }
+
+        let missing_creators = self.missing_creators();
+        for node in &missing_creators {
+            diagnostics.push(Diagnostic::warning(
+                DiagnosticCode::MissingCreator,
+                format!(
+                    "No Agent creates '{}': add a Creation edge from an Agent",
+                    node
+                ),
+                self.node_pointer(node),
+            ));
+        }
+
+        let mut simulation = crate::planner::Simulation::new();
+        simulation.provide("SoftwareApplication", serde_json::Value::Null);
+        for node in &missing_creators {
+            simulation.provide(node, serde_json::Value::Null);
+        }
+        simulation.predict(self, 100, 1);
+        let mut unreachable: Vec<&String> = self
+            .kind_map
+            .keys()
+            .filter(|node| !self.is_agent(node) && !simulation.artifacts.contains_key(*node))
+            .collect();
+        unreachable.sort();
+        for node in unreachable {
+            diagnostics.push(Diagnostic::warning(
+                DiagnosticCode::UnreachableNode,
+                format!(
+                    "'{}' is never produced: its dependencies can never be met",
+                    node
+                ),
+                self.node_pointer(node),
+            ));
+        }
+
+        let mut edges: Vec<_> = self.edge_categories.iter().collect();
+        edges.sort_by(|a, b| a.0.cmp(b.0));
+        let refined: HashSet<&String> = edges
+            .iter()
+            .filter(|(_, category)| **category == RelationCategory::Refinement)
+            .map(|((_, _, target), _)| target)
+            .collect();
+        for (key, category) in edges {
+            let (source, relation, target) = key;
+            if !category.is_actionable()
+                || !self.is_agent(source)
+                || source == COMMAND_RUNNER
+                || self.verifiers.contains_key(key)
+            {
+                continue;
+            }
+            if !self.prompt_templates.contains_key(key)
+                && !self.relationship_prompts.contains_key(relation)
+            {
+                diagnostics.push(Diagnostic::warning(
+                    DiagnosticCode::MissingPromptFile,
+                    format!(
+                        "No prompt for '{} {} {}': add relationship/prompt/{}_{}_{}.md or relationship/prompt/{}.md",
+                        source, relation, target, source, relation, target, relation
+                    ),
+                    self.edge_pointer(key),
+                ));
+            }
+            if *category == RelationCategory::Verification && !refined.contains(target) {
+                diagnostics.push(Diagnostic::warning(
+                    DiagnosticCode::VerificationWithoutRefinement,
+                    format!(
+                        "'{} {} {}' has no Refinement edge to '{}': a failed verification cannot be fixed",
+                        source, relation, target, target
+                    ),
+                    self.edge_pointer(key),
+                ));
+            }
+        }
+        diagnostics
+    }
+
+    /// Artifacts that no Agent creates. The initial `SoftwareApplication` is
+    /// provided by the user.
+    pub fn missing_creators(&self) -> Vec<String> {
+        let mut created: HashSet<&String> = HashSet::new();
+        for ((source, _, target), category) in &self.edge_categories {
+            if *category == RelationCategory::Creation && self.is_agent(source) {
+                created.insert(target);
+            }
+        }
+        let mut missing: Vec<String> = self
+            .kind_map
+            .keys()
+            .filter(|node| {
+                !self.is_agent(node) && *node != "SoftwareApplication" && !created.contains(node)
+            })
+            .cloned()
+            .collect();
+        missing.sort();
+        missing
+    }
+
+    /// Where an entity first occurs in ontology.json, or the root when it
+    /// comes from elsewhere (an import, the engine).
+    pub(super) fn node_pointer(&self, node: &str) -> &str {
+        self.node_pointers.get(node).map_or("", |p| p.as_str())
+    }
+
+    /// Where an edge is declared in ontology.json, falling back to its target.
+    pub(super) fn edge_pointer(&self, key: &(String, String, String)) -> &str {
+        match self.edge_pointers.get(key) {
+            Some(pointer) => pointer,
+            None => self.node_pointer(&key.2),
+        }
+    }
+}
+
+/// Line and column (both 1-based) where the value at the JSON `pointer`
+/// starts in `json`.
+pub fn locate(json: &str, pointer: &str) -> Option<(usize, usize)> {
+    let bytes = json.as_bytes();
+    let mut pos = skip_whitespace(bytes, 0);
+    let tokens: Vec<String> = match pointer {
+        "" => Vec::new(),
+        _ => pointer
+            .strip_prefix('/')?
+            .split('/')
+            .map(|token| token.replace("~1", "/").replace("~0", "~"))
+            .collect(),
+    };
+
+    for token in tokens {
+        match bytes.get(pos)? {
+            b'{' => {
+                pos += 1;
+                loop {
+                    pos = skip_whitespace(bytes, pos);
+                    let end = skip_value(bytes, pos)?;
+                    let key: String = serde_json::from_slice(&bytes[pos..end]).ok()?;
+                    pos = skip_whitespace(bytes, end);
+                    if bytes.get(pos) != Some(&b':') {
+                        return None;
+                    }
+                    pos = skip_whitespace(bytes, pos + 1);
+                    if key == token {
+                        break;
+                    }
+                    pos = skip_whitespace(bytes, skip_value(bytes, pos)?);
+                    if bytes.get(pos) != Some(&b',') {
+                        return None;
+                    }
+                    pos += 1;
+                }
+            }
+            b'[' => {
+                let index: usize = token.parse().ok()?;
+                pos = skip_whitespace(bytes, pos + 1);
+                for _ in 0..index {
+                    pos = skip_whitespace(bytes, skip_value(bytes, pos)?);
+                    if bytes.get(pos) != Some(&b',') {
+                        return None;
+                    }
+                    pos = skip_whitespace(bytes, pos + 1);
+                }
+                if bytes.get(pos) == Some(&b']') {
+                    return None;
+                }
+            }
+            _ => return None,
+        }
+    }
+
+    let before = &json[..pos];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    Some((line, json[line_start..pos].chars().count() + 1))
+}
+
+fn skip_whitespace(bytes: &[u8], mut pos: usize) -> usize {
+    while bytes.get(pos).is_some_and(|b| b.is_ascii_whitespace()) {
+        pos += 1;
+    }
+    pos
+}
+
+/// Position right after the value starting at `pos`.
+fn skip_value(bytes: &[u8], mut pos: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    loop {
+        let byte = *bytes.get(pos)?;
+        pos += 1;
+        if in_string {
+            match byte {
+                b'\\' => pos += 1,
+                b'"' => {
+                    in_string = false;
+                    if depth == 0 {
+                        return Some(pos);
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'"' => in_string = true,
+            b'{' | b'[' => depth += 1,
+            b'}' | b']' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return Some(pos);
+                }
+            }
+            _ if depth == 0 => {
+                // A literal ends where the enclosing container continues
+                while bytes
+                    .get(pos)
+                    .is_some_and(|b| !matches!(b, b',' | b'}' | b']') && !b.is_ascii_whitespace())
+                {
+                    pos += 1;
+                }
+                return Some(pos);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pointers_resolve_to_lines_and_columns() {
+        let json = "[\n  {\"a\": 1, \"b/c\": [true, {\"d\": \"x\\\"y\"}]},\n  {\"e\": null}\n]";
+        assert_eq!(locate(json, ""), Some((1, 1)));
+        assert_eq!(locate(json, "/0"), Some((2, 3)));
+        assert_eq!(locate(json, "/0/b~1c/1/d"), Some((2, 32)));
+        assert_eq!(locate(json, "/1/e"), Some((3, 9)));
+        assert_eq!(locate(json, "/2"), None);
+        assert_eq!(locate(json, "/0/z"), None);
+    }
+
+    #[test]
+    fn test_diagnose_reports_every_finding_with_its_location() {
+        let edges = r#"
+    { "source": { "name": "Dev", "type": "Agent" }, "target": { "name": "Code", "type": "Other" }, "type": { "name": "implements", "verbType": "Creation" } },
+    { "source": { "name": "Dev", "type": "Agent" }, "target": { "name": "Code", "type": "Other" }, "type": { "name": "verifies", "verbType": "Verification" } },
+    { "source": { "name": "Dev", "type": "Agent" }, "target": { "name": "Code", "type": "Other" }, "type": { "name": "implements", "verbType": "Creation" } },
+    { "source": { "name": "Code", "type": "Other" }, "target": { "name": "Spec", "type": "Other" }, "type": { "name": "dependsOn", "verbType": "Dependency" } }"#;
+        let found = |json: &str| -> Vec<(Severity, &'static str, usize)> {
+            DependencyGraph::diagnose(json, None)
+                .iter()
+                .map(|d| (d.severity, d.code.as_str(), d.line.unwrap_or(0)))
+                .collect()
+        };
+
+        assert_eq!(
+            found(&format!("[{}\n]", edges)),
+            vec![
+                (Severity::Warning, "missing-prompt-file", 2),
+                (Severity::Warning, "missing-prompt-file", 3),
+                (Severity::Warning, "verification-without-refinement", 3),
+                (Severity::Warning, "duplicate-edge", 4),
+                (Severity::Warning, "missing-creator", 5),
+            ]
+        );
+
+        // Errors are reported without the findings that assume a valid ontology
+        let agent_verifies_agent = r#"{ "source": { "name": "Dev", "type": "Agent" }, "target": { "name": "Dev", "type": "Agent" }, "type": { "name": "verifies", "verbType": "Verification" } }"#;
+        let json = format!("[{},\n    {}\n]", edges, agent_verifies_agent);
+        assert_eq!(
+            found(&json),
+            vec![
+                (Severity::Warning, "duplicate-edge", 4),
+                (Severity::Error, "invalid-edge", 6),
+            ]
+        );
+        let err = DependencyGraph::load_from_metamodel(&json, None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Agent 'Dev' cannot 'verify' another Agent 'Dev'"
+        );
+
+        let diagnostics = DependencyGraph::diagnose("[\n  {\"source\": }\n]", None);
+        assert_eq!(diagnostics[0].code, DiagnosticCode::InvalidJson);
+        assert_eq!(
+            (diagnostics[0].line, diagnostics[0].column),
+            (Some(2), Some(14))
+        );
+        assert_eq!(
+            diagnostics[0].render(Path::new("ontology.json")),
+            format!("ontology.json:2:14: {}", diagnostics[0])
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use crate::agents::Agent;
 use crate::domain::types::AgentRole;
 use crate::graph::DependencyGraph;
+use crate::usage::UsageSlot;
 use anyhow::Result;
 use async_trait::async_trait;
 use petgraph::graph::NodeIndex;
@@ -13,6 +14,12 @@ pub struct ExecutionOptions {
     pub model_type: Option<String>,
     pub model: Option<String>,
     pub ai_cli: Option<String>,
+    /// Kill the AI CLI if the whole action takes longer than this.
+    pub timeout_secs: Option<u64>,
+    /// Kill the AI CLI if it writes nothing to stdout/stderr for this long.
+    pub stall_timeout_secs: Option<u64>,
+    /// Filled by the AI client with the token counts it observed, if any.
+    pub usage: UsageSlot,
 }
 
 #[derive(Debug, Clone)]
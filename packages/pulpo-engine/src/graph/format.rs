@@ -0,0 +1,612 @@
+use anyhow::Result;
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+
+/// File names an ontology directory may define its relationships in, in the
+/// order they are looked for.
+pub const ONTOLOGY_FILES: &[&str] = &[
+    "ontology.json",
+    "ontology.yaml",
+    "ontology.yml",
+    "ontology.triples",
+];
+
+/// The ontology file of `dir`, if it has one.
+pub fn find_ontology_file(dir: &Path) -> Option<PathBuf> {
+    ONTOLOGY_FILES
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Syntax in which an ontology's relationships are written. All of them are
+/// read into the JSON model of `ontology.json`, so that the meta-schema and
+/// `MetaRelationship` apply unchanged.
+///
+/// The triple syntax has one relationship per line; `#` starts a comment:
+///
+/// ```text
+/// Architect[Agent] creates(Creation) DesignSpec[Document] loop(maxRetries=3)
+/// Tests[Command, command="cargo test"] verifies(Verification) Code[Code]
+/// ```
+///
+/// An entity is `Name` or `Name[Type, key=value, ...]`, the relation is
+/// `name(verbType)`, and each trailing `key(...)` sets the relationship's
+/// `key` to a value or, from `k=v` pairs, an object (`score.from=json` nests).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OntologyFormat {
+    Json,
+    Yaml,
+    Triples,
+}
+
+/// Error at a position of an ontology file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl std::fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} at line {} column {}",
+            self.message, self.line, self.column
+        )
+    }
+}
+
+impl std::error::Error for SyntaxError {}
+
+impl OntologyFormat {
+    /// The format of a file, by its extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "json" => Some(Self::Json),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "triples" => Some(Self::Triples),
+            _ => None,
+        }
+    }
+
+    /// The format of `content`, read from the file `path`: by the file's
+    /// extension when it has a known one, else guessed from the content.
+    pub fn of(path: Option<&Path>, content: &str) -> Self {
+        path.and_then(Self::from_path)
+            .unwrap_or_else(|| Self::detect(content))
+    }
+
+    /// Guesses the format of `content` from its first significant line: JSON
+    /// opens an array, YAML a sequence item, anything else is triples.
+    pub fn detect(content: &str) -> Self {
+        let first = content
+            .lines()
+            .map(str::trim_start)
+            .find(|line| !line.is_empty() && !line.starts_with('#'));
+        match first {
+            None => Self::Json,
+            Some(line) if line.starts_with('[') || line.starts_with('{') => Self::Json,
+            Some(line) if line.starts_with('-') => Self::Yaml,
+            Some(_) => Self::Triples,
+        }
+    }
+
+    /// Reads `content` into the JSON model of `ontology.json`.
+    pub fn parse(self, content: &str) -> Result<Value> {
+        match self {
+            Self::Json => Ok(serde_json::from_str(content)?),
+            Self::Yaml => serde_yaml::from_str(content).map_err(|e| {
+                let (line, column) = e
+                    .location()
+                    .map_or((0, 0), |location| (location.line(), location.column()));
+                SyntaxError {
+                    message: e.to_string(),
+                    line,
+                    column,
+                }
+                .into()
+            }),
+            Self::Triples => {
+                let mut relationships = Vec::new();
+                for (index, line) in content.lines().enumerate() {
+                    if let Some(relationship) =
+                        parse_statement(line).map_err(|(message, at)| SyntaxError {
+                            message,
+                            line: index + 1,
+                            column: at + 1,
+                        })?
+                    {
+                        relationships.push(relationship);
+                    }
+                }
+                Ok(Value::Array(relationships))
+            }
+        }
+    }
+
+    /// Writes relationships in the JSON model of `ontology.json` in this format.
+    pub fn render(self, relationships: &Value) -> Result<String> {
+        match self {
+            Self::Json => Ok(serde_json::to_string_pretty(relationships)?),
+            Self::Yaml => Ok(serde_yaml::to_string(relationships)?),
+            Self::Triples => {
+                let items = relationships
+                    .as_array()
+                    .ok_or_else(|| anyhow::anyhow!("An ontology is an array of relationships"))?;
+                let mut out = String::new();
+                for item in items {
+                    out.push_str(&render_statement(item)?);
+                    out.push('\n');
+                }
+                Ok(out)
+            }
+        }
+    }
+
+    /// Line and column of the relationship a JSON pointer points into. JSON
+    /// is located down to the pointed value; the other formats to the line
+    /// of the relationship.
+    pub fn locate(self, content: &str, pointer: &str) -> Option<(usize, usize)> {
+        let index: usize = match self {
+            Self::Json => return super::locate(content, pointer),
+            _ => pointer.strip_prefix('/')?.split('/').next()?.parse().ok()?,
+        };
+        let significant = |line: &&str| {
+            let line = line.trim_start();
+            !line.is_empty() && !line.starts_with('#') && !line.starts_with("---")
+        };
+        let indent = |line: &str| line.len() - line.trim_start().len();
+        // Relationships are the items of the outermost sequence
+        let depth = match self {
+            Self::Yaml => content.lines().find(significant).map(indent)?,
+            _ => 0,
+        };
+        let starts = content.lines().enumerate().filter(|(_, line)| match self {
+            Self::Yaml => {
+                let item = line.trim_start();
+                indent(line) == depth && (item == "-" || item.starts_with("- "))
+            }
+            _ => significant(line),
+        });
+        starts
+            .map(|(number, line)| (number + 1, indent(line) + 1))
+            .nth(index)
+    }
+}
+
+// A statement that failed to parse: the message and the offending character
+type StatementError = (String, usize);
+
+/// Splits a statement into its terms at the whitespace outside brackets and
+/// quotes, with their offsets.
+fn terms(line: &str) -> Result<Vec<(usize, &str)>, StatementError> {
+    let mut terms = Vec::new();
+    let mut start = None;
+    let mut depth = 0usize;
+    let mut quoted = false;
+    let mut escaped = false;
+    let mut end = line.len();
+    for (at, c) in line.char_indices() {
+        if quoted {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => quoted = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '#' if depth == 0 => {
+                end = at;
+                break;
+            }
+            c if c.is_whitespace() && depth == 0 => {
+                if let Some(begin) = start.take() {
+                    terms.push((begin, &line[begin..at]));
+                }
+                continue;
+            }
+            '"' => quoted = true,
+            '[' | '(' => depth += 1,
+            ']' | ')' => {
+                depth = depth
+                    .checked_sub(1)
+                    .ok_or_else(|| (format!("Unbalanced '{}'", c), at))?;
+            }
+            _ => {}
+        }
+        start.get_or_insert(at);
+    }
+    if quoted {
+        return Err(("Unterminated string".to_string(), line.len()));
+    }
+    if depth > 0 {
+        return Err(("Unclosed bracket".to_string(), line.len()));
+    }
+    if let Some(begin) = start {
+        terms.push((begin, &line[begin..end]));
+    }
+    Ok(terms)
+}
+
+/// Splits `name<open>args<close>` into the name and the arguments.
+fn call(term: &str, open: char, close: char) -> Option<(&str, &str)> {
+    let (name, rest) = term.split_once(open)?;
+    Some((name, rest.strip_suffix(close)?))
+}
+
+/// Splits arguments at the commas outside quotes.
+fn arguments(args: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut quoted = false;
+    let mut escaped = false;
+    let mut begin = 0;
+    for (at, c) in args.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                parts.push(args[begin..at].trim());
+                begin = at + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(args[begin..].trim());
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+
+/// A quoted string, or a bare word read as a boolean, a number or a string.
+fn scalar(text: &str) -> Value {
+    if let Some(inner) = text.strip_prefix('"').and_then(|t| t.strip_suffix('"')) {
+        return Value::String(inner.replace("\\\"", "\"").replace("\\\\", "\\"));
+    }
+    match text {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => serde_json::from_str::<serde_json::Number>(text)
+            .map(Value::Number)
+            .unwrap_or_else(|_| Value::String(text.to_string())),
+    }
+}
+
+/// Sets `key` in `object`; a dotted key sets a nested object.
+fn assign(object: &mut Map<String, Value>, key: &str, value: Value) {
+    match key.split_once('.') {
+        Some((head, rest)) => {
+            let nested = object
+                .entry(head.to_string())
+                .or_insert_with(|| Value::Object(Map::new()));
+            if let Value::Object(nested) = nested {
+                assign(nested, rest, value);
+            }
+        }
+        None => {
+            object.insert(key.to_string(), value);
+        }
+    }
+}
+
+fn pairs(args: &[&str], at: usize) -> Result<Map<String, Value>, StatementError> {
+    let mut object = Map::new();
+    for arg in args {
+        let (key, value) = arg
+            .split_once('=')
+            .ok_or_else(|| (format!("Expected key=value, found '{}'", arg), at))?;
+        assign(&mut object, key.trim(), scalar(value.trim()));
+    }
+    Ok(object)
+}
+
+fn parse_entity(term: &str, at: usize) -> Result<Value, StatementError> {
+    let Some((name, attributes)) = call(term, '[', ']') else {
+        if term.contains(['[', ']', '(', ')']) {
+            return Err((format!("Expected an entity, found '{}'", term), at));
+        }
+        return Ok(serde_json::json!({ "name": term }));
+    };
+    let mut args = arguments(attributes);
+    let mut entity = Map::new();
+    entity.insert("name".to_string(), Value::String(name.to_string()));
+    if let Some(first) = args.first()
+        && !first.contains('=')
+    {
+        entity.insert("type".to_string(), scalar(first));
+        args.remove(0);
+    }
+    entity.extend(pairs(&args, at)?);
+    Ok(Value::Object(entity))
+}
+
+/// Reads one line of the triple syntax; `None` for blank and comment lines.
+fn parse_statement(line: &str) -> Result<Option<Value>, StatementError> {
+    let terms = terms(line)?;
+    if terms.is_empty() {
+        return Ok(None);
+    }
+    if terms.len() < 3 {
+        return Err((
+            "Expected 'Source relation(VerbType) Target'".to_string(),
+            terms[0].0,
+        ));
+    }
+    let (source_at, source) = terms[0];
+    let (verb_at, verb) = terms[1];
+    let (target_at, target) = terms[2];
+    let (name, verb_type) = call(verb, '(', ')')
+        .filter(|(name, verb_type)| !name.is_empty() && !verb_type.trim().is_empty())
+        .ok_or_else(|| {
+            (
+                format!("Expected 'relation(VerbType)', found '{}'", verb),
+                verb_at,
+            )
+        })?;
+
+    let mut relationship = Map::new();
+    relationship.insert("source".to_string(), parse_entity(source, source_at)?);
+    relationship.insert("target".to_string(), parse_entity(target, target_at)?);
+    relationship.insert(
+        "type".to_string(),
+        serde_json::json!({ "name": name, "verbType": verb_type.trim() }),
+    );
+    for &(at, clause) in &terms[3..] {
+        let (key, args) = call(clause, '(', ')')
+            .ok_or_else(|| (format!("Expected 'key(...)', found '{}'", clause), at))?;
+        let args = arguments(args);
+        let value = match args.as_slice() {
+            [single] if !single.contains('=') => scalar(single),
+            _ => Value::Object(pairs(&args, at)?),
+        };
+        relationship.insert(key.to_string(), value);
+    }
+    Ok(Some(Value::Object(relationship)))
+}
+
+/// A value as a bare word when it reads back as the same value, quoted
+/// otherwise.
+fn render_scalar(value: &Value) -> Result<String> {
+    match value {
+        Value::String(s) => {
+            let bare = !s.is_empty()
+                && !s.contains(|c: char| c.is_whitespace() || "\"#,=()[]".contains(c))
+                && scalar(s) == *value;
+            if bare {
+                Ok(s.clone())
+            } else {
+                Ok(format!(
+                    "\"{}\"",
+                    s.replace('\\', "\\\\").replace('"', "\\\"")
+                ))
+            }
+        }
+        Value::Bool(_) | Value::Number(_) => Ok(value.to_string()),
+        _ => anyhow::bail!("Cannot write {} in the triple syntax", value),
+    }
+}
+
+/// `k=v` pairs of an object, nested objects as dotted keys.
+fn render_pairs(prefix: &str, object: &Map<String, Value>, out: &mut Vec<String>) -> Result<()> {
+    for (key, value) in object {
+        let key = format!("{}{}", prefix, key);
+        match value {
+            Value::Null => {}
+            Value::Object(nested) => render_pairs(&format!("{}.", key), nested, out)?,
+            _ => out.push(format!("{}={}", key, render_scalar(value)?)),
+        }
+    }
+    Ok(())
+}
+
+fn render_entity(entity: &Value) -> Result<String> {
+    let object = entity
+        .as_object()
+        .ok_or_else(|| anyhow::anyhow!("An entity is an object, found {}", entity))?;
+    let name = object
+        .get("name")
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow::anyhow!("Entity without a name: {}", entity))?;
+    let mut attributes = Vec::new();
+    if let Some(entity_type) = object.get("type").filter(|t| !t.is_null()) {
+        attributes.push(render_scalar(entity_type)?);
+    }
+    let rest: Map<String, Value> = object
+        .iter()
+        .filter(|(key, _)| *key != "name" && *key != "type")
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    render_pairs("", &rest, &mut attributes)?;
+    if attributes.is_empty() {
+        Ok(name.to_string())
+    } else {
+        Ok(format!("{}[{}]", name, attributes.join(", ")))
+    }
+}
+
+fn render_statement(relationship: &Value) -> Result<String> {
+    let object = relationship
+        .as_object()
+        .ok_or_else(|| anyhow::anyhow!("A relationship is an object, found {}", relationship))?;
+    let field = |key: &str| {
+        object
+            .get(key)
+            .ok_or_else(|| anyhow::anyhow!("Relationship without '{}': {}", key, relationship))
+    };
+    let verb = field("type")?;
+    let verb = format!(
+        "{}({})",
+        verb["name"].as_str().unwrap_or_default(),
+        verb["verbType"].as_str().unwrap_or_default()
+    );
+    let mut terms = vec![
+        render_entity(field("source")?)?,
+        verb,
+        render_entity(field("target")?)?,
+    ];
+    for (key, value) in object {
+        if matches!(key.as_str(), "source" | "target" | "type") {
+            continue;
+        }
+        match value {
+            Value::Null => {}
+            Value::Object(nested) => {
+                let mut args = Vec::new();
+                render_pairs("", nested, &mut args)?;
+                terms.push(format!("{}({})", key, args.join(", ")));
+            }
+            _ => terms.push(format!("{}({})", key, render_scalar(value)?)),
+        }
+    }
+    Ok(terms.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRIPLES: &str = r#"
+# The architect designs, the tests verify
+Architect[Agent, model=gemini-2.5-pro] creates(Creation) DesignSpec[Document] loop(maxRetries=3, passThreshold=0.8)
+Tests[Command, command="cargo test -- --nocapture"] verifies(Verification) Code  # scored below
+Feature[Document] contains(Context) Task forEach(Feature) verifier(command="cargo tarpaulin", score.from=metric, score.label="coverage:", scale=100)
+"#;
+
+    #[test]
+    fn test_triples_read_into_the_json_model() {
+        assert_eq!(OntologyFormat::detect(TRIPLES), OntologyFormat::Triples);
+        let value = OntologyFormat::Triples.parse(TRIPLES).unwrap();
+        assert_eq!(
+            value[0],
+            serde_json::json!({
+                "source": {"name": "Architect", "type": "Agent", "model": "gemini-2.5-pro"},
+                "type": {"name": "creates", "verbType": "Creation"},
+                "target": {"name": "DesignSpec", "type": "Document"},
+                "loop": {"maxRetries": 3, "passThreshold": 0.8}
+            })
+        );
+        assert_eq!(value[1]["source"]["command"], "cargo test -- --nocapture");
+        assert_eq!(value[1]["target"], serde_json::json!({"name": "Code"}));
+        assert_eq!(value[2]["forEach"], "Feature");
+        assert_eq!(
+            value[2]["verifier"],
+            serde_json::json!({
+                "command": "cargo tarpaulin",
+                "score": {"from": "metric", "label": "coverage:"},
+                "scale": 100
+            })
+        );
+        assert_eq!(
+            OntologyFormat::Triples.locate(TRIPLES, "/1/target"),
+            Some((4, 1))
+        );
+
+        let err = OntologyFormat::Triples
+            .parse("Architect creates DesignSpec")
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<SyntaxError>().unwrap().to_string(),
+            "Expected 'relation(VerbType)', found 'creates' at line 1 column 11"
+        );
+    }
+
+    #[test]
+    fn test_every_format_round_trips() {
+        let value = OntologyFormat::Triples.parse(TRIPLES).unwrap();
+        for format in [
+            OntologyFormat::Json,
+            OntologyFormat::Yaml,
+            OntologyFormat::Triples,
+        ] {
+            let rendered = format.render(&value).unwrap();
+            assert_eq!(OntologyFormat::detect(&rendered), format);
+            assert_eq!(format.parse(&rendered).unwrap(), value, "{:?}", format);
+        }
+        let yaml = OntologyFormat::Yaml.render(&value).unwrap();
+        assert_eq!(
+            OntologyFormat::Yaml
+                .locate(&yaml, "/2/forEach")
+                .map(|(_, c)| c),
+            Some(1)
+        );
+        assert_eq!(
+            OntologyFormat::from_path(Path::new("ontology.yml")),
+            Some(OntologyFormat::Yaml)
+        );
+
+        // Items of an indented sequence are located, not the nested ones
+        let indented = "# Relationships\n  - source: {name: A, type: Agent}\n    type: {name: creates, verbType: Creation}\n    tags:\n    - draft\n    target: {name: B}\n  - source: {name: C, type: Agent}\n";
+        assert_eq!(
+            OntologyFormat::Yaml.locate(indented, "/1/source"),
+            Some((7, 3))
+        );
+        assert_eq!(OntologyFormat::Yaml.locate(indented, "/2"), None);
+    }
+
+    #[test]
+    fn test_the_file_extension_decides_the_format() {
+        let path = Path::new("ontology.triples");
+        assert_eq!(OntologyFormat::detect(""), OntologyFormat::Json);
+        assert_eq!(
+            OntologyFormat::of(Some(path), "# Nothing yet\n"),
+            OntologyFormat::Triples
+        );
+        assert_eq!(
+            OntologyFormat::of(Some(Path::new("ontology")), "- a"),
+            OntologyFormat::Yaml
+        );
+
+        // An empty triples file is an empty ontology, not broken JSON
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("ontology.triples"), "").unwrap();
+        let diagnostics = crate::graph::DependencyGraph::diagnose("", Some(dir.path()));
+        assert!(
+            diagnostics
+                .iter()
+                .all(|d| d.code != crate::graph::DiagnosticCode::InvalidJson),
+            "{:?}",
+            diagnostics
+        );
+    }
+
+    #[test]
+    fn test_every_format_loads_the_same_graph() {
+        let json = r#"[
+            { "source": { "name": "Architect", "type": "Agent" }, "target": { "name": "Design", "type": "Document" }, "type": { "name": "creates", "verbType": "Creation" } },
+            { "source": { "name": "Reviewer", "type": "Agent" }, "target": { "name": "Design", "type": "Document" }, "type": { "name": "verifies", "verbType": "Verification" }, "loop": { "maxRetries": 2 } }
+        ]"#;
+        let triples = "Architect[Agent] creates(Creation) Design[Document]\nReviewer[Agent] verifies(Verification) Design[Document] loop(maxRetries=2)\n";
+        let yaml = OntologyFormat::Yaml
+            .render(&serde_json::from_str(json).unwrap())
+            .unwrap();
+        let expected = crate::graph::DependencyGraph::load_from_metamodel(json, None).unwrap();
+        for content in [triples, yaml.as_str()] {
+            let graph = crate::graph::DependencyGraph::load_from_metamodel(content, None).unwrap();
+            assert_eq!(graph.kind_map.len(), expected.kind_map.len());
+            assert_eq!(graph.edge_categories, expected.edge_categories);
+            assert_eq!(graph.loop_configs.len(), 1);
+        }
+
+        // Both are checked against the meta-schema, and located by line
+        let invalid = "# Design\nArchitect[Agent] creates(Creation) Design\nReviewer[Agent] checks(Checking) Design\n";
+        let diagnostics = crate::graph::DependencyGraph::diagnose(invalid, None);
+        assert_eq!(
+            diagnostics[0].code,
+            crate::graph::DiagnosticCode::SchemaViolation
+        );
+        assert_eq!(diagnostics[0].line, Some(3));
+        let diagnostics =
+            crate::graph::DependencyGraph::diagnose("A[Agent] creates(Creation", None);
+        assert_eq!(
+            diagnostics[0].code,
+            crate::graph::DiagnosticCode::InvalidSyntax
+        );
+        assert_eq!(
+            (diagnostics[0].line, diagnostics[0].column),
+            (Some(1), Some(26))
+        );
+    }
+}
@@ -0,0 +1,262 @@
+use super::{DependencyGraph, MetaEntity, RelationCategory};
+use anyhow::Result;
+use petgraph::visit::EdgeRef;
+
+type Getter = fn(&MetaEntity) -> &Option<String>;
+type Setter = fn(&mut MetaEntity) -> &mut Option<String>;
+
+// Agent settings that are inherited unless the agent overrides them
+const SETTINGS: [(&str, Getter, Setter); 3] = [
+    ("model", |c| &c.model, |c| &mut c.model),
+    ("modelType", |c| &c.model_type, |c| &mut c.model_type),
+    ("aiCli", |c| &c.ai_cli, |c| &mut c.ai_cli),
+];
+
+impl DependencyGraph {
+    /// Agents `agent` inherits from, directly or not, nearest first.
+    pub fn ancestors(&self, agent: &str) -> Vec<String> {
+        let mut ancestors: Vec<String> = Vec::new();
+        let mut pending: Vec<&String> = self
+            .inherits
+            .get(agent)
+            .map(|parents| parents.iter().rev().collect())
+            .unwrap_or_default();
+        while let Some(parent) = pending.pop() {
+            if parent == agent || ancestors.contains(parent) {
+                continue;
+            }
+            ancestors.push(parent.clone());
+            if let Some(grandparents) = self.inherits.get(parent) {
+                pending.extend(grandparents.iter().rev());
+            }
+        }
+        ancestors
+    }
+
+    /// System prompt of an agent, built from its whole inheritance chain: the
+    /// most general ancestor first, the agent's own prompt last.
+    pub fn system_prompt(&self, agent: &str) -> String {
+        let own_prompt = |role: &str| {
+            self.loaded_agents
+                .get(role)
+                .and_then(|config| serde_json::from_str::<serde_json::Value>(config).ok())
+                .and_then(|v| v.get("system_prompt")?.as_str().map(str::to_string))
+                .filter(|prompt| !prompt.trim().is_empty())
+        };
+        let mut chain = self.ancestors(agent);
+        chain.reverse();
+        chain.push(agent.to_string());
+        chain
+            .iter()
+            .filter_map(|role| own_prompt(role))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    /// Rejects inheritance cycles and agents that inherit different values of
+    /// the same setting from different parents without overriding it.
+    pub(super) fn validate_inheritance(&self) -> Result<()> {
+        let mut agents: Vec<&String> = self.inherits.keys().collect();
+        agents.sort();
+
+        for agent in &agents {
+            let mut path = vec![agent.to_string()];
+            self.find_cycle(&mut path)?;
+        }
+
+        for agent in agents {
+            for (name, get, _) in SETTINGS {
+                self.inherited_setting(agent, name, get)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn find_cycle(&self, path: &mut Vec<String>) -> Result<()> {
+        let current = path.last().cloned().unwrap_or_default();
+        for parent in self.inherits.get(&current).into_iter().flatten() {
+            if path.contains(parent) {
+                path.push(parent.clone());
+                anyhow::bail!("Inheritance cycle: {}", path.join(" -> "));
+            }
+            path.push(parent.clone());
+            self.find_cycle(path)?;
+            path.pop();
+        }
+        Ok(())
+    }
+
+    /// Value of a setting for `agent`: its own, else the one its parents
+    /// agree on.
+    fn inherited_setting(&self, agent: &str, name: &str, get: Getter) -> Result<Option<String>> {
+        if let Some(own) = self.node_configs.get(agent).and_then(|c| get(c).clone()) {
+            return Ok(Some(own));
+        }
+        let mut found: Option<(String, &String)> = None;
+        for parent in self.inherits.get(agent).into_iter().flatten() {
+            let Some(value) = self.inherited_setting(parent, name, get)? else {
+                continue;
+            };
+            match found {
+                Some((ref existing, from)) if *existing != value => anyhow::bail!(
+                    "Agent '{}' inherits conflicting {} values: '{}' from '{}' and '{}' from '{}'. Override it on '{}'",
+                    agent,
+                    name,
+                    existing,
+                    from,
+                    value,
+                    parent,
+                    agent
+                ),
+                Some(_) => {}
+                None => found = Some((value, parent)),
+            }
+        }
+        Ok(found.map(|(value, _)| value))
+    }
+
+    /// Gives every inheriting agent the relationships and default settings of
+    /// its ancestors that it does not declare itself. Creation, Refinement and
+    /// Verification edges stay with the ancestor, so every target keeps a
+    /// single producer. Must run after `validate_inheritance`.
+    pub(super) fn apply_inheritance(&mut self) {
+        let mut agents: Vec<String> = self.inherits.keys().cloned().collect();
+        agents.sort();
+
+        let mut settings = Vec::new();
+        let mut edges = Vec::new();
+        for agent in &agents {
+            for (name, get, set) in SETTINGS {
+                if let Ok(Some(value)) = self.inherited_setting(agent, name, get) {
+                    settings.push((agent.clone(), set, value));
+                }
+            }
+
+            for ancestor in self.ancestors(agent) {
+                let Some(&ancestor_idx) = self.kind_map.get(&ancestor) else {
+                    continue;
+                };
+                for edge in self.graph.edges(ancestor_idx) {
+                    let target = self.graph[edge.target()].clone();
+                    let relation = edge.weight().clone();
+                    let from = (ancestor.clone(), relation.clone(), target.clone());
+                    let to = (agent.clone(), relation, target);
+                    if matches!(
+                        self.edge_categories.get(&from),
+                        Some(
+                            RelationCategory::Inheritance
+                                | RelationCategory::Creation
+                                | RelationCategory::Refinement
+                                | RelationCategory::Verification
+                        )
+                    ) || self.edge_categories.contains_key(&to)
+                        || edges.iter().any(|(_, existing)| *existing == to)
+                    {
+                        continue;
+                    }
+                    edges.push((from, to));
+                }
+            }
+        }
+
+        for (agent, set, value) in settings {
+            let config = self
+                .node_configs
+                .entry(agent.clone())
+                .or_insert_with(|| MetaEntity {
+                    name: agent,
+                    entity_type: Some("Agent".to_string()),
+                    model_type: None,
+                    model: None,
+                    ai_cli: None,
+                    scope: None,
+                    path: None,
+                    command: None,
+                });
+            *set(config) = Some(value);
+        }
+
+        for (from, to) in edges {
+            let s_idx = self.get_or_create_node(&to.0);
+            let t_idx = self.get_or_create_node(&to.2);
+            self.graph.add_edge(s_idx, t_idx, to.1.clone());
+            if let Some(category) = self.edge_categories.get(&from).copied() {
+                self.edge_categories.insert(to, category);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_agents_inherit_prompts_relationships_and_settings() {
+        let json = r#"[
+            { "source": { "name": "Architect", "type": "Agent", "modelType": "High Reasoning", "aiCli": "gemini" }, "target": { "name": "DesignSpec", "type": "Other" }, "type": { "name": "creates", "verbType": "Creation" }, "onFailure": { "action": "skip" } },
+            { "source": { "name": "Architect", "type": "Agent" }, "target": { "name": "CodingStyle", "type": "Other" }, "type": { "name": "uses", "verbType": "Context" } },
+            { "source": { "name": "SeniorEngineer", "type": "Agent", "aiCli": "claude" }, "target": { "name": "Architect", "type": "Agent" }, "type": { "name": "inherits", "verbType": "Inheritance" } },
+            { "source": { "name": "SeniorEngineer", "type": "Agent" }, "target": { "name": "Code", "type": "Other" }, "type": { "name": "implements", "verbType": "Creation" } }
+        ]"#;
+        let mut graph = DependencyGraph::load_from_metamodel(json, None).unwrap();
+        assert_eq!(graph.ancestors("SeniorEngineer"), vec!["Architect"]);
+
+        let key = |agent: &str, relation: &str, target: &str| {
+            (agent.to_string(), relation.to_string(), target.to_string())
+        };
+        assert_eq!(
+            graph
+                .edge_categories
+                .get(&key("SeniorEngineer", "uses", "CodingStyle")),
+            Some(&RelationCategory::Context)
+        );
+        // DesignSpec keeps its single producer
+        let creates = key("SeniorEngineer", "creates", "DesignSpec");
+        assert!(!graph.edge_categories.contains_key(&creates));
+        assert!(!graph.failure_policies.contains_key(&creates));
+        let config = &graph.node_configs["SeniorEngineer"];
+        assert_eq!(config.model_type.as_deref(), Some("High Reasoning"));
+        assert_eq!(config.ai_cli.as_deref(), Some("claude"));
+
+        graph.loaded_agents.insert(
+            "Architect".to_string(),
+            serde_json::json!({"name": "Architect", "system_prompt": "You design systems."})
+                .to_string(),
+        );
+        graph.loaded_agents.insert(
+            "SeniorEngineer".to_string(),
+            serde_json::json!({"name": "SeniorEngineer", "system_prompt": "You also write code."})
+                .to_string(),
+        );
+        assert_eq!(
+            graph.system_prompt("SeniorEngineer"),
+            "You design systems.\n\nYou also write code."
+        );
+    }
+
+    #[test]
+    fn test_inheritance_cycles_and_conflicts_are_rejected() {
+        let cycle = r#"[
+            { "source": { "name": "A", "type": "Agent" }, "target": { "name": "B", "type": "Agent" }, "type": { "name": "inherits", "verbType": "Inheritance" } },
+            { "source": { "name": "B", "type": "Agent" }, "target": { "name": "A", "type": "Agent" }, "type": { "name": "inherits", "verbType": "Inheritance" } }
+        ]"#;
+        let err = DependencyGraph::load_from_metamodel(cycle, None).unwrap_err();
+        assert_eq!(err.to_string(), "Inheritance cycle: A -> B -> A");
+
+        let conflict = r#"[
+            { "source": { "name": "Lead", "type": "Agent" }, "target": { "name": "Architect", "type": "Agent", "model": "o1-pro" }, "type": { "name": "inherits", "verbType": "Inheritance" } },
+            { "source": { "name": "Lead", "type": "Agent" }, "target": { "name": "Engineer", "type": "Agent", "model": "flash" }, "type": { "name": "inherits", "verbType": "Inheritance" } }
+        ]"#;
+        let err = DependencyGraph::load_from_metamodel(conflict, None).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("inherits conflicting model values")
+        );
+
+        let not_agent = r#"[
+            { "source": { "name": "Lead", "type": "Agent" }, "target": { "name": "Code", "type": "Other" }, "type": { "name": "inherits", "verbType": "Inheritance" } }
+        ]"#;
+        assert!(DependencyGraph::load_from_metamodel(not_agent, None).is_err());
+    }
+}
@@ -1,11 +1,26 @@
+use crate::hooks::{HOOKS_FILE, Hook};
+use crate::lock::split_front_matter;
 use anyhow::Result;
 use petgraph::graph::DiGraph;
 use petgraph::graph::NodeIndex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+mod builtin;
+mod composition;
+mod diagnostics;
 pub mod executor;
+mod format;
+mod inheritance;
+mod registry;
 mod validation_test;
+mod verifier;
+
+pub use builtin::{BUILTIN_ONTOLOGY, builtin_ontology_root};
+pub use diagnostics::{Diagnostic, DiagnosticCode, Severity, locate};
+pub use format::{ONTOLOGY_FILES, OntologyFormat, SyntaxError, find_ontology_file};
+pub use registry::{SCHEMA_BASE_URI, SchemaRegistry};
+pub use verifier::{ScoreSource, Verifier};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RelationCategory {
@@ -14,8 +29,16 @@ pub enum RelationCategory {
     Refinement,   // refines, improves
     Dependency,   // requires
     Context,      // uses, isA, defines, contains
+    Inheritance,  // inherits (Agent -> Agent)
 }
 
+/// Built-in agent that runs the command line of `Command` entities.
+pub const COMMAND_RUNNER: &str = "CommandRunner";
+/// Relation of the Creation edge from the command runner to each `Command` entity.
+pub const COMMAND_RELATION: &str = "runs";
+/// Schema every `Command` result is validated against, unless the entity has its own.
+pub const COMMAND_RESULT_SCHEMA: &str = "https://pulpo.dev/schemas/meta/command-result.schema.json";
+
 impl RelationCategory {
     pub fn from_verb_type(s: &str) -> Self {
         match s {
@@ -23,6 +46,7 @@ impl RelationCategory {
             "Verification" => Self::Verification,
             "Refinement" => Self::Refinement,
             "Dependency" => Self::Dependency,
+            "Inheritance" => Self::Inheritance,
             _ => Self::Context,
         }
     }
@@ -41,6 +65,17 @@ pub struct MetaRelationship {
     pub rel_type: MetaVerb,
     #[serde(rename = "loop")]
     pub loop_config: Option<LoopConfig>,
+    #[serde(rename = "onFailure", default)]
+    pub on_failure: Option<FailurePolicy>,
+    #[serde(default)]
+    pub timeout: Option<TimeoutConfig>,
+    #[serde(rename = "forEach", default)]
+    pub for_each: Option<String>,
+    #[serde(default)]
+    pub verifier: Option<Verifier>,
+    // Position in ontology.json, for diagnostics
+    #[serde(skip)]
+    pub index: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -79,6 +114,84 @@ impl LoopConfig {
     }
 }
 
+/// Time limits for the AI CLI process running an edge's action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct TimeoutConfig {
+    /// Maximum wall time of the action, in seconds.
+    #[serde(default)]
+    pub seconds: Option<u64>,
+    /// Maximum time without any stdout/stderr output, in seconds.
+    #[serde(rename = "stallSeconds", default)]
+    pub stall_seconds: Option<u64>,
+}
+
+impl TimeoutConfig {
+    /// Fields set in `self` win over the ones in `fallback`.
+    pub fn or(self, fallback: TimeoutConfig) -> TimeoutConfig {
+        TimeoutConfig {
+            seconds: self.seconds.or(fallback.seconds),
+            stall_seconds: self.stall_seconds.or(fallback.stall_seconds),
+        }
+    }
+}
+
+/// What the engine does when an edge's agent dispatch fails or its artifact is
+/// rejected by schema validation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum FailurePolicy {
+    /// Dispatch again, feeding the error back into the prompt; abort once the
+    /// retries are used up.
+    Retry {
+        #[serde(rename = "maxRetries", default = "LoopConfig::default_max_retries")]
+        max_retries: usize,
+    },
+    /// Mark the target as skipped and continue with the rest of the graph.
+    Skip,
+    /// Stop the iteration so the user can resume it later.
+    Pause,
+    /// Fail the iteration.
+    #[default]
+    Abort,
+}
+
+impl std::str::FromStr for FailurePolicy {
+    type Err = anyhow::Error;
+
+    /// Parses `abort`, `skip`, `pause`, `retry` or `retry:<n>`.
+    fn from_str(s: &str) -> Result<Self> {
+        match s.split_once(':') {
+            Some(("retry", n)) => Ok(Self::Retry {
+                max_retries: n
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("Invalid retry count: {}", n))?,
+            }),
+            None => match s {
+                "retry" => Ok(Self::Retry {
+                    max_retries: LoopConfig::default_max_retries(),
+                }),
+                "skip" => Ok(Self::Skip),
+                "pause" => Ok(Self::Pause),
+                "abort" => Ok(Self::Abort),
+                _ => anyhow::bail!("Unknown failure policy: {}", s),
+            },
+            _ => anyhow::bail!("Unknown failure policy: {}", s),
+        }
+    }
+}
+
+/// How long an artifact lives and where it is stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// Shared by the whole application: created once and reused by every
+    /// later iteration (e.g. coding style, architecture style).
+    Application,
+    /// Produced for the feature of a single iteration.
+    #[default]
+    Feature,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MetaEntity {
     pub name: String,
@@ -89,6 +202,33 @@ pub struct MetaEntity {
     pub model: Option<String>,
     #[serde(rename = "aiCli")]
     pub ai_cli: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<Scope>,
+    // Directory of the referenced ontology, relative to this one (type "Ontology")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    // Command line run in the work dir (type "Command")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+}
+
+impl MetaEntity {
+    /// Combines the attributes of another occurrence of the same entity; the
+    /// ones it sets win.
+    pub fn merge(&mut self, other: &MetaEntity) {
+        let pick = |own: &mut Option<String>, other: &Option<String>| {
+            if other.is_some() {
+                own.clone_from(other);
+            }
+        };
+        pick(&mut self.entity_type, &other.entity_type);
+        pick(&mut self.model_type, &other.model_type);
+        pick(&mut self.model, &other.model);
+        pick(&mut self.ai_cli, &other.ai_cli);
+        pick(&mut self.path, &other.path);
+        pick(&mut self.command, &other.command);
+        self.scope = other.scope.or(self.scope);
+    }
 }
 
 // 2. The In-Memory Graph
@@ -100,13 +240,34 @@ pub struct DependencyGraph {
     pub prompt_templates: HashMap<(String, String, String), String>,
     pub relationship_prompts: HashMap<String, String>, // Key: Relation, Value: Default Template
     pub schemas: HashMap<String, String>,              // Key: Entity, Value: Schema Content
-    pub loaded_agents: HashMap<String, String>,        // Key: Role, Value: JSON Content
+    // Resolves the `$ref`s of schemas that are not in `schemas`
+    pub registry: SchemaRegistry,
+    pub loaded_agents: HashMap<String, String>, // Key: Role, Value: JSON Content
     pub agent_roles: std::collections::HashSet<String>, // Roles defined in the metamodel
     pub node_types: HashMap<String, String>, // Key: Entity Name, Value: Type (e.g. "Code", "Agent")
     // Data-driven verb categories (replaces hardcoded from_str matching)
     pub edge_categories: HashMap<(String, String, String), RelationCategory>,
     pub loop_configs: HashMap<(String, String, String), LoopConfig>,
+    pub failure_policies: HashMap<(String, String, String), FailurePolicy>,
+    pub timeouts: HashMap<(String, String, String), TimeoutConfig>,
+    // Verification edges scored by a command instead of by their agent
+    pub verifiers: HashMap<(String, String, String), Verifier>,
+    // Key: Fanned-out Entity, Value: Collection Entity it is produced per item of
+    pub for_each: HashMap<String, String>,
+    // Key: Entity Name, Value: Scope declared on any of its occurrences
+    pub scopes: HashMap<String, Scope>,
+    // Key: Namespace, Value: Directory of the ontology imported under it
+    pub imports: HashMap<String, std::path::PathBuf>,
+    // Key: Agent, Value: Agents it inherits from, in declaration order
+    pub inherits: HashMap<String, Vec<String>>,
     pub node_configs: HashMap<String, MetaEntity>, // Key: Entity Name, Value: MetaEntity
+    // Lifecycle hooks, those of imported ontologies first
+    pub hooks: Vec<Hook>,
+    // JSON pointers into ontology.json of the first occurrence of each entity and edge
+    pub node_pointers: HashMap<String, String>,
+    pub edge_pointers: HashMap<(String, String, String), String>,
+    // Problems found while loading; loading fails if any is an error
+    pub diagnostics: Vec<Diagnostic>,
 }
 
 impl Default for DependencyGraph {
@@ -123,12 +284,24 @@ impl DependencyGraph {
             prompt_templates: HashMap::new(),
             relationship_prompts: HashMap::new(),
             schemas: HashMap::new(),
+            registry: SchemaRegistry::new(),
             loaded_agents: HashMap::new(),
             agent_roles: std::collections::HashSet::new(),
             node_types: HashMap::new(),
             edge_categories: HashMap::new(),
             loop_configs: HashMap::new(),
+            failure_policies: HashMap::new(),
+            timeouts: HashMap::new(),
+            verifiers: HashMap::new(),
+            for_each: HashMap::new(),
+            scopes: HashMap::new(),
+            imports: HashMap::new(),
+            inherits: HashMap::new(),
             node_configs: HashMap::new(),
+            hooks: Vec::new(),
+            node_pointers: HashMap::new(),
+            edge_pointers: HashMap::new(),
+            diagnostics: Vec::new(),
         }
     }
 
@@ -136,43 +309,106 @@ impl DependencyGraph {
         json_content: &str,
         base_path: Option<&std::path::Path>,
     ) -> Result<Self> {
-        let relationships: Vec<MetaRelationship> = serde_json::from_str(json_content)?;
+        Self::load_composed(json_content, base_path, &mut Vec::new())
+    }
+
+    /// Loads an ontology, first loading the ontologies it references as
+    /// namespaced subgraphs. `loading` holds the directories being loaded, to
+    /// reject import cycles.
+    fn load_composed(
+        json_content: &str,
+        base_path: Option<&std::path::Path>,
+        loading: &mut Vec<std::path::PathBuf>,
+    ) -> Result<Self> {
+        let dg = Self::analyze(json_content, base_path, loading)?;
+        // See `diagnose` for the codes and locations of these
+        let errors: Vec<&str> = dg
+            .diagnostics
+            .iter()
+            .filter(|d| d.is_error())
+            .map(|d| d.message.as_str())
+            .collect();
+        if !errors.is_empty() {
+            anyhow::bail!("{}", errors.join("\n"));
+        }
+        Ok(dg)
+    }
+
+    /// Loads an ontology, recording what is wrong with it in `diagnostics`
+    /// rather than stopping at the first problem. Fails only when there is
+    /// nothing to analyze: unreadable JSON, broken imports or hooks.
+    fn analyze(
+        json_content: &str,
+        base_path: Option<&std::path::Path>,
+        loading: &mut Vec<std::path::PathBuf>,
+    ) -> Result<Self> {
+        let file = base_path.and_then(find_ontology_file);
+        let instance = OntologyFormat::of(file.as_deref(), json_content).parse(json_content)?;
         let mut dg = Self::new();
 
         dg.load_meta_schemas();
-        dg.validate_metamodel_logic(json_content)?;
-
-        let root = base_path
-            .unwrap_or_else(|| std::path::Path::new("pulpo-ontologies/software-engineering"));
+        dg.validate_metamodel_logic(&instance)?;
+        if dg.diagnostics.iter().any(|d| d.is_error()) {
+            return Ok(dg);
+        }
+        let mut relationships: Vec<MetaRelationship> = serde_json::from_value(instance)?;
+        for (index, rel) in relationships.iter_mut().enumerate() {
+            rel.index = index;
+        }
 
-        dg.load_artifact_schemas(root);
-        dg.load_relationship_prompts_logic(root);
-        dg.process_relationships_logic(root, relationships);
-        dg.load_agents_logic(root);
+        // Without a directory there are no schemas, prompts, hooks or agents
+        // to load, and imports have nothing to resolve against
+        let relationships = match base_path {
+            Some(root) => {
+                if !root.is_dir() {
+                    anyhow::bail!("Ontology directory {} does not exist", root.display());
+                }
+                dg.registry = SchemaRegistry::new().with_ontology_root(root);
+                let relationships = dg.load_imports(root, relationships, loading)?;
+                dg.load_artifact_schemas(root);
+                dg.load_relationship_prompts_logic(root);
+                dg.load_hooks(root)?;
+                relationships
+            }
+            None => {
+                if let Some(entity) = relationships
+                    .iter()
+                    .flat_map(|rel| [&rel.source, &rel.target])
+                    .find(|entity| {
+                        entity.entity_type.as_deref() == Some(composition::ONTOLOGY_TYPE)
+                    })
+                {
+                    anyhow::bail!(
+                        "Ontology reference '{}' needs the directory of the ontology importing it",
+                        entity.name
+                    );
+                }
+                relationships
+            }
+        };
+        dg.process_relationships_logic(base_path, relationships);
+        if let Some(root) = base_path {
+            dg.load_agents_logic(root);
+        }
+        dg.add_command_runs();
 
-        dg.validate_meta_ontology()?;
+        let findings = dg.validate_meta_ontology();
+        dg.diagnostics.extend(findings);
         dg.validate_topology()?;
+        if !dg.diagnostics.iter().any(|d| d.is_error()) {
+            dg.apply_inheritance();
+        }
         Ok(dg)
     }
 
     fn load_meta_schemas(&mut self) {
-        let manifest_dir = env!("CARGO_MANIFEST_DIR");
-        let engine_meta_root =
-            std::path::Path::new(manifest_dir).join("../../packages/pulpo-schema/meta");
-
-        let mut meta_files = Vec::new();
-        Self::find_json_files(&engine_meta_root, &mut meta_files);
-        for path in meta_files {
-            if let Ok(content) = std::fs::read_to_string(&path)
-                && let Ok(json) = serde_json::from_str::<serde_json::Value>(&content)
+        for (id, content) in SchemaRegistry::embedded() {
+            if let Ok(json) = serde_json::from_str::<serde_json::Value>(content)
+                && let Some(title) = json.get("title").and_then(|v| v.as_str())
             {
-                if let Some(id) = json.get("$id").and_then(|v| v.as_str()) {
-                    self.schemas.insert(id.to_string(), content.clone());
-                }
-                if let Some(title) = json.get("title").and_then(|v| v.as_str()) {
-                    self.schemas.insert(title.to_string(), content);
-                }
+                self.schemas.insert(title.to_string(), content.to_string());
             }
+            self.schemas.insert(id, content.to_string());
         }
 
         if let Some(content) = self
@@ -187,7 +423,7 @@ impl DependencyGraph {
         }
     }
 
-    fn validate_metamodel_logic(&self, json_content: &str) -> Result<()> {
+    fn validate_metamodel_logic(&mut self, instance: &serde_json::Value) -> Result<()> {
         if let Some(schema_content) = self
             .schemas
             .get("https://pulpo.dev/schemas/meta/ontology.schema.json")
@@ -209,12 +445,14 @@ impl DependencyGraph {
                 .build(&schema_json)
                 .map_err(|e| anyhow::anyhow!("Failed to compile Meta-Ontology schema: {}", e))?;
 
-            let instance: serde_json::Value = serde_json::from_str(json_content)?;
-            if let Err(errors) = compiled.validate(&instance) {
-                let error_msg = errors.to_string();
-                return Err(anyhow::anyhow!(
-                    "Metamodel validation against Meta-Ontology failed: {}",
-                    error_msg
+            for error in compiled.iter_errors(instance) {
+                self.diagnostics.push(Diagnostic::error(
+                    DiagnosticCode::SchemaViolation,
+                    format!(
+                        "Metamodel validation against Meta-Ontology failed: {}",
+                        error
+                    ),
+                    &error.instance_path.to_string(),
                 ));
             }
         }
@@ -255,16 +493,41 @@ impl DependencyGraph {
                     && let Some(file_stem) = path.file_stem().and_then(|s| s.to_str())
                     && let Ok(content) = std::fs::read_to_string(&path)
                 {
+                    let (_, body) = split_front_matter(&content);
                     self.relationship_prompts
-                        .insert(file_stem.to_string(), content);
+                        .insert(file_stem.to_string(), body.to_string());
                 }
             }
         }
     }
 
+    fn load_hooks(&mut self, root: &std::path::Path) -> Result<()> {
+        let path = root.join(HOOKS_FILE);
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            return Ok(());
+        };
+        let instance: serde_json::Value = serde_json::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Invalid {}: {}", path.display(), e))?;
+        if let Some(schema) = self
+            .schemas
+            .get("https://pulpo.dev/schemas/meta/hooks.schema.json")
+        {
+            let compiled = self.compile_validator(schema)?;
+            if let Err(errors) = compiled.validate(&instance) {
+                anyhow::bail!("{} is invalid: {}", path.display(), errors);
+            }
+        }
+        let hooks: Vec<Hook> = serde_json::from_value(instance)?;
+        for hook in &hooks {
+            hook.validate()?;
+        }
+        self.hooks.extend(hooks);
+        Ok(())
+    }
+
     fn process_relationships_logic(
         &mut self,
-        root: &std::path::Path,
+        root: Option<&std::path::Path>,
         relationships: Vec<MetaRelationship>,
     ) {
         for rel in relationships {
@@ -278,6 +541,26 @@ impl DependencyGraph {
             self.graph.add_edge(s_idx, t_idx, relation_str.clone());
 
             let edge_key = (source_str.clone(), relation_str.clone(), target_str.clone());
+            let pointer = format!("/{}", rel.index);
+            if self.edge_pointers.contains_key(&edge_key) {
+                self.diagnostics.push(Diagnostic::warning(
+                    DiagnosticCode::DuplicateEdge,
+                    format!(
+                        "'{} {} {}' is declared more than once; the later declaration's settings win",
+                        source_str, relation_str, target_str
+                    ),
+                    &pointer,
+                ));
+            } else {
+                self.edge_pointers.insert(edge_key.clone(), pointer.clone());
+            }
+            self.node_pointers
+                .entry(source_str.clone())
+                .or_insert_with(|| format!("{}/source", pointer));
+            self.node_pointers
+                .entry(target_str.clone())
+                .or_insert_with(|| format!("{}/target", pointer));
+
             let category = RelationCategory::from_verb_type(&verb_type);
             self.edge_categories.insert(edge_key.clone(), category);
 
@@ -285,8 +568,30 @@ impl DependencyGraph {
                 self.loop_configs.insert(edge_key.clone(), lc);
             }
 
+            if let Some(policy) = rel.on_failure {
+                self.failure_policies.insert(edge_key.clone(), policy);
+            }
+
+            if let Some(timeout) = rel.timeout {
+                self.timeouts.insert(edge_key.clone(), timeout);
+            }
+
+            if let Some(verifier) = rel.verifier {
+                self.verifiers.insert(edge_key.clone(), verifier);
+            }
+
+            if let Some(collection) = rel.for_each {
+                self.for_each.insert(target_str.clone(), collection);
+            }
+
+            if category == RelationCategory::Inheritance {
+                self.inherits
+                    .entry(source_str.clone())
+                    .or_default()
+                    .push(target_str.clone());
+            }
+
             if let Some(t) = rel.source.entity_type.clone() {
-                println!("DEBUG: Inserting node_type for {}: {}", source_str, t);
                 self.node_types.insert(source_str.clone(), t.clone());
                 if t == "Agent" {
                     self.agent_roles.insert(source_str.clone());
@@ -301,7 +606,6 @@ impl DependencyGraph {
                 }
             }
             if let Some(t) = rel.target.entity_type.clone() {
-                println!("DEBUG: Inserting node_type for {}: {}", target_str, t);
                 self.node_types.insert(target_str.clone(), t.clone());
                 if t == "Agent" {
                     self.agent_roles.insert(target_str.clone());
@@ -316,15 +620,71 @@ impl DependencyGraph {
                 }
             }
 
-            self.node_configs
-                .insert(source_str.clone(), rel.source.clone());
-            self.node_configs
-                .insert(target_str.clone(), rel.target.clone());
+            for entity in [&rel.source, &rel.target] {
+                if let Some(scope) = entity.scope {
+                    self.scopes.insert(entity.name.clone(), scope);
+                }
+            }
+
+            // Imported entities keep the configuration of their own ontology
+            for entity in [&rel.source, &rel.target] {
+                if !self.is_imported(&entity.name) {
+                    self.node_configs
+                        .entry(entity.name.clone())
+                        .and_modify(|config| config.merge(entity))
+                        .or_insert_with(|| entity.clone());
+                }
+            }
+
+            if let Some(root) = root {
+                self.discover_prompt_template(root, &source_str, &relation_str, &target_str);
+            }
+        }
+    }
+
+    /// Gives every `Command` entity a Creation edge from the built-in command
+    /// runner, so it is planned like any other artifact once its dependencies
+    /// are met.
+    fn add_command_runs(&mut self) {
+        let mut commands: Vec<String> = self
+            .node_types
+            .iter()
+            .filter(|(_, t)| t.as_str() == "Command")
+            .map(|(name, _)| name.clone())
+            .collect();
+        commands.sort();
+        if commands.is_empty() {
+            return;
+        }
 
-            self.discover_prompt_template(root, &source_str, &relation_str, &target_str);
+        self.agent_roles.insert(COMMAND_RUNNER.to_string());
+        self.node_types
+            .insert(COMMAND_RUNNER.to_string(), "Agent".to_string());
+        let runner = self.get_or_create_node(COMMAND_RUNNER);
+        for command in commands {
+            let key = (
+                COMMAND_RUNNER.to_string(),
+                COMMAND_RELATION.to_string(),
+                command.clone(),
+            );
+            if self.edge_categories.contains_key(&key) {
+                continue;
+            }
+            let idx = self.get_or_create_node(&command);
+            self.graph
+                .add_edge(runner, idx, COMMAND_RELATION.to_string());
+            self.edge_categories.insert(key, RelationCategory::Creation);
         }
     }
 
+    /// Command line of a `Command` entity.
+    pub fn command_line(&self, kind: &str) -> Option<&str> {
+        if self.node_types.get(kind).map(|t| t.as_str()) != Some("Command") {
+            return None;
+        }
+        self.node_configs.get(kind)?.command.as_deref()
+    }
+
     fn discover_prompt_template(
         &mut self,
         root: &std::path::Path,
@@ -333,20 +693,11 @@ impl DependencyGraph {
         target: &str,
     ) {
         let prompt_filename = format!("{}_{}_{}.md", source, relation, target);
-        let p = root.join("relationship/prompt").join(&prompt_filename);
-
-        let paths_to_try = vec![
-            p.clone(),
-            std::path::Path::new("../..").join(&p),
-            std::path::Path::new("..").join(&p),
-        ];
-
-        for path in paths_to_try {
-            if let Ok(c) = std::fs::read_to_string(&path) {
-                let key = (source.to_string(), relation.to_string(), target.to_string());
-                self.prompt_templates.insert(key, c);
-                break;
-            }
+        let path = root.join("relationship/prompt").join(&prompt_filename);
+        if let Ok(c) = std::fs::read_to_string(&path) {
+            let key = (source.to_string(), relation.to_string(), target.to_string());
+            let (_, body) = split_front_matter(&c);
+            self.prompt_templates.insert(key, body.to_string());
         }
     }
 
@@ -361,9 +712,11 @@ impl DependencyGraph {
                     let role = file_stem.to_string();
                     self.agent_roles.insert(role.clone());
                     if let Ok(content) = std::fs::read_to_string(&path) {
+                        // The front matter versions the definition; it is not part of the prompt
+                        let (_, body) = split_front_matter(&content);
                         let config_wrapper = serde_json::json!({
                             "name": role,
-                            "system_prompt": content
+                            "system_prompt": body
                         });
                         self.loaded_agents.insert(role, config_wrapper.to_string());
                     }
@@ -415,10 +768,13 @@ impl DependencyGraph {
         Ok(())
     }
 
-    pub fn validate_meta_ontology(&self) -> Result<()> {
-        // Ensure all nodes are known either as Agents or Artifacts (have a schema)
+    /// Checks the rules of the Meta-Ontology: entity types, which verbs may
+    /// link which entities, verifiers and inheritance.
+    pub fn validate_meta_ontology(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
         for node_idx in self.graph.node_indices() {
             let name = &self.graph[node_idx];
+            let pointer = self.node_pointer(name);
 
             // Check implicit or explicit types
             let is_agent = self.is_agent(name);
@@ -431,14 +787,27 @@ impl DependencyGraph {
 
             // Check if explicitly typed as "Other" or "Code" (which might not have schema yet? Code should have schema now)
             let node_type = self.node_types.get(name).map(|s| s.as_str());
-            let is_other = node_type == Some("Other");
+            let is_other = node_type == Some("Other") || node_type == Some("Command");
 
             // If it's not an agent, not an artifact (no schema), and NOT explicitly "Other", then warn.
             if !is_agent && !has_schema && !is_other && name != "SoftwareApplication" {
-                println!(
-                    "Warning: Entity '{}' is neither an Agent nor has a known Artifact schema (Type: {:?})",
-                    name, node_type
-                );
+                diagnostics.push(Diagnostic::warning(
+                    DiagnosticCode::UnknownSchema,
+                    format!(
+                        "Entity '{}' is neither an Agent nor has a known Artifact schema (Type: {:?})",
+                        name, node_type
+                    ),
+                    pointer,
+                ));
+            }
+
+            // Rule: a Command entity declares the command line it runs
+            if node_type == Some("Command") && self.command_line(name).is_none() {
+                diagnostics.push(Diagnostic::error(
+                    DiagnosticCode::MissingCommandLine,
+                    format!("Command '{}' does not declare a command line", name),
+                    pointer,
+                ));
             }
         }
 
@@ -448,22 +817,109 @@ impl DependencyGraph {
             let target = &self.graph[target_idx];
             let relation = self.graph.edge_weight(edge).unwrap();
             let edge_key = (source.to_string(), relation.to_string(), target.to_string());
+            let pointer = self.edge_pointer(&edge_key);
             let category = self
                 .edge_categories
                 .get(&edge_key)
                 .copied()
                 .unwrap_or(RelationCategory::Context);
+            let mut error = |code, message: String| {
+                diagnostics.push(Diagnostic::error(code, message, pointer));
+            };
 
             // Rule: Agent -(Creation)-> Artifact
             if self.is_agent(source)
                 && category == RelationCategory::Creation
                 && self.is_agent(target)
             {
-                return Err(anyhow::anyhow!(
-                    "Agent '{}' cannot 'create' another Agent '{}'",
-                    source,
-                    target
-                ));
+                error(
+                    DiagnosticCode::InvalidEdge,
+                    format!(
+                        "Agent '{}' cannot 'create' another Agent '{}'",
+                        source, target
+                    ),
+                );
+            }
+
+            // Rule: an application-scoped artifact is reused by every later
+            // feature, so it cannot depend on one feature's artifacts
+            if category == RelationCategory::Dependency
+                && self.scope_of(source) == Scope::Application
+                && self.scope_of(target) == Scope::Feature
+            {
+                error(
+                    DiagnosticCode::ScopeViolation,
+                    format!(
+                        "Application-scoped '{}' cannot depend on feature-scoped '{}'",
+                        source, target
+                    ),
+                );
+            }
+
+            // Rule: forEach must name another artifact in the graph
+            if let Some(collection) = self.for_each.get(target)
+                && (collection == target
+                    || !self.kind_map.contains_key(collection)
+                    || self.is_agent(collection))
+            {
+                error(
+                    DiagnosticCode::InvalidForEach,
+                    format!(
+                        "'{}' cannot be produced for each item of '{}': it is not another artifact in the ontology",
+                        target, collection
+                    ),
+                );
+            }
+
+            // Rule: Command entities are only produced by running their command
+            if self.node_types.get(target).map(|t| t.as_str()) == Some("Command")
+                && matches!(
+                    category,
+                    RelationCategory::Creation | RelationCategory::Refinement
+                )
+                && source != COMMAND_RUNNER
+            {
+                error(
+                    DiagnosticCode::InvalidEdge,
+                    format!(
+                        "'{}' cannot {} Command '{}': commands are run by the engine",
+                        source, relation, target
+                    ),
+                );
+            }
+
+            // Rule: only Verification edges are scored by a verifier command
+            if let Some(verifier) = self.verifiers.get(&edge_key) {
+                if category != RelationCategory::Verification {
+                    error(
+                        DiagnosticCode::InvalidVerifier,
+                        format!(
+                            "'{} {} {}' declares a verifier but is not a Verification edge",
+                            source, relation, target
+                        ),
+                    );
+                } else if verifier.command.trim().is_empty() || verifier.scale <= 0.0 {
+                    error(
+                        DiagnosticCode::InvalidVerifier,
+                        format!(
+                            "The verifier of '{} {} {}' needs a command and a positive scale",
+                            source, relation, target
+                        ),
+                    );
+                }
+            }
+
+            // Rule: Agent -(Inheritance)-> Agent
+            if category == RelationCategory::Inheritance
+                && (!self.is_agent(source) || !self.is_agent(target))
+            {
+                error(
+                    DiagnosticCode::InvalidInheritance,
+                    format!(
+                        "'{}' cannot inherit '{}': inheritance is only allowed between Agents",
+                        source, target
+                    ),
+                );
             }
 
             // Rule: Agent -(Verification)-> Artifact
@@ -471,14 +927,36 @@ impl DependencyGraph {
                 && category == RelationCategory::Verification
                 && self.is_agent(target)
             {
-                return Err(anyhow::anyhow!(
-                    "Agent '{}' cannot 'verify' another Agent '{}'",
-                    source,
-                    target
-                ));
+                error(
+                    DiagnosticCode::InvalidEdge,
+                    format!(
+                        "Agent '{}' cannot 'verify' another Agent '{}'",
+                        source, target
+                    ),
+                );
             }
         }
-        Ok(())
+
+        if let Err(e) = self.validate_inheritance() {
+            diagnostics.push(Diagnostic::error(
+                DiagnosticCode::InvalidInheritance,
+                e.to_string(),
+                "",
+            ));
+        }
+        diagnostics
+    }
+
+    /// Command that scores the Verification edge `agent relation target`, if
+    /// it is not scored by the agent itself.
+    pub fn verifier(&self, agent: &str, relation: &str, target: &str) -> Option<&Verifier> {
+        self.verifiers
+            .get(&(agent.to_string(), relation.to_string(), target.to_string()))
+    }
+
+    /// Scope of an entity; entities that do not declare one are feature-scoped.
+    pub fn scope_of(&self, kind: &str) -> Scope {
+        self.scopes.get(kind).copied().unwrap_or_default()
     }
 
     pub fn is_agent(&self, kind: &str) -> bool {
@@ -492,10 +970,7 @@ impl DependencyGraph {
             Some(content) => content,
             None => {
                 let node_type = self.node_types.get(kind).map(|s| s.as_str());
-                println!(
-                    "DEBUG: validate_artifact for {}. node_type: {:?}",
-                    kind, node_type
-                );
+                tracing::debug!("No schema for {} (node type {:?})", kind, node_type);
                 if node_type == Some("Other") || kind == "SoftwareApplication" {
                     return Ok(());
                 }
@@ -536,12 +1011,17 @@ impl DependencyGraph {
         self.schemas
             .get(kind)
             .or_else(|| self.schemas.get(&snake_kind))
+            .or_else(|| {
+                self.command_line(kind)
+                    .and_then(|_| self.schemas.get(COMMAND_RESULT_SCHEMA))
+            })
     }
 
     fn compile_validator(&self, schema_content: &str) -> Result<jsonschema::Validator> {
         let schema_json: serde_json::Value = serde_json::from_str(schema_content)?;
 
         let mut options = jsonschema::options();
+        options.with_retriever(self.registry.clone());
         for (id, content) in &self.schemas {
             if id.starts_with("http")
                 && let Ok(sub_json) = serde_json::from_str::<serde_json::Value>(content)
@@ -687,6 +1167,36 @@ impl DependencyGraph {
         related.dedup();
         related
     }
+
+    /// Every node that depends on `node`, directly or transitively, through
+    /// `Dependency` edges (e.g. "Code requires DesignSpec"). Nodes are returned
+    /// in breadth-first order, without `node` itself.
+    pub fn dependents(&self, node: &str) -> Vec<String> {
+        use petgraph::visit::EdgeRef;
+
+        let mut found: Vec<String> = Vec::new();
+        let mut queue = std::collections::VecDeque::from([node.to_string()]);
+        while let Some(current) = queue.pop_front() {
+            let Some(&idx) = self.kind_map.get(&current) else {
+                continue;
+            };
+            for edge in self
+                .graph
+                .edges_directed(idx, petgraph::Direction::Incoming)
+            {
+                let source = &self.graph[edge.source()];
+                let key = (source.clone(), edge.weight().clone(), current.clone());
+                if self.edge_categories.get(&key) == Some(&RelationCategory::Dependency)
+                    && source != node
+                    && !found.contains(source)
+                {
+                    found.push(source.clone());
+                    queue.push_back(source.clone());
+                }
+            }
+        }
+        found
+    }
 }
 
 #[cfg(test)]
@@ -718,6 +1228,39 @@ mod tests {
         assert_eq!(related_req, vec!["Feature"]);
     }
 
+    #[test]
+    fn test_missing_ontology_directory_is_an_error() {
+        let json = r#"[
+            { "source": { "name": "Agent", "type": "Agent" }, "target": { "name": "Feature" }, "type": { "name": "creates", "verbType": "Creation" } }
+        ]"#;
+        let missing = std::env::temp_dir().join("pulpo-no-such-ontology");
+        let err = DependencyGraph::load_from_metamodel(json, Some(&missing)).unwrap_err();
+        assert!(err.to_string().contains("does not exist"), "{}", err);
+
+        let root = builtin_ontology_root().unwrap();
+        let graph = DependencyGraph::load_from_metamodel(BUILTIN_ONTOLOGY, Some(&root)).unwrap();
+        assert!(!graph.prompt_templates.is_empty());
+    }
+
+    #[test]
+    fn test_dependents_are_transitive() {
+        let json = r#"[
+            { "source": { "name": "PM", "type": "Agent" }, "target": { "name": "Requirement" }, "type": { "name": "creates", "verbType": "Creation" } },
+            { "source": { "name": "DesignSpec" }, "target": { "name": "Requirement" }, "type": { "name": "requires", "verbType": "Dependency" } },
+            { "source": { "name": "Code" }, "target": { "name": "DesignSpec" }, "type": { "name": "requires", "verbType": "Dependency" } },
+            { "source": { "name": "Code" }, "target": { "name": "Requirement" }, "type": { "name": "requires", "verbType": "Dependency" } },
+            { "source": { "name": "Code" }, "target": { "name": "CodingStyle" }, "type": { "name": "uses", "verbType": "Context" } }
+        ]"#;
+        let graph = DependencyGraph::load_from_metamodel(json, None).expect("Failed to load graph");
+
+        let mut dependents = graph.dependents("Requirement");
+        dependents.sort();
+        assert_eq!(dependents, vec!["Code", "DesignSpec"]);
+        assert_eq!(graph.dependents("DesignSpec"), vec!["Code"]);
+        assert!(graph.dependents("Code").is_empty());
+        assert!(graph.dependents("CodingStyle").is_empty());
+    }
+
     #[test]
     fn test_invalid_topology_multiple_roots() {
         let json = r#"[
@@ -742,4 +1285,51 @@ mod tests {
         // Unreachable nodes/cycles are now allowed
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_failure_policy_parsing() {
+        let json = r#"[
+            { "source": { "name": "Dev", "type": "Agent" }, "target": { "name": "Code" }, "type": { "name": "creates", "verbType": "Creation" }, "onFailure": { "action": "retry", "maxRetries": 2 } },
+            { "source": { "name": "QA", "type": "Agent" }, "target": { "name": "Code" }, "type": { "name": "verifies", "verbType": "Verification" }, "onFailure": { "action": "skip" } }
+        ]"#;
+        let graph = DependencyGraph::load_from_metamodel(json, None).unwrap();
+        let key = |s: &str, r: &str| (s.to_string(), r.to_string(), "Code".to_string());
+        assert_eq!(
+            graph.failure_policies.get(&key("Dev", "creates")),
+            Some(&FailurePolicy::Retry { max_retries: 2 })
+        );
+        assert_eq!(
+            graph.failure_policies.get(&key("QA", "verifies")),
+            Some(&FailurePolicy::Skip)
+        );
+
+        assert_eq!(
+            "retry:5".parse::<FailurePolicy>().unwrap(),
+            FailurePolicy::Retry { max_retries: 5 }
+        );
+        assert_eq!(
+            "pause".parse::<FailurePolicy>().unwrap(),
+            FailurePolicy::Pause
+        );
+        assert!("explode".parse::<FailurePolicy>().is_err());
+    }
+
+    #[test]
+    fn test_scopes() {
+        let json = r#"[
+            { "source": { "name": "Engineer", "type": "Agent" }, "target": { "name": "CodingStyle", "scope": "application" }, "type": { "name": "defines", "verbType": "Creation" } },
+            { "source": { "name": "Engineer", "type": "Agent" }, "target": { "name": "Code" }, "type": { "name": "implements", "verbType": "Creation" } },
+            { "source": { "name": "Code" }, "target": { "name": "CodingStyle" }, "type": { "name": "requires", "verbType": "Dependency" } }
+        ]"#;
+        let graph = DependencyGraph::load_from_metamodel(json, None).unwrap();
+        assert_eq!(graph.scope_of("CodingStyle"), Scope::Application);
+        assert_eq!(graph.scope_of("Code"), Scope::Feature);
+
+        let json = r#"[
+            { "source": { "name": "Engineer", "type": "Agent" }, "target": { "name": "CodingStyle", "scope": "application" }, "type": { "name": "defines", "verbType": "Creation" } },
+            { "source": { "name": "CodingStyle", "scope": "application" }, "target": { "name": "Requirement" }, "type": { "name": "requires", "verbType": "Dependency" } }
+        ]"#;
+        let err = DependencyGraph::load_from_metamodel(json, None).unwrap_err();
+        assert!(err.to_string().contains("cannot depend on feature-scoped"));
+    }
 }
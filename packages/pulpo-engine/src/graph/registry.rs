@@ -0,0 +1,238 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// Base of the `$id` of every schema Pulpo ships or generates.
+pub const SCHEMA_BASE_URI: &str = "https://pulpo.dev/schemas/";
+
+/// Meta-schemas compiled into the engine, so that an installed binary does
+/// not depend on the source tree.
+const EMBEDDED: &[&str] = &[
+    include_str!("../../../pulpo-schema/meta/base.schema.json"),
+    include_str!("../../../pulpo-schema/meta/command-result.schema.json"),
+    include_str!("../../../pulpo-schema/meta/hooks.schema.json"),
+    include_str!("../../../pulpo-schema/meta/icl.schema.json"),
+    include_str!("../../../pulpo-schema/meta/ontology.schema.json"),
+];
+
+/// Resolves the schemas referenced by `$id` or `$ref` when artifact schemas
+/// are compiled. A URI is looked up, in order, in the ontology's schema
+/// directories, in the user's schema cache and among the embedded
+/// meta-schemas. URIs under [`SCHEMA_BASE_URI`] map to files by their path
+/// (`entities/user_story.schema.json`) or file name (`user_story.schema.json`).
+#[derive(Debug, Clone, Default)]
+pub struct SchemaRegistry {
+    // Searched most specific first: the ontology's, then the cache
+    search_dirs: Vec<PathBuf>,
+}
+
+impl SchemaRegistry {
+    /// Registry of the embedded meta-schemas and the user's schema cache.
+    pub fn new() -> Self {
+        Self {
+            search_dirs: Self::cache_dir().into_iter().collect(),
+        }
+    }
+
+    /// Searches the schemas of the ontology at `root` before anything else.
+    pub fn with_ontology_root(mut self, root: &Path) -> Self {
+        let dirs = [root.join("artifact/schema"), root.to_path_buf()];
+        self.search_dirs.splice(0..0, dirs);
+        self
+    }
+
+    /// Where the user keeps schemas shared by several ontologies:
+    /// `$PULPO_SCHEMA_CACHE`, else `$XDG_CACHE_HOME/pulpo/schemas`, else
+    /// `~/.cache/pulpo/schemas`.
+    pub fn cache_dir() -> Option<PathBuf> {
+        if let Some(dir) = std::env::var_os("PULPO_SCHEMA_CACHE") {
+            return Some(PathBuf::from(dir));
+        }
+        let cache = std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+        Some(cache.join("pulpo/schemas"))
+    }
+
+    /// The embedded meta-schemas, as `($id, content)`.
+    pub fn embedded() -> impl Iterator<Item = (String, &'static str)> {
+        EMBEDDED.iter().filter_map(|content| {
+            let schema: Value = serde_json::from_str(content).ok()?;
+            let id = schema.get("$id")?.as_str()?.to_string();
+            Some((id, *content))
+        })
+    }
+
+    /// The schema identified by `uri`, ignoring any fragment.
+    pub fn resolve(&self, uri: &str) -> Result<Value> {
+        let uri = uri.split_once('#').map_or(uri, |(base, _)| base);
+        let mut tried = Vec::new();
+        for path in self.candidates(uri) {
+            if path.is_file() {
+                let content = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read schema {}", path.display()))?;
+                return serde_json::from_str(&content)
+                    .with_context(|| format!("Schema {} is not valid JSON", path.display()));
+            }
+            tried.push(path.display().to_string());
+        }
+        if let Some((_, content)) = Self::embedded().find(|(id, _)| id == uri) {
+            return Ok(serde_json::from_str(content)?);
+        }
+        if tried.is_empty() {
+            anyhow::bail!(
+                "Cannot resolve schema reference '{}': it is not under {} nor an embedded meta-schema",
+                uri,
+                SCHEMA_BASE_URI
+            );
+        }
+        anyhow::bail!(
+            "Cannot resolve schema reference '{}': not found at {} nor among the embedded meta-schemas",
+            uri,
+            tried.join(", ")
+        )
+    }
+
+    /// Files that may hold the schema of `uri`, in search order.
+    fn candidates(&self, uri: &str) -> Vec<PathBuf> {
+        if let Some(path) = uri.strip_prefix("file://") {
+            return vec![PathBuf::from(path)];
+        }
+        let Some(relative) = uri.strip_prefix(SCHEMA_BASE_URI) else {
+            return Vec::new();
+        };
+        let file_name = relative.rsplit('/').next().unwrap_or(relative);
+        let mut candidates = Vec::new();
+        for dir in &self.search_dirs {
+            candidates.push(dir.join(relative));
+            if file_name != relative {
+                candidates.push(dir.join(file_name));
+            }
+        }
+        candidates
+    }
+}
+
+impl jsonschema::Retrieve for SchemaRegistry {
+    fn retrieve(
+        &self,
+        uri: &jsonschema::Uri<&str>,
+    ) -> std::result::Result<Value, Box<dyn std::error::Error + Send + Sync>> {
+        self.resolve(uri.as_str())
+            .map_err(|e| format!("{:#}", e).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_references_resolve_from_the_ontology_then_the_cache_then_the_engine() {
+        let ontology = tempfile::tempdir().unwrap();
+        let cache = tempfile::tempdir().unwrap();
+        let schema_dir = ontology.path().join("artifact/schema");
+        std::fs::create_dir_all(&schema_dir).unwrap();
+        std::fs::write(
+            schema_dir.join("money.schema.json"),
+            r#"{"type": "number", "minimum": 0}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            cache.path().join("money.schema.json"),
+            r#"{"type": "string"}"#,
+        )
+        .unwrap();
+        std::fs::create_dir_all(cache.path().join("shared")).unwrap();
+        std::fs::write(
+            cache.path().join("shared/currency.schema.json"),
+            r#"{"enum": ["EUR", "USD"]}"#,
+        )
+        .unwrap();
+
+        let registry = SchemaRegistry {
+            search_dirs: vec![cache.path().to_path_buf()],
+        }
+        .with_ontology_root(ontology.path());
+        assert_eq!(
+            registry
+                .resolve("https://pulpo.dev/schemas/entities/money.schema.json#/minimum")
+                .unwrap()["type"],
+            "number"
+        );
+        assert_eq!(
+            registry
+                .resolve("https://pulpo.dev/schemas/shared/currency.schema.json")
+                .unwrap()["enum"][0],
+            "EUR"
+        );
+        assert_eq!(
+            registry
+                .resolve("https://pulpo.dev/schemas/meta/command-result.schema.json")
+                .unwrap()["title"],
+            "CommandResult"
+        );
+
+        let err = registry
+            .resolve("https://pulpo.dev/schemas/entities/price.schema.json")
+            .unwrap_err();
+        assert!(err.to_string().contains("Cannot resolve schema reference"));
+        assert!(err.to_string().contains("price.schema.json"));
+        assert!(
+            registry
+                .resolve("https://example.com/price.json")
+                .unwrap_err()
+                .to_string()
+                .contains("nor an embedded meta-schema")
+        );
+
+        // A $ref the registry resolves compiles; one it cannot names the URI
+        let schema = serde_json::json!({
+            "$id": "https://pulpo.dev/schemas/entities/invoice.schema.json",
+            "properties": {"total": {"$ref": "money.schema.json"}}
+        });
+        let validator = jsonschema::options()
+            .with_retriever(registry.clone())
+            .build(&schema)
+            .unwrap();
+        assert!(!validator.is_valid(&serde_json::json!({"total": -1})));
+        let broken = serde_json::json!({"$ref": "https://pulpo.dev/schemas/nope.schema.json"});
+        let err = jsonschema::options()
+            .with_retriever(registry)
+            .build(&broken)
+            .unwrap_err();
+        assert!(err.to_string().contains("nope.schema.json"));
+    }
+
+    #[test]
+    fn test_unresolved_references_are_reported_by_diagnose() {
+        let ontology = tempfile::tempdir().unwrap();
+        let schema_dir = ontology.path().join("artifact/schema");
+        std::fs::create_dir_all(&schema_dir).unwrap();
+        std::fs::write(
+            schema_dir.join("invoice.schema.json"),
+            r#"{"$id": "https://pulpo.dev/schemas/entities/invoice.schema.json", "title": "Invoice", "properties": {"total": {"$ref": "money.schema.json"}}}"#,
+        )
+        .unwrap();
+        let json = r#"[
+            { "source": { "name": "Clerk", "type": "Agent" }, "target": { "name": "Invoice", "type": "Document" }, "type": { "name": "creates", "verbType": "Creation" } }
+        ]"#;
+
+        let diagnostics = crate::graph::DependencyGraph::diagnose(json, Some(ontology.path()));
+        let unresolved: Vec<_> = diagnostics
+            .iter()
+            .filter(|d| d.code == crate::graph::DiagnosticCode::UnresolvedReference)
+            .collect();
+        assert_eq!(unresolved.len(), 1);
+        assert!(unresolved[0].is_error());
+        assert!(unresolved[0].message.contains("money.schema.json"));
+
+        std::fs::write(
+            schema_dir.join("money.schema.json"),
+            r#"{"type": "number"}"#,
+        )
+        .unwrap();
+        let diagnostics = crate::graph::DependencyGraph::diagnose(json, Some(ontology.path()));
+        assert!(!diagnostics.iter().any(|d| d.is_error()));
+    }
+}
@@ -0,0 +1,188 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Deterministic scorer of a Verification edge. Instead of asking the agent
+/// for a score, the engine runs `command` in the work dir and derives the
+/// score from its result.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Verifier {
+    pub command: String,
+    #[serde(default)]
+    pub score: ScoreSource,
+    /// Divides the extracted value, e.g. 100 for a percentage.
+    #[serde(default = "Verifier::default_scale")]
+    pub scale: f64,
+}
+
+/// Where the score of a verifier comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "from", rename_all = "camelCase")]
+pub enum ScoreSource {
+    /// 1.0 when the command exits with 0, 0.0 otherwise.
+    #[default]
+    ExitCode,
+    /// Number at a JSON pointer of the command's stdout, parsed as JSON.
+    Json { pointer: String },
+    /// Last number printed after `label` in the command's output, e.g.
+    /// `coverage:`.
+    Metric { label: String },
+}
+
+impl Verifier {
+    fn default_scale() -> f64 {
+        1.0
+    }
+
+    /// Turns the result of running the command (see `CommandAgent`) into a
+    /// verification report: the result plus the derived `score` and a
+    /// `feedback` holding the command's output for the Refinement edge.
+    pub fn report(&self, result: &Value) -> Value {
+        let text = |field: &str| result.get(field).and_then(|v| v.as_str()).unwrap_or("");
+        let exit_code = result
+            .get("exitCode")
+            .and_then(|v| v.as_i64())
+            .map_or("none".to_string(), |code| code.to_string());
+
+        let (score, problem) = match self.extract(result) {
+            Ok(score) => (score, None),
+            Err(e) => (0.0, Some(e.to_string())),
+        };
+
+        let mut feedback = format!(
+            "`{}` exited with code {} (score {:.2}).",
+            self.command, exit_code, score
+        );
+        if let Some(problem) = problem {
+            feedback.push_str(&format!(" Could not read the score: {}.", problem));
+        }
+        for stream in ["stdout", "stderr"] {
+            if !text(stream).trim().is_empty() {
+                feedback.push_str(&format!("\n\n{}:\n{}", stream, text(stream).trim_end()));
+            }
+        }
+
+        let mut report = result.clone();
+        if let Some(object) = report.as_object_mut() {
+            object.insert("score".to_string(), serde_json::json!(score));
+            object.insert("feedback".to_string(), Value::String(feedback));
+        }
+        report
+    }
+
+    /// Score before it is compared with the pass threshold: the extracted
+    /// value divided by `scale`.
+    fn extract(&self, result: &Value) -> Result<f64> {
+        let stdout = result.get("stdout").and_then(|v| v.as_str()).unwrap_or("");
+        match &self.score {
+            ScoreSource::ExitCode => {
+                let success = result
+                    .get("success")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+                Ok(if success { 1.0 } else { 0.0 })
+            }
+            ScoreSource::Json { pointer } => {
+                let output: Value = serde_json::from_str(stdout.trim())
+                    .map_err(|e| anyhow::anyhow!("stdout is not JSON ({})", e))?;
+                output
+                    .pointer(pointer)
+                    .and_then(|v| v.as_f64())
+                    .map(|value| value / self.scale)
+                    .ok_or_else(|| anyhow::anyhow!("no number at '{}'", pointer))
+            }
+            ScoreSource::Metric { label } => {
+                let stderr = result.get("stderr").and_then(|v| v.as_str()).unwrap_or("");
+                let output = format!("{}\n{}", stdout, stderr);
+                let (_, after) = output
+                    .rsplit_once(label.as_str())
+                    .ok_or_else(|| anyhow::anyhow!("'{}' was not printed", label))?;
+                let number: String = after
+                    .trim_start()
+                    .chars()
+                    .enumerate()
+                    .take_while(|(i, c)| c.is_ascii_digit() || *c == '.' || (*i == 0 && *c == '-'))
+                    .map(|(_, c)| c)
+                    .collect();
+                number
+                    .parse::<f64>()
+                    .map(|value| value / self.scale)
+                    .map_err(|_| anyhow::anyhow!("no number after '{}'", label))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(success: bool, stdout: &str) -> Value {
+        serde_json::json!({
+            "command": "check",
+            "exitCode": if success { 0 } else { 1 },
+            "success": success,
+            "stdout": stdout,
+            "stderr": "",
+        })
+    }
+
+    #[test]
+    fn test_scores_are_derived_from_the_command_result() {
+        let exit_code: Verifier = serde_json::from_str(r#"{"command": "cargo test"}"#).unwrap();
+        assert_eq!(exit_code.report(&run(true, ""))["score"], 1.0);
+        let failed = exit_code.report(&run(false, "test parser ... FAILED\n"));
+        assert_eq!(failed["score"], 0.0);
+        assert_eq!(
+            failed["feedback"],
+            "`cargo test` exited with code 1 (score 0.00).\n\nstdout:\ntest parser ... FAILED"
+        );
+
+        let json: Verifier = serde_json::from_str(
+            r#"{"command": "report", "score": {"from": "json", "pointer": "/summary/passRate"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            json.report(&run(false, r#"{"summary": {"passRate": 0.75}}"#))["score"],
+            0.75
+        );
+
+        let metric: Verifier = serde_json::from_str(
+            r#"{"command": "coverage", "score": {"from": "metric", "label": "coverage:"}, "scale": 100}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            metric.report(&run(true, "coverage: 10%\nlines coverage: 87.5%\n"))["score"],
+            0.875
+        );
+        let missing = metric.report(&run(true, "no numbers here"));
+        assert_eq!(missing["score"], 0.0);
+        assert!(
+            missing["feedback"]
+                .as_str()
+                .unwrap()
+                .contains("Could not read the score: 'coverage:' was not printed")
+        );
+    }
+
+    #[test]
+    fn test_verifiers_only_score_verification_edges() {
+        let json = r#"[
+            { "source": { "name": "Dev", "type": "Agent" }, "target": { "name": "Code", "type": "Other" }, "type": { "name": "implements", "verbType": "Creation" }, "verifier": { "command": "cargo test" } }
+        ]"#;
+        let err = crate::graph::DependencyGraph::load_from_metamodel(json, None).unwrap_err();
+        assert!(err.to_string().contains("is not a Verification edge"));
+
+        let json = json.replace(
+            r#""implements", "verbType": "Creation""#,
+            r#""verifies", "verbType": "Verification""#,
+        );
+        let graph = crate::graph::DependencyGraph::load_from_metamodel(&json, None).unwrap();
+        assert_eq!(
+            graph
+                .verifier("Dev", "verifies", "Code")
+                .map(|v| v.command.as_str()),
+            Some("cargo test")
+        );
+    }
+}
@@ -0,0 +1,387 @@
+use crate::logging::LogEvent;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// File of an ontology directory declaring its hooks.
+pub const HOOKS_FILE: &str = "hooks.json";
+
+/// How long a notification hook without `timeoutSecs` may run. Approval
+/// hooks without one wait for their answer.
+pub const DEFAULT_HOOK_TIMEOUT_SECS: u64 = 30;
+
+/// Point of the lifecycle a hook fires at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HookEvent {
+    /// Before an action is handed to its agent. Approval hooks fire here.
+    BeforeDispatch,
+    /// After an artifact was created or refined and written to disk.
+    AfterPersist,
+    /// After a Verification edge scored below its threshold.
+    VerificationFailed,
+    /// When a run of the iteration ends, however it ended.
+    IterationEnd,
+}
+
+impl HookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookEvent::BeforeDispatch => "beforeDispatch",
+            HookEvent::AfterPersist => "afterPersist",
+            HookEvent::VerificationFailed => "verificationFailed",
+            HookEvent::IterationEnd => "iterationEnd",
+        }
+    }
+}
+
+/// Selects what a hook fires for. Every field given must match; an empty
+/// selector matches everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HookMatch {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entity: Option<String>,
+    #[serde(
+        rename = "entityType",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub entity_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relation: Option<String>,
+    #[serde(rename = "verbType", default, skip_serializing_if = "Option::is_none")]
+    pub verb_type: Option<String>,
+}
+
+/// What an event is about. Iteration-wide events have no subject.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HookSubject {
+    pub entity: Option<String>,
+    pub entity_type: Option<String>,
+    pub relation: Option<String>,
+    pub verb_type: Option<String>,
+}
+
+/// Command or HTTP callback run at points of the lifecycle, declared in the
+/// ontology's `hooks.json`. It receives the event's `LogEvent` as JSON: on
+/// stdin for a command, as the body of a POST for a URL.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hook {
+    pub name: String,
+    pub on: Vec<HookEvent>,
+    #[serde(rename = "match", default)]
+    pub matcher: HookMatch,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Blocks the dispatch until the hook allows or denies it.
+    #[serde(default)]
+    pub approval: bool,
+    /// Defaults to [`DEFAULT_HOOK_TIMEOUT_SECS`], and to none for approval
+    /// hooks.
+    #[serde(
+        rename = "timeoutSecs",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub timeout_secs: Option<u64>,
+}
+
+/// Answer of an approval hook.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+    Allow,
+    Deny(String),
+}
+
+impl Hook {
+    pub fn validate(&self) -> Result<()> {
+        if self.on.is_empty() {
+            anyhow::bail!("Hook '{}' does not fire on any event", self.name);
+        }
+        match (&self.command, &self.url) {
+            (Some(command), None) if !command.trim().is_empty() => {}
+            (None, Some(url)) if url.starts_with("http://") || url.starts_with("https://") => {}
+            (None, Some(url)) => {
+                anyhow::bail!("Hook '{}' has a non-HTTP url '{}'", self.name, url)
+            }
+            _ => anyhow::bail!("Hook '{}' must have either a command or a url", self.name),
+        }
+        if self.approval && self.on != [HookEvent::BeforeDispatch] {
+            anyhow::bail!(
+                "Approval hook '{}' can only fire on beforeDispatch",
+                self.name
+            );
+        }
+        Ok(())
+    }
+
+    pub fn matches(&self, event: HookEvent, subject: &HookSubject) -> bool {
+        let field = |wanted: &Option<String>, actual: &Option<String>| {
+            wanted
+                .as_ref()
+                .is_none_or(|wanted| Some(wanted) == actual.as_ref())
+        };
+        self.on.contains(&event)
+            && field(&self.matcher.entity, &subject.entity)
+            && field(&self.matcher.entity_type, &subject.entity_type)
+            && field(&self.matcher.relation, &subject.relation)
+            && field(&self.matcher.verb_type, &subject.verb_type)
+    }
+
+    /// How long the hook may run: its `timeoutSecs`, else the default for
+    /// notification hooks. An approval hook blocks until it answers.
+    pub fn timeout(&self) -> Option<u64> {
+        match (self.timeout_secs, self.approval) {
+            (Some(secs), _) => Some(secs),
+            (None, true) => None,
+            (None, false) => Some(DEFAULT_HOOK_TIMEOUT_SECS),
+        }
+    }
+
+    /// Runs the hook with `payload`. A hook that fails or denies is an error
+    /// for notification hooks; for approval hooks it is a denial.
+    pub async fn run(
+        &self,
+        event: HookEvent,
+        payload: &LogEvent,
+        work_dir: &Path,
+    ) -> Result<Decision> {
+        let body = serde_json::to_string(payload)?;
+        let timeout_secs = self.timeout();
+        let run = async {
+            match (&self.command, &self.url) {
+                (Some(command), _) => self.run_command(command, event, &body, work_dir).await,
+                (None, Some(url)) => self.post(url, &body, timeout_secs).await,
+                (None, None) => anyhow::bail!("Hook '{}' has nothing to run", self.name),
+            }
+        };
+        let (success, output) = match timeout_secs {
+            Some(secs) => tokio::time::timeout(Duration::from_secs(secs), run)
+                .await
+                .map_err(|_| anyhow::anyhow!("Hook '{}' timed out after {}s", self.name, secs))?,
+            None => run.await,
+        }?;
+
+        let decision = decision(success, &output);
+        match (&decision, self.approval) {
+            (Decision::Deny(reason), false) => {
+                anyhow::bail!("Hook '{}' failed: {}", self.name, reason)
+            }
+            _ => Ok(decision),
+        }
+    }
+
+    /// Runs the command in the work dir with the payload on stdin. Returns
+    /// whether it exited with 0, and its stdout.
+    async fn run_command(
+        &self,
+        command_line: &str,
+        event: HookEvent,
+        body: &str,
+        work_dir: &Path,
+    ) -> Result<(bool, String)> {
+        let mut cmd = if cfg!(windows) {
+            let mut cmd = Command::new("cmd");
+            cmd.arg("/C").arg(command_line);
+            cmd
+        } else {
+            let mut cmd = Command::new("sh");
+            cmd.arg("-c").arg(command_line);
+            cmd
+        };
+        cmd.current_dir(work_dir)
+            .env("PULPO_HOOK", &self.name)
+            .env("PULPO_HOOK_EVENT", event.as_str())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        let mut child = cmd
+            .spawn()
+            .with_context(|| format!("Failed to run hook '{}'", self.name))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            // A hook that does not read its payload closes stdin early
+            let _ = stdin.write_all(body.as_bytes()).await;
+        }
+        let output = child.wait_with_output().await?;
+        let mut text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if !output.status.success() && text.is_empty() {
+            text = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        }
+        Ok((output.status.success(), text))
+    }
+
+    /// POSTs the payload to the URL, giving up after `timeout_secs` if set.
+    /// Returns whether the status is 2xx, and the response body.
+    async fn post(
+        &self,
+        url: &str,
+        body: &str,
+        timeout_secs: Option<u64>,
+    ) -> Result<(bool, String)> {
+        let mut client = reqwest::Client::builder();
+        if let Some(secs) = timeout_secs {
+            client = client.timeout(Duration::from_secs(secs));
+        }
+        let response = client
+            .build()?
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.to_string())
+            .send()
+            .await
+            .with_context(|| format!("Hook '{}' could not reach {}", self.name, url))?;
+        let status = response.status();
+        let mut text = response.text().await.unwrap_or_default().trim().to_string();
+        if !status.is_success() && text.is_empty() {
+            text = status.to_string();
+        }
+        Ok((status.is_success(), text))
+    }
+}
+
+/// Reads the decision from a hook's output: a `{"decision": "allow"|"deny",
+/// "reason": ...}` object or a bare `allow`/`deny`. Anything else is decided
+/// by the exit code or HTTP status.
+fn decision(success: bool, output: &str) -> Decision {
+    let (word, reason) = match serde_json::from_str::<Value>(output) {
+        Ok(Value::Object(object)) => (
+            object
+                .get("decision")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_lowercase(),
+            object
+                .get("reason")
+                .and_then(|v| v.as_str())
+                .map(str::to_string),
+        ),
+        _ => (output.to_lowercase(), None),
+    };
+    match word.as_str() {
+        "allow" => Decision::Allow,
+        "deny" => Decision::Deny(reason.unwrap_or_else(|| "denied".to_string())),
+        _ if success => Decision::Allow,
+        _ if output.is_empty() => Decision::Deny("no decision".to_string()),
+        _ => Decision::Deny(output.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::LogEventType;
+
+    fn hook(json: Value) -> Hook {
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn dispatch_event() -> LogEvent {
+        LogEvent::info_with_details(
+            LogEventType::ActionDispatched,
+            "Dispatching Dev implements Code",
+            serde_json::json!({"agent": "Dev", "target": "Code"}),
+        )
+    }
+
+    #[test]
+    fn test_hooks_match_their_selector() {
+        let hook = hook(serde_json::json!({
+            "name": "notify",
+            "on": ["afterPersist"],
+            "match": {"entityType": "Code", "verbType": "Creation"},
+            "command": "true"
+        }));
+        hook.validate().unwrap();
+        let subject = HookSubject {
+            entity: Some("Code".to_string()),
+            entity_type: Some("Code".to_string()),
+            relation: Some("implements".to_string()),
+            verb_type: Some("Creation".to_string()),
+        };
+        assert!(hook.matches(HookEvent::AfterPersist, &subject));
+        assert!(!hook.matches(HookEvent::BeforeDispatch, &subject));
+        let refinement = HookSubject {
+            verb_type: Some("Refinement".to_string()),
+            ..subject
+        };
+        assert!(!hook.matches(HookEvent::AfterPersist, &refinement));
+        assert!(!hook.matches(HookEvent::AfterPersist, &HookSubject::default()));
+
+        let err = serde_json::from_value::<Hook>(serde_json::json!({
+            "name": "gate", "on": ["afterPersist"], "command": "true", "approval": true
+        }))
+        .unwrap()
+        .validate()
+        .unwrap_err();
+        assert!(err.to_string().contains("can only fire on beforeDispatch"));
+    }
+
+    #[tokio::test]
+    async fn test_command_hooks_get_the_event_and_decide() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let notify = hook(serde_json::json!({
+            "name": "notify",
+            "on": ["beforeDispatch"],
+            "command": "cat > payload.json; echo $PULPO_HOOK_EVENT > event.txt"
+        }));
+        assert_eq!(
+            notify
+                .run(HookEvent::BeforeDispatch, &dispatch_event(), dir.path())
+                .await?,
+            Decision::Allow
+        );
+        let payload: Value =
+            serde_json::from_str(&std::fs::read_to_string(dir.path().join("payload.json"))?)?;
+        assert_eq!(payload["event_type"], "action_dispatched");
+        assert_eq!(payload["details"]["target"], "Code");
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("event.txt"))?,
+            "beforeDispatch\n"
+        );
+
+        let gate = hook(serde_json::json!({
+            "name": "gate",
+            "on": ["beforeDispatch"],
+            "approval": true,
+            "command": "echo '{\"decision\": \"deny\", \"reason\": \"frozen\"}'"
+        }));
+        assert_eq!(
+            gate.run(HookEvent::BeforeDispatch, &dispatch_event(), dir.path())
+                .await?,
+            Decision::Deny("frozen".to_string())
+        );
+
+        let failing = hook(serde_json::json!({
+            "name": "failing", "on": ["afterPersist"], "command": "echo broken >&2; exit 2"
+        }));
+        let err = failing
+            .run(HookEvent::AfterPersist, &dispatch_event(), dir.path())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Hook 'failing' failed: broken"));
+
+        let slow = hook(serde_json::json!({
+            "name": "slow", "on": ["afterPersist"], "command": "sleep 5", "timeoutSecs": 1
+        }));
+        let err = slow
+            .run(HookEvent::AfterPersist, &dispatch_event(), dir.path())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Hook 'slow' timed out after 1s"));
+        assert_eq!(failing.timeout(), Some(DEFAULT_HOOK_TIMEOUT_SECS));
+        assert_eq!(gate.timeout(), None);
+
+        assert_eq!(decision(false, "allow"), Decision::Allow);
+        assert_eq!(decision(true, "DENY"), Decision::Deny("denied".to_string()));
+        Ok(())
+    }
+}
@@ -42,6 +42,8 @@ pub mod mocks {
     pub struct MockUserInteraction {
         pub feature_responses: Arc<Mutex<VecDeque<String>>>,
         pub confirmations: Arc<Mutex<VecDeque<bool>>>,
+        pub user_inputs: Arc<Mutex<VecDeque<String>>>,
+        pub selections: Arc<Mutex<VecDeque<usize>>>,
     }
 
     impl MockUserInteraction {
@@ -56,12 +58,25 @@ pub mod mocks {
         pub fn add_confirmation(&self, response: bool) {
             self.confirmations.lock().unwrap().push_back(response);
         }
+
+        pub fn add_user_input(&self, response: String) {
+            self.user_inputs.lock().unwrap().push_back(response);
+        }
+
+        pub fn add_selection(&self, index: usize) {
+            self.selections.lock().unwrap().push_back(index);
+        }
     }
 
     #[async_trait]
     impl UserInteraction for MockUserInteraction {
         async fn ask_user(&self, _prompt: &str) -> Result<String> {
-            Ok("MOCK_USER_INPUT".to_string())
+            Ok(self
+                .user_inputs
+                .lock()
+                .unwrap()
+                .pop_front()
+                .unwrap_or_else(|| "MOCK_USER_INPUT".to_string()))
         }
 
         async fn ask_for_feature(&self, _prompt: &str) -> Result<String> {
@@ -83,7 +98,7 @@ pub mod mocks {
         }
 
         async fn select_option(&self, _prompt: &str, _options: &[String]) -> Result<usize> {
-            Ok(0)
+            Ok(self.selections.lock().unwrap().pop_front().unwrap_or(0))
         }
 
         fn start_step(&self, _name: &str) {}
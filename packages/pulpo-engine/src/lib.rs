@@ -1,9 +1,19 @@
 // Legacy modules removed: plan, product, spec
 
 pub mod agents;
+pub mod clarification;
+pub mod collection;
 pub mod config;
 pub mod domain;
+pub mod git;
 pub mod graph;
+pub mod hooks;
 pub mod interaction;
+pub mod lifecycle;
+pub mod lock;
 pub mod logging;
 pub mod orchestrator;
+pub mod planner;
+pub mod provenance;
+pub mod state;
+pub mod usage;
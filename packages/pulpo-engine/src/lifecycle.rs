@@ -0,0 +1,257 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Lifecycle state of a single ontology node (artifact) during an iteration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeState {
+    /// Not produced yet and no agent creates it (must be provided externally).
+    Pending,
+    /// An agent can create it, but one or more Dependency edges are unmet.
+    Blocked,
+    /// All dependencies are met; the creation action can be dispatched.
+    Ready,
+    /// An action targeting this node is currently in flight.
+    Running,
+    /// Produced and not subject to verification.
+    Created,
+    /// Produced and waiting for a Verification edge to run.
+    AwaitingVerification,
+    /// Verification failed and a refinement attempt is still allowed.
+    NeedsRefinement,
+    /// Verification passed.
+    Verified,
+    /// Produced from upstream artifacts that have changed since; refinement is due.
+    Stale,
+    /// Verification failed and the refinement retries are used up.
+    Exhausted,
+    /// The last action on this node failed.
+    Failed,
+    /// The node was deliberately skipped.
+    Skipped,
+}
+
+impl NodeState {
+    /// Whether an artifact exists for a node in this state.
+    pub fn has_artifact(&self) -> bool {
+        matches!(
+            self,
+            Self::Created
+                | Self::AwaitingVerification
+                | Self::NeedsRefinement
+                | Self::Verified
+                | Self::Stale
+                | Self::Exhausted
+        )
+    }
+
+    /// States that can be recomputed from the graph and the produced artifacts.
+    pub fn is_derived(&self) -> bool {
+        matches!(self, Self::Pending | Self::Blocked | Self::Ready)
+    }
+
+    /// Validated transition table. Any state may be reset to `Pending`.
+    pub fn can_transition_to(&self, to: NodeState) -> bool {
+        use NodeState::*;
+        if *self == to || to == Pending {
+            return true;
+        }
+        match self {
+            Pending | Blocked | Ready => matches!(
+                to,
+                Blocked | Ready | Running | Created | AwaitingVerification | Skipped
+            ),
+            Running => matches!(
+                to,
+                Ready
+                    | Created
+                    | AwaitingVerification
+                    | NeedsRefinement
+                    | Verified
+                    | Exhausted
+                    | Failed
+                    | Skipped
+            ),
+            Created | AwaitingVerification | Verified => {
+                matches!(to, Running | AwaitingVerification | NeedsRefinement | Stale)
+            }
+            NeedsRefinement => matches!(to, Running | Exhausted | Skipped | Stale),
+            Exhausted => matches!(to, Running | NeedsRefinement | Stale),
+            Stale => matches!(to, Running | Skipped),
+            Failed => matches!(to, Ready | Running | Skipped),
+            Skipped => matches!(to, Ready | Blocked),
+        }
+    }
+}
+
+impl std::fmt::Display for NodeState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Self::Pending => "Pending",
+            Self::Blocked => "Blocked",
+            Self::Ready => "Ready",
+            Self::Running => "Running",
+            Self::Created => "Created",
+            Self::AwaitingVerification => "AwaitingVerification",
+            Self::NeedsRefinement => "NeedsRefinement",
+            Self::Verified => "Verified",
+            Self::Stale => "Stale",
+            Self::Exhausted => "Exhausted",
+            Self::Failed => "Failed",
+            Self::Skipped => "Skipped",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// The state of a node together with a human readable explanation, e.g. which
+/// dependency it is blocked on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeStatus {
+    pub node: String,
+    pub state: NodeState,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeRecord {
+    pub state: NodeState,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Recorded per-node states. Nodes without a record are `Pending`.
+#[derive(Debug, Clone, Default)]
+pub struct NodeLifecycle {
+    records: HashMap<String, NodeRecord>,
+}
+
+impl NodeLifecycle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn state(&self, node: &str) -> NodeState {
+        self.records
+            .get(node)
+            .map(|r| r.state)
+            .unwrap_or(NodeState::Pending)
+    }
+
+    pub fn record(&self, node: &str) -> Option<&NodeRecord> {
+        self.records.get(node)
+    }
+
+    /// Moves `node` to `to`, returning the previous state. Fails if the
+    /// transition is not allowed by [`NodeState::can_transition_to`].
+    pub fn transition(
+        &mut self,
+        node: &str,
+        to: NodeState,
+        reason: Option<String>,
+    ) -> Result<NodeState> {
+        let from = self.state(node);
+        if !from.can_transition_to(to) {
+            anyhow::bail!(
+                "Invalid lifecycle transition for {}: {} -> {}",
+                node,
+                from,
+                to
+            );
+        }
+        self.records
+            .insert(node.to_string(), NodeRecord { state: to, reason });
+        Ok(from)
+    }
+
+    /// Records a state derived from the graph and the produced artifacts
+    /// (e.g. `Blocked` or an artifact loaded from disk). Unlike
+    /// [`NodeLifecycle::transition`] this reflects an observation rather than
+    /// an event, so it is not validated.
+    pub fn observe(&mut self, node: &str, state: NodeState, reason: Option<String>) {
+        self.records
+            .insert(node.to_string(), NodeRecord { state, reason });
+    }
+
+    /// Forgets everything recorded for `node`, making it `Pending` again.
+    pub fn reset(&mut self, node: &str) {
+        self.records.remove(node);
+    }
+
+    pub fn to_map(&self) -> BTreeMap<String, NodeRecord> {
+        self.records
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+
+    pub fn from_map(map: BTreeMap<String, NodeRecord>) -> Self {
+        Self {
+            records: map.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_creation_and_verification_path() {
+        let mut lifecycle = NodeLifecycle::new();
+        assert_eq!(lifecycle.state("Code"), NodeState::Pending);
+
+        for to in [
+            NodeState::Ready,
+            NodeState::Running,
+            NodeState::AwaitingVerification,
+            NodeState::Running,
+            NodeState::NeedsRefinement,
+            NodeState::Running,
+            NodeState::AwaitingVerification,
+            NodeState::Running,
+            NodeState::Verified,
+        ] {
+            lifecycle.transition("Code", to, None).unwrap();
+        }
+        assert_eq!(lifecycle.state("Code"), NodeState::Verified);
+    }
+
+    #[test]
+    fn test_invalid_transition_is_rejected() {
+        let mut lifecycle = NodeLifecycle::new();
+        let err = lifecycle
+            .transition("Code", NodeState::Verified, None)
+            .unwrap_err();
+        assert!(err.to_string().contains("Pending -> Verified"));
+        assert_eq!(lifecycle.state("Code"), NodeState::Pending);
+
+        lifecycle
+            .transition("Code", NodeState::Skipped, Some("by policy".into()))
+            .unwrap();
+        assert!(
+            lifecycle
+                .transition("Code", NodeState::Running, None)
+                .is_err()
+        );
+        assert_eq!(
+            lifecycle.record("Code").unwrap().reason.as_deref(),
+            Some("by policy")
+        );
+    }
+
+    #[test]
+    fn test_reset_and_round_trip() {
+        let mut lifecycle = NodeLifecycle::new();
+        lifecycle
+            .transition("Spec", NodeState::Created, None)
+            .unwrap();
+        let restored = NodeLifecycle::from_map(lifecycle.to_map());
+        assert_eq!(restored.state("Spec"), NodeState::Created);
+
+        lifecycle.reset("Spec");
+        assert_eq!(lifecycle.state("Spec"), NodeState::Pending);
+    }
+}
@@ -0,0 +1,388 @@
+use crate::graph::find_ontology_file;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// File, next to the ontology file, pinning the versions and content hashes of
+/// everything the ontology is made of.
+pub const LOCK_FILE: &str = "ontology.lock";
+
+/// File of an ontology directory whose `version` is the ontology's version.
+pub const MANIFEST_FILE: &str = "package.json";
+
+/// What a locked file defines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LockedKind {
+    Ontology,
+    Hooks,
+    Agent,
+    Schema,
+    Prompt,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockEntry {
+    pub kind: LockedKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    pub hash: String,
+}
+
+/// Versions and SHA-256 hashes of the files of an ontology and of the
+/// ontologies it imports. Files are keyed by their path relative to their
+/// ontology, prefixed with `<namespace>:` for imported ones.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OntologyLock {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Hash of all the entries, identifying the ontology as a whole.
+    pub hash: String,
+    pub entries: BTreeMap<String, LockEntry>,
+}
+
+impl OntologyLock {
+    /// Locks the ontology at `root` as it is on disk. Fails if any declared
+    /// version is not a semantic version.
+    pub fn compute(root: &Path, imports: &HashMap<String, PathBuf>) -> Result<Self> {
+        let (lock, problems) = Self::scan(root, imports);
+        if !problems.is_empty() {
+            anyhow::bail!("{}", problems.join("\n"));
+        }
+        Ok(lock)
+    }
+
+    /// Like `compute`, but returns the invalid versions instead of failing.
+    pub fn scan(root: &Path, imports: &HashMap<String, PathBuf>) -> (Self, Vec<String>) {
+        let mut entries = BTreeMap::new();
+        let mut problems = Vec::new();
+        let namespaces = std::iter::once((String::new(), root.to_path_buf())).chain(
+            imports
+                .iter()
+                .map(|(ns, dir)| (format!("{}:", ns), dir.clone())),
+        );
+        for (prefix, dir) in namespaces {
+            for (path, kind) in Self::files(&dir) {
+                let Ok(content) = std::fs::read_to_string(&path) else {
+                    continue;
+                };
+                let relative = path.strip_prefix(&dir).unwrap_or(&path);
+                let key = format!("{}{}", prefix, relative.display());
+                let version = match kind {
+                    LockedKind::Ontology => manifest_version(&dir),
+                    LockedKind::Hooks => None,
+                    LockedKind::Schema => json_version(&content),
+                    LockedKind::Agent | LockedKind::Prompt => match path.extension() {
+                        Some(ext) if ext == "json" => json_version(&content),
+                        _ => front_matter_version(&content),
+                    },
+                };
+                if let Some(version) = &version
+                    && let Err(e) = semver::Version::parse(version)
+                {
+                    problems.push(format!("{}: invalid version '{}': {}", key, version, e));
+                }
+                let hash = hex(Sha256::digest(content.as_bytes()).as_slice());
+                entries.insert(
+                    key,
+                    LockEntry {
+                        kind,
+                        version,
+                        hash,
+                    },
+                );
+            }
+        }
+
+        let mut hasher = Sha256::new();
+        for (key, entry) in &entries {
+            hasher.update(key.as_bytes());
+            hasher.update(entry.hash.as_bytes());
+        }
+        let lock = Self {
+            version: manifest_version(root),
+            hash: hex(hasher.finalize().as_slice()),
+            entries,
+        };
+        (lock, problems)
+    }
+
+    /// Files an ontology is made of, sorted, with what they define.
+    fn files(root: &Path) -> Vec<(PathBuf, LockedKind)> {
+        let mut files = vec![(root.join(crate::hooks::HOOKS_FILE), LockedKind::Hooks)];
+        if let Some(ontology) = find_ontology_file(root) {
+            files.insert(0, (ontology, LockedKind::Ontology));
+        }
+        let listings = [
+            ("agent/system_prompt", "md", LockedKind::Agent),
+            ("agent", "json", LockedKind::Agent),
+            ("relationship/prompt", "md", LockedKind::Prompt),
+        ];
+        for (dir, extension, kind) in listings {
+            let mut found: Vec<PathBuf> = std::fs::read_dir(root.join(dir))
+                .into_iter()
+                .flatten()
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == extension))
+                .collect();
+            found.sort();
+            files.extend(found.into_iter().map(|path| (path, kind)));
+        }
+        let mut schemas = Vec::new();
+        collect_json(&root.join("artifact/schema"), &mut schemas);
+        schemas.sort();
+        files.extend(schemas.into_iter().map(|path| (path, LockedKind::Schema)));
+        files.retain(|(path, _)| path.is_file());
+        files
+    }
+
+    /// Reads the lock of the ontology at `root`, if it has one.
+    pub fn load(root: &Path) -> Result<Option<Self>> {
+        Self::read(&root.join(LOCK_FILE))
+    }
+
+    pub fn read(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let lock = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        Ok(Some(lock))
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, content + "\n")
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Locks the ontology at `root` and updates its `ontology.lock`. Fails if
+    /// a versioned definition changed since the previous lock while keeping
+    /// its version: a published version is immutable.
+    pub fn sync(root: &Path, imports: &HashMap<String, PathBuf>) -> Result<Self> {
+        let lock = Self::compute(root, imports)?;
+        let previous = Self::load(root)?;
+        if let Some(previous) = &previous {
+            lock.check_immutable(previous)?;
+        }
+        // An ontology installed read-only still runs, just unlocked on disk
+        if previous.as_ref() != Some(&lock)
+            && let Err(e) = lock.write(&root.join(LOCK_FILE))
+        {
+            tracing::warn!("{:#}", e);
+        }
+        Ok(lock)
+    }
+
+    /// Fails if a definition versioned in both locks has the same version but
+    /// a different content.
+    pub fn check_immutable(&self, previous: &OntologyLock) -> Result<()> {
+        let changed: Vec<String> = self
+            .entries
+            .iter()
+            .filter_map(|(key, entry)| {
+                let old = previous.entries.get(key)?;
+                let version = entry.version.as_ref()?;
+                (old.version.as_ref() == Some(version) && old.hash != entry.hash)
+                    .then(|| format!("{} changed without a version bump (still {})", key, version))
+            })
+            .collect();
+        if !changed.is_empty() {
+            anyhow::bail!(
+                "{}\nBump the version of each changed definition, or delete {} to re-lock the ontology",
+                changed.join("\n"),
+                LOCK_FILE
+            );
+        }
+        Ok(())
+    }
+
+    /// What changed from `locked` to this lock, one line per file.
+    pub fn drift(&self, locked: &OntologyLock) -> Vec<String> {
+        let mut changes = Vec::new();
+        for (key, entry) in &self.entries {
+            match locked.entries.get(key) {
+                None => changes.push(format!("added {}", key)),
+                Some(old) if old.hash != entry.hash => {
+                    let versions = match (&old.version, &entry.version) {
+                        (Some(old), Some(new)) if old != new => format!(" ({} -> {})", old, new),
+                        _ => String::new(),
+                    };
+                    changes.push(format!("changed {}{}", key, versions));
+                }
+                Some(_) => {}
+            }
+        }
+        for key in locked.entries.keys() {
+            if !self.entries.contains_key(key) {
+                changes.push(format!("removed {}", key));
+            }
+        }
+        changes
+    }
+}
+
+/// Splits a Markdown file into its YAML front matter, if it starts with one
+/// delimited by `---` lines, and the rest.
+pub fn split_front_matter(content: &str) -> (Option<&str>, &str) {
+    let Some(rest) = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))
+    else {
+        return (None, content);
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            let body = &rest[offset + line.len()..];
+            return (Some(&rest[..offset]), body.trim_start_matches(['\r', '\n']));
+        }
+        offset += line.len();
+    }
+    (None, content)
+}
+
+/// Version declared in the front matter of a Markdown definition.
+pub fn front_matter_version(content: &str) -> Option<String> {
+    let (front_matter, _) = split_front_matter(content);
+    let yaml: serde_yaml::Value = serde_yaml::from_str(front_matter?).ok()?;
+    scalar(yaml.get("version")?)
+}
+
+fn json_version(content: &str) -> Option<String> {
+    let json: serde_json::Value = serde_json::from_str(content).ok()?;
+    json.get("version")?.as_str().map(str::to_string)
+}
+
+fn manifest_version(root: &Path) -> Option<String> {
+    json_version(&std::fs::read_to_string(root.join(MANIFEST_FILE)).ok()?)
+}
+
+// YAML reads `version: 1.0` as a number
+fn scalar(value: &serde_yaml::Value) -> Option<String> {
+    match value {
+        serde_yaml::Value::String(s) => Some(s.clone()),
+        serde_yaml::Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+fn collect_json(dir: &Path, found: &mut Vec<PathBuf>) {
+    for entry in std::fs::read_dir(dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_json(&path, found);
+        } else if path.extension().is_some_and(|ext| ext == "json") {
+            found.push(path);
+        }
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_front_matter_is_split_from_the_body() {
+        let content = "---\nversion: 1.2.0\nauthor: pulpo\n---\n\n# Architect\nDesign.";
+        let (front_matter, body) = split_front_matter(content);
+        assert_eq!(front_matter, Some("version: 1.2.0\nauthor: pulpo\n"));
+        assert_eq!(body, "# Architect\nDesign.");
+        assert_eq!(front_matter_version(content).as_deref(), Some("1.2.0"));
+
+        let plain = "# Architect\n---\nDesign.";
+        assert_eq!(split_front_matter(plain), (None, plain));
+        assert_eq!(
+            front_matter_version("---\nversion: 1.0\n---\n").as_deref(),
+            Some("1.0")
+        );
+    }
+
+    #[test]
+    fn test_versioned_definitions_are_immutable_once_locked() {
+        let root = tempfile::tempdir().unwrap();
+        let write = |path: &str, content: &str| {
+            let path = root.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        };
+        write("ontology.json", "[]");
+        write(MANIFEST_FILE, r#"{"name": "demo", "version": "0.2.0"}"#);
+        write(
+            "agent/system_prompt/Dev.md",
+            "---\nversion: 1.0.0\n---\nYou code.",
+        );
+        write(
+            "relationship/prompt/Dev_writes_Code.md",
+            "Write {{target}}.",
+        );
+        write(
+            "artifact/schema/code.schema.json",
+            r#"{"version": "2.1.0"}"#,
+        );
+
+        let lock = OntologyLock::sync(root.path(), &HashMap::new()).unwrap();
+        assert_eq!(lock.version.as_deref(), Some("0.2.0"));
+        assert_eq!(lock.entries.len(), 4);
+        assert_eq!(
+            lock.entries["agent/system_prompt/Dev.md"].kind,
+            LockedKind::Agent
+        );
+        assert_eq!(
+            lock.entries["artifact/schema/code.schema.json"]
+                .version
+                .as_deref(),
+            Some("2.1.0")
+        );
+        assert_eq!(OntologyLock::load(root.path()).unwrap(), Some(lock.clone()));
+
+        // Unversioned definitions may change; the lock follows them
+        write(
+            "relationship/prompt/Dev_writes_Code.md",
+            "Write {{target}} well.",
+        );
+        let relocked = OntologyLock::sync(root.path(), &HashMap::new()).unwrap();
+        assert_ne!(relocked.hash, lock.hash);
+        assert_eq!(
+            relocked.drift(&lock),
+            vec!["changed relationship/prompt/Dev_writes_Code.md"]
+        );
+
+        write(
+            "agent/system_prompt/Dev.md",
+            "---\nversion: 1.0.0\n---\nYou code fast.",
+        );
+        let err = OntologyLock::sync(root.path(), &HashMap::new()).unwrap_err();
+        assert!(
+            err.to_string().contains(
+                "agent/system_prompt/Dev.md changed without a version bump (still 1.0.0)"
+            )
+        );
+
+        write(
+            "agent/system_prompt/Dev.md",
+            "---\nversion: 1.1.0\n---\nYou code fast.",
+        );
+        let bumped = OntologyLock::sync(root.path(), &HashMap::new()).unwrap();
+        assert_eq!(
+            bumped.drift(&relocked),
+            vec!["changed agent/system_prompt/Dev.md (1.0.0 -> 1.1.0)"]
+        );
+
+        write("artifact/schema/code.schema.json", r#"{"version": "v2"}"#);
+        let err = OntologyLock::compute(root.path(), &HashMap::new()).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("artifact/schema/code.schema.json: invalid version 'v2'")
+        );
+    }
+}
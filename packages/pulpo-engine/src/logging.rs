@@ -1,3 +1,5 @@
+use crate::graph::executor::ExecutionOptions;
+use crate::usage::{DispatchUsage, UsageReport};
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
@@ -9,6 +11,8 @@ use tokio::io::AsyncWriteExt;
 pub enum LogEventType {
     IterationStart,
     IterationResumed,
+    IterationInterrupted,
+    IterationRolledBack,
     IterationEnd,
     LoopCycle,
     ActionIdentified,
@@ -17,9 +21,21 @@ pub enum LogEventType {
     PromptSent,
     ResponseReceived,
     ArtifactPersisted,
+    ArtifactEdited,
+    ArtifactReused,
+    ArtifactCommitted,
+    Clarification,
+    HookRun,
+    OntologyDrift,
     ValidationResult,
     VerificationResult,
     RefinementAttempt,
+    ActionAttempt,
+    NodeStateChanged,
+    NodesInvalidated,
+    DispatchUsage,
+    UsageSummary,
+    BudgetExceeded,
     Error,
     Info,
 }
@@ -99,6 +115,71 @@ impl LogEvent {
     pub fn error_with_details(message: impl Into<String>, details: serde_json::Value) -> Self {
         Self::new(LogEventType::Error, LogLevel::Error, message, Some(details))
     }
+
+    /// An action being dispatched to an agent.
+    pub fn action_dispatched(
+        agent: &str,
+        relation: &str,
+        target: &str,
+        category: &str,
+        options: &ExecutionOptions,
+        resolution: &str,
+    ) -> Self {
+        Self::info_with_details(
+            LogEventType::ActionDispatched,
+            format!("Dispatching: {} {} {}", agent, relation, target),
+            serde_json::json!({
+                "agent": agent,
+                "relation": relation,
+                "target": target,
+                "category": category,
+                "model_type": options.model_type,
+                "model": options.model,
+                "ai_cli": options.ai_cli,
+                "resolution": resolution,
+            }),
+        )
+    }
+
+    /// A verification result with its score.
+    pub fn verification(target: &str, score: f64, threshold: f64, feedback: &str) -> Self {
+        let passed = score >= threshold;
+        let level = if passed {
+            LogLevel::Info
+        } else {
+            LogLevel::Warn
+        };
+        Self::new(
+            LogEventType::VerificationResult,
+            level,
+            format!(
+                "Verification {} for {} (score: {:.2}, threshold: {:.2})",
+                if passed { "passed" } else { "failed" },
+                target,
+                score,
+                threshold,
+            ),
+            Some(serde_json::json!({
+                "target": target,
+                "score": score,
+                "threshold": threshold,
+                "passed": passed,
+                "feedback": feedback,
+            })),
+        )
+    }
+
+    /// An artifact written to `path`, relative to the work directory.
+    pub fn artifact_persisted(name: &str, path: &str) -> Self {
+        Self::info_with_details(
+            LogEventType::ArtifactPersisted,
+            format!("Persisted {} to {}", name, path),
+            serde_json::json!({
+                "name": name,
+                "path": path,
+            }),
+        )
+    }
 }
 
 /// Manages writing structured JSONL log files for an iteration.
@@ -165,6 +246,90 @@ impl IterationLogger {
         .await
     }
 
+    /// Convenience: log an iteration rolled back to the commit made after a node.
+    pub async fn log_iteration_rolled_back(
+        &self,
+        node: &str,
+        sha: &str,
+        actions_executed: usize,
+    ) -> Result<()> {
+        self.log(LogEvent::warn_with_details(
+            LogEventType::IterationRolledBack,
+            format!("Rolled back to {} ({})", node, sha),
+            serde_json::json!({
+                "node": node,
+                "sha": sha,
+                "actions_executed": actions_executed,
+            }),
+        ))
+        .await
+    }
+
+    /// Convenience: log a cancelled iteration and the actions that were in flight.
+    pub async fn log_iteration_interrupted(&self, in_flight: &[String]) -> Result<()> {
+        self.log(LogEvent::warn_with_details(
+            LogEventType::IterationInterrupted,
+            format!(
+                "Iteration interrupted with {} action(s) in flight",
+                in_flight.len()
+            ),
+            serde_json::json!({ "in_flight": in_flight }),
+        ))
+        .await
+    }
+
+    /// Convenience: log what a single dispatch cost.
+    pub async fn log_dispatch_usage(
+        &self,
+        agent: &str,
+        relation: &str,
+        target: &str,
+        usage: &DispatchUsage,
+    ) -> Result<()> {
+        self.log(LogEvent::info_with_details(
+            LogEventType::DispatchUsage,
+            format!(
+                "{} {} {} took {}ms",
+                agent, relation, target, usage.duration_ms
+            ),
+            serde_json::json!({
+                "agent": agent,
+                "relation": relation,
+                "target": target,
+                "prompt_chars": usage.prompt_chars,
+                "response_chars": usage.response_chars,
+                "duration_ms": usage.duration_ms,
+                "input_tokens": usage.tokens.map(|t| t.input),
+                "output_tokens": usage.tokens.map(|t| t.output),
+                "total_tokens": usage.tokens.map(|t| t.total),
+            }),
+        ))
+        .await
+    }
+
+    /// Convenience: log the usage totals per agent, per edge and for the iteration.
+    pub async fn log_usage_summary(&self, report: &UsageReport) -> Result<()> {
+        self.log(LogEvent::info_with_details(
+            LogEventType::UsageSummary,
+            format!(
+                "{} dispatch(es), {} tokens",
+                report.iteration.dispatches, report.iteration.total_tokens
+            ),
+            serde_json::to_value(report)?,
+        ))
+        .await
+    }
+
+    /// Convenience: log that a budget stopped or paused the run.
+    pub async fn log_budget_exceeded(&self, reason: &str, action: &str) -> Result<()> {
+        self.log(LogEvent::warn_with_details(
+            LogEventType::BudgetExceeded,
+            format!("Budget exceeded: {}", reason),
+            serde_json::json!({ "reason": reason, "action": action }),
+        ))
+        .await
+    }
+
     /// Convenience: log a loop cycle start.
     pub async fn log_loop_cycle(&self, cycle_number: usize) -> Result<()> {
         self.log(LogEvent::info_with_details(
@@ -203,16 +368,11 @@ impl IterationLogger {
         relation: &str,
         target: &str,
         category: &str,
+        options: &ExecutionOptions,
+        resolution: &str,
     ) -> Result<()> {
-        self.log(LogEvent::info_with_details(
-            LogEventType::ActionDispatched,
-            format!("Dispatching: {} {} {}", agent, relation, target),
-            serde_json::json!({
-                "agent": agent,
-                "relation": relation,
-                "target": target,
-                "category": category,
-            }),
+        self.log(LogEvent::action_dispatched(
+            agent, relation, target, category, options, resolution,
         ))
         .await
     }
@@ -305,44 +465,13 @@ impl IterationLogger {
         threshold: f64,
         feedback: &str,
     ) -> Result<()> {
-        let passed = score >= threshold;
-        let level = if passed {
-            LogLevel::Info
-        } else {
-            LogLevel::Warn
-        };
-        self.log(LogEvent::new(
-            LogEventType::VerificationResult,
-            level,
-            format!(
-                "Verification {} for {} (score: {:.2}, threshold: {:.2})",
-                if passed { "passed" } else { "failed" },
-                target,
-                score,
-                threshold,
-            ),
-            Some(serde_json::json!({
-                "target": target,
-                "score": score,
-                "threshold": threshold,
-                "passed": passed,
-                "feedback": feedback,
-            })),
-        ))
-        .await
+        self.log(LogEvent::verification(target, score, threshold, feedback))
+            .await
     }
 
     /// Convenience: log artifact persisted.
     pub async fn log_artifact_persisted(&self, name: &str, path: &str) -> Result<()> {
-        self.log(LogEvent::info_with_details(
-            LogEventType::ArtifactPersisted,
-            format!("Persisted {} to {}", name, path),
-            serde_json::json!({
-                "name": name,
-                "path": path,
-            }),
-        ))
-        .await
+        self.log(LogEvent::artifact_persisted(name, path)).await
     }
 
     /// Convenience: log a refinement attempt.
@@ -367,6 +496,145 @@ impl IterationLogger {
         .await
     }
 
+    /// Convenience: log the outcome of one attempt at an action
+    /// (`succeeded`, `retrying`, `skipped`, `paused` or `aborted`).
+    pub async fn log_action_attempt(
+        &self,
+        agent: &str,
+        relation: &str,
+        target: &str,
+        attempt: usize,
+        outcome: &str,
+        error: Option<&str>,
+    ) -> Result<()> {
+        let message = format!(
+            "Attempt {} of {} {} {}: {}",
+            attempt, agent, relation, target, outcome
+        );
+        let details = serde_json::json!({
+            "agent": agent,
+            "relation": relation,
+            "target": target,
+            "attempt": attempt,
+            "outcome": outcome,
+            "error": error,
+        });
+        let event = if error.is_some() {
+            LogEvent::warn_with_details(LogEventType::ActionAttempt, message, details)
+        } else {
+            LogEvent::info_with_details(LogEventType::ActionAttempt, message, details)
+        };
+        self.log(event).await
+    }
+
+    /// Convenience: log a node lifecycle transition.
+    pub async fn log_node_state_changed(
+        &self,
+        node: &str,
+        from: &str,
+        to: &str,
+        reason: Option<&str>,
+    ) -> Result<()> {
+        self.log(LogEvent::info_with_details(
+            LogEventType::NodeStateChanged,
+            format!("{}: {} -> {}", node, from, to),
+            serde_json::json!({
+                "node": node,
+                "from": from,
+                "to": to,
+                "reason": reason,
+            }),
+        ))
+        .await
+    }
+
+    /// Convenience: log an artifact edited outside the engine (e.g. by hand in the docs folder).
+    pub async fn log_artifact_edited(&self, name: &str, path: &str) -> Result<()> {
+        self.log(LogEvent::info_with_details(
+            LogEventType::ArtifactEdited,
+            format!("Artifact {} was edited in {}", name, path),
+            serde_json::json!({ "name": name, "path": path }),
+        ))
+        .await
+    }
+
+    /// Convenience: log an application-scoped artifact reused from an earlier iteration.
+    pub async fn log_artifact_reused(&self, name: &str, path: &str) -> Result<()> {
+        self.log(LogEvent::info_with_details(
+            LogEventType::ArtifactReused,
+            format!("Reused application artifact {} from {}", name, path),
+            serde_json::json!({ "name": name, "path": path }),
+        ))
+        .await
+    }
+
+    /// Convenience: log the commit made after an artifact was produced.
+    pub async fn log_artifact_committed(
+        &self,
+        name: &str,
+        sha: &str,
+        branch: Option<&str>,
+    ) -> Result<()> {
+        self.log(LogEvent::info_with_details(
+            LogEventType::ArtifactCommitted,
+            format!("Committed {} as {}", name, sha),
+            serde_json::json!({ "name": name, "sha": sha, "branch": branch }),
+        ))
+        .await
+    }
+
+    /// Convenience: log the questions an agent asked and the user's answers.
+    pub async fn log_clarification(
+        &self,
+        key: &str,
+        round: usize,
+        answers: &[crate::clarification::Answer],
+    ) -> Result<()> {
+        self.log(LogEvent::info_with_details(
+            LogEventType::Clarification,
+            format!("Answered {} question(s) for {}", answers.len(), key),
+            serde_json::json!({ "key": key, "round": round, "answers": answers }),
+        ))
+        .await
+    }
+
+    /// Convenience: log a lifecycle hook that ran, and what came of it.
+    pub async fn log_hook_run(&self, hook: &str, event: &str, outcome: &str) -> Result<()> {
+        self.log(LogEvent::info_with_details(
+            LogEventType::HookRun,
+            format!("Hook {} ran on {}: {}", hook, event, outcome),
+            serde_json::json!({ "hook": hook, "event": event, "outcome": outcome }),
+        ))
+        .await
+    }
+
+    /// Convenience: log the changes of the ontology since the iteration locked it.
+    pub async fn log_ontology_drift(&self, locked_hash: &str, changes: &[String]) -> Result<()> {
+        self.log(LogEvent::warn_with_details(
+            LogEventType::OntologyDrift,
+            format!(
+                "The ontology drifted from its lock: {} change(s)",
+                changes.len()
+            ),
+            serde_json::json!({ "locked_hash": locked_hash, "changes": changes }),
+        ))
+        .await
+    }
+
+    /// Convenience: log a node invalidated together with its dependents.
+    pub async fn log_nodes_invalidated(&self, node: &str, invalidated: &[String]) -> Result<()> {
+        self.log(LogEvent::warn_with_details(
+            LogEventType::NodesInvalidated,
+            format!(
+                "Invalidated {} and {} dependent(s)",
+                node,
+                invalidated.len() - 1
+            ),
+            serde_json::json!({ "node": node, "invalidated": invalidated }),
+        ))
+        .await
+    }
+
     /// Convenience: log an error.
     pub async fn log_error(&self, message: &str, details: Option<&str>) -> Result<()> {
         self.log(LogEvent::new(
@@ -402,7 +670,14 @@ mod tests {
             .await
             .unwrap();
         logger
-            .log_action_dispatched("Architect", "creates", "DesignSpec", "Creation")
+            .log_action_dispatched(
+                "Architect",
+                "creates",
+                "DesignSpec",
+                "Creation",
+                &ExecutionOptions::default(),
+                "client default",
+            )
             .await
             .unwrap();
         logger
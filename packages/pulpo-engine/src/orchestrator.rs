@@ -1,22 +1,40 @@
 use crate::agents::cli_client::AiCliClient;
+use crate::agents::command::CommandAgent;
 use crate::agents::generic::GenericAgent;
+use crate::clarification::{self, CLARIFICATIONS_SUFFIX, clarifications_key, parse_questions};
+use crate::collection::{file_stem, split_instance};
 
 use crate::domain::types::AgentRole;
-use crate::graph::executor::{GraphExecutor, InMemoryExecutor, Task};
-use crate::graph::{DependencyGraph, RelationCategory};
-use crate::logging::IterationLogger;
+use crate::git::{CommitMessage, GitOptions, GitRepo, iteration_branch};
+use crate::graph::executor::{ExecutionOptions, GraphExecutor, InMemoryExecutor, Task};
+use crate::graph::{
+    BUILTIN_ONTOLOGY, COMMAND_RUNNER, DependencyGraph, FailurePolicy, RelationCategory, Scope,
+    TimeoutConfig, builtin_ontology_root,
+};
+use crate::hooks::{Decision, HookEvent, HookSubject};
+use crate::lifecycle::{NodeLifecycle, NodeState, NodeStatus};
+use crate::lock::{LOCK_FILE, OntologyLock};
+use crate::logging::{IterationLogger, LogEvent, LogEventType, LogLevel};
+use crate::planner::{Planner, Simulation};
+use crate::provenance::{Provenance, content_hash};
+use crate::state::OrchestratorState;
+use crate::usage::{Budget, BudgetAction, DispatchUsage, UsageReport};
 use anyhow::{Context, Result};
-use petgraph::visit::EdgeRef;
+use futures::StreamExt;
 use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use std::time::Instant;
 use tracing::{debug, info, warn};
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct ActionPlan {
-    pub agent: String,
-    pub target: String,
-    pub relation: String,
-    pub category: RelationCategory,
+pub use crate::planner::ActionPlan;
+
+/// A predicted step of a dry run, with the prompt the agent would receive.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DryRunStep {
+    pub step: usize,
+    pub action: ActionPlan,
+    pub context: Vec<String>,
+    pub prompt: String,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -24,6 +42,11 @@ pub struct IterationInfo {
     pub id: String,
     pub name: String,
     pub timestamp: String,
+    // Version and hash of the ontology the iteration locked when it started
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ontology_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ontology_hash: Option<String>,
 }
 
 pub struct Orchestrator<C: AiCliClient + Clone + Send + Sync + 'static> {
@@ -36,9 +59,23 @@ pub struct Orchestrator<C: AiCliClient + Clone + Send + Sync + 'static> {
     // Tracking produced artifacts (State)
     pub artifacts: HashMap<String, serde_json::Value>, // EntityKind -> Last Produced Value
     pub verification_feedback: HashMap<String, String>, // Target -> Feedback
-    pub verified_artifacts: std::collections::HashSet<String>, // Tracks those with score 1.0
+    pub lifecycle: NodeLifecycle,                      // Recorded per-node lifecycle states
     pub refinement_attempts: HashMap<String, usize>,   // Target -> retry count
+    pub provenance: Provenance, // Upstream hashes each artifact was produced from
+    pub failure_attempts: HashMap<String, usize>, // Target -> failed attempts
+    pub failure_feedback: HashMap<String, String>, // Target -> last error, fed into retries
+    pub actions_executed: usize, // Completed actions in this iteration
+    pub interrupted_at: Option<String>, // Set when the last run was cancelled
+    pub usage: UsageReport,     // Prompt/response sizes, durations and tokens
+    in_flight: Vec<ActionPlan>, // Actions dispatched but not completed
+    // Dirty files of the work dir before the actions in flight were
+    // dispatched, to commit the files they write
+    work_tree: Option<HashMap<String, Option<String>>>,
     max_iterations: usize,
+    // Maximum number of agent dispatches in flight at once (1 = sequential)
+    concurrency: usize,
+    // Times an agent may answer with questions before its action fails
+    max_clarification_rounds: usize,
     // Iteration tracking
     pub current_iteration: Option<IterationInfo>,
     // Execution logger for full traceability
@@ -46,7 +83,23 @@ pub struct Orchestrator<C: AiCliClient + Clone + Send + Sync + 'static> {
     // The client used to interact with the AI
     pub client: C,
     // Category mapping defaults
-    pub category_defaults: HashMap<String, crate::graph::executor::ExecutionOptions>,
+    pub category_defaults: HashMap<String, ExecutionOptions>,
+    // Failure policy for edges that do not declare `onFailure`
+    pub failure_policy: FailurePolicy,
+    // Timeouts that override the ones declared in the ontology
+    pub timeouts: TimeoutConfig,
+    // Flips to true (e.g. on Ctrl-C) to interrupt the run
+    cancel: Option<tokio::sync::watch::Receiver<bool>>,
+    // Limits that pause or stop the run once exhausted
+    pub budget: Budget,
+    // When the current `run` started, for the wall time budget
+    run_started: Option<Instant>,
+    // Commits after each produced artifact and the optional iteration branch
+    pub git: GitOptions,
+    // Lock of the ontology loaded from disk; none for an inline metamodel
+    pub ontology_lock: Option<OntologyLock>,
+    // Refuse to resume an iteration whose ontology drifted from its lock
+    locked: bool,
 }
 
 impl<C: AiCliClient + Clone + Send + Sync + 'static> Orchestrator<C> {
@@ -56,9 +109,16 @@ impl<C: AiCliClient + Clone + Send + Sync + 'static> Orchestrator<C> {
         app_name: String,
         work_dir: PathBuf,
     ) -> Result<Self> {
-        let metamodel_json =
-            include_str!("../../../pulpo-ontologies/software-engineering/ontology.json");
-        Self::new_with_metamodel(client, app_id, app_name, work_dir, metamodel_json, None).await
+        let root = builtin_ontology_root()?;
+        Self::new_with_metamodel(
+            client,
+            app_id,
+            app_name,
+            work_dir,
+            BUILTIN_ONTOLOGY,
+            Some(&root),
+        )
+        .await
     }
 
     pub async fn new_with_metamodel(
@@ -76,33 +136,26 @@ impl<C: AiCliClient + Clone + Send + Sync + 'static> Orchestrator<C> {
 
         // Initialize Graph (Load Metamodel)
         let graph = DependencyGraph::load_from_metamodel(metamodel_json, ontology_base_path)?;
+        let ontology_lock = ontology_base_path
+            .map(|root| OntologyLock::sync(root, &graph.imports))
+            .transpose()?;
 
         // Initialize Executor and Register Agents
         let mut executor = InMemoryExecutor::new(graph);
 
         // Dynamic Registration from Graph
-        // Collect roles and configs first to avoid borrowing conflict
+        // Collect roles and prompts first to avoid borrowing conflict; the
+        // prompt is built from the agent's whole inheritance chain
         let agent_data: Vec<(String, String)> = executor
             .graph
             .loaded_agents
-            .iter()
-            .map(|(r, c)| (r.clone(), c.clone()))
+            .keys()
+            .map(|r| (r.clone(), executor.graph.system_prompt(r)))
             .collect();
 
-        for (role_str, config_json) in agent_data {
+        for (role_str, system_prompt) in agent_data {
             let role = AgentRole::from(role_str);
 
-            // Parse Config
-            let system_prompt =
-                if let Ok(v) = serde_json::from_str::<serde_json::Value>(&config_json) {
-                    v.get("system_prompt")
-                        .and_then(|t| t.as_str())
-                        .unwrap_or("")
-                        .to_string()
-                } else {
-                    "".to_string()
-                };
-
             debug!("Registering agent: {:?}", role);
             executor.register_agent(Box::new(GenericAgent::new(
                 client.clone(),
@@ -110,6 +163,9 @@ impl<C: AiCliClient + Clone + Send + Sync + 'static> Orchestrator<C> {
                 system_prompt,
             )));
         }
+        if executor.graph.is_agent(COMMAND_RUNNER) || !executor.graph.verifiers.is_empty() {
+            executor.register_agent(Box::new(CommandAgent::new(work_dir.clone())));
+        }
 
         Ok(Self {
             app_id,
@@ -119,13 +175,31 @@ impl<C: AiCliClient + Clone + Send + Sync + 'static> Orchestrator<C> {
             executor,
             artifacts: HashMap::new(),
             verification_feedback: HashMap::new(),
-            verified_artifacts: std::collections::HashSet::new(),
+            lifecycle: NodeLifecycle::new(),
             refinement_attempts: HashMap::new(),
+            provenance: Provenance::new(),
+            failure_attempts: HashMap::new(),
+            failure_feedback: HashMap::new(),
+            actions_executed: 0,
+            interrupted_at: None,
+            usage: UsageReport::default(),
+            in_flight: Vec::new(),
+            work_tree: None,
             max_iterations: 100,
+            concurrency: 1,
+            max_clarification_rounds: 3,
             current_iteration: None,
             logger: None,
             client,
             category_defaults: HashMap::new(),
+            failure_policy: FailurePolicy::default(),
+            timeouts: TimeoutConfig::default(),
+            cancel: None,
+            budget: Budget::default(),
+            run_started: None,
+            git: GitOptions::default(),
+            ontology_lock,
+            locked: false,
         })
     }
 
@@ -134,19 +208,267 @@ impl<C: AiCliClient + Clone + Send + Sync + 'static> Orchestrator<C> {
         self
     }
 
+    /// Sets how many independent ready actions may be dispatched in parallel.
+    /// A limit of 1 (the default) keeps the sequential behaviour.
+    pub fn with_concurrency(mut self, limit: usize) -> Self {
+        self.concurrency = limit.max(1);
+        self
+    }
+
+    /// Sets how many rounds of questions an agent may ask for one action. 0
+    /// tells agents not to ask at all.
+    pub fn with_max_clarification_rounds(mut self, rounds: usize) -> Self {
+        self.max_clarification_rounds = rounds;
+        self
+    }
+
+    /// Refuses to resume an iteration when the ontology on disk differs from
+    /// the one locked by the iteration, instead of warning.
+    pub fn with_locked(mut self, locked: bool) -> Self {
+        self.locked = locked;
+        self
+    }
+
     pub fn with_docs_folder(mut self, folder: String) -> Self {
         self.docs_folder = folder;
         self
     }
 
-    pub fn with_category_defaults(
-        mut self,
-        defaults: HashMap<String, crate::graph::executor::ExecutionOptions>,
-    ) -> Self {
+    pub fn with_category_defaults(mut self, defaults: HashMap<String, ExecutionOptions>) -> Self {
         self.category_defaults = defaults;
         self
     }
 
+    pub fn with_failure_policy(mut self, policy: FailurePolicy) -> Self {
+        self.failure_policy = policy;
+        self
+    }
+
+    pub fn with_timeouts(mut self, timeouts: TimeoutConfig) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Interrupts the run as soon as `true` is sent on the channel: running AI
+    /// CLI processes are killed and the iteration is checkpointed as interrupted.
+    pub fn with_cancellation(mut self, cancel: tokio::sync::watch::Receiver<bool>) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    pub fn with_budget(mut self, budget: Budget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    pub fn with_git(mut self, git: GitOptions) -> Self {
+        self.git = git;
+        self
+    }
+
+    fn git_repo(&self) -> Option<GitRepo> {
+        match &self.work_dir {
+            Some(work_dir) if self.git.commit => Some(GitRepo::new(work_dir.clone())),
+            _ => None,
+        }
+    }
+
+    /// Checks out the iteration's branch, if configured. The work dir must
+    /// already be a git repository; otherwise nothing is committed. Git
+    /// problems never stop the iteration; they are reported and the artifacts
+    /// are simply not committed.
+    async fn prepare_git(&self, iteration_id: &str) {
+        let Some(repo) = self.git_repo() else {
+            return;
+        };
+        if !repo.is_repo().await {
+            info!("The work dir is not a git repository; artifacts are not committed");
+            return;
+        }
+        let result = async {
+            if self.git.branch_per_iteration {
+                repo.switch_branch(&iteration_branch(iteration_id)).await?;
+            }
+            anyhow::Ok(())
+        }
+        .await;
+        if let Err(e) = result {
+            warn!("Git is not available for this iteration: {}", e);
+            if let Some(ref logger) = self.logger {
+                let _ = logger
+                    .log_error(
+                        "Git is not available for this iteration",
+                        Some(&e.to_string()),
+                    )
+                    .await;
+            }
+        }
+    }
+
+    /// Dirty files of the work dir, when the engine commits to it and it is a
+    /// git repository.
+    async fn dirty_files(&self) -> Option<HashMap<String, Option<String>>> {
+        let repo = self.git_repo()?;
+        if !repo.is_repo().await {
+            return None;
+        }
+        match repo.dirty_files().await {
+            Ok(files) => Some(files),
+            Err(e) => {
+                warn!("Failed to read the status of the work dir: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Commits the artifacts written since the last commit after an action
+    /// produced `key`, along with the files of the work dir that changed since
+    /// the action was dispatched, with trailers identifying the action, and
+    /// records the SHA in the log, in artifacts.json and as a checkpoint to
+    /// roll back to.
+    async fn commit_action(&mut self, action: &ActionPlan, key: &str) {
+        let Some(repo) = self.git_repo() else {
+            return;
+        };
+        if !repo.is_repo().await {
+            return;
+        }
+        let mut paths = match self.uncommitted_paths().await {
+            Ok(paths) => paths,
+            Err(e) => {
+                warn!("Failed to read the artifacts to commit: {}", e);
+                return;
+            }
+        };
+        if let Some(before) = &self.work_tree
+            && let Some(after) = self.dirty_files().await
+        {
+            // The engine's own state is not part of the work
+            let mut written: Vec<&String> = before
+                .keys()
+                .chain(after.keys())
+                .filter(|path| before.get(*path) != after.get(*path))
+                .filter(|path| !path.starts_with(".infinitecodingloop/"))
+                .collect();
+            written.sort();
+            written.dedup();
+            for path in written {
+                if !paths.contains(path) {
+                    paths.push(path.clone());
+                }
+            }
+        }
+        let (options, _) = self.resolve_action_options(action);
+        let model = options
+            .model
+            .or(options.model_type)
+            .unwrap_or_else(|| "default".to_string());
+        let iteration = self
+            .current_iteration
+            .as_ref()
+            .map(|i| i.id.clone())
+            .unwrap_or_default();
+        let message = CommitMessage::new(format!("{} {} {}", action.agent, action.relation, key))
+            .with_trailer("Pulpo-Iteration", iteration)
+            .with_trailer("Pulpo-Agent", action.agent.as_str())
+            .with_trailer("Pulpo-Relation", action.relation.as_str())
+            .with_trailer("Pulpo-Target", key)
+            .with_trailer("Pulpo-Model", model);
+
+        match repo.commit_paths(&message, &paths).await {
+            Ok(Some(sha)) => {
+                info!("Committed {} as {}", key, sha);
+                self.work_tree = self.dirty_files().await;
+                let branch = repo.current_branch().await.ok();
+                if let Some(ref logger) = self.logger {
+                    let _ = logger
+                        .log_artifact_committed(key, &sha, branch.as_deref())
+                        .await;
+                }
+                if let Err(e) = self.record_commit(&sha).await {
+                    warn!("Failed to record the commit of {}: {}", key, e);
+                }
+            }
+            Ok(None) => {
+                debug!("Nothing to commit after {}", key);
+                self.work_tree = self.dirty_files().await;
+            }
+            Err(e) => {
+                warn!("Failed to commit {}: {}", key, e);
+                if let Some(ref logger) = self.logger {
+                    let _ = logger
+                        .log_error(&format!("Failed to commit {}", key), Some(&e.to_string()))
+                        .await;
+                }
+            }
+        }
+    }
+
+    /// What hooks see an action as: its entity, the entity's type and the edge.
+    fn hook_subject(&self, action: &ActionPlan) -> HookSubject {
+        HookSubject {
+            entity: Some(action.target.clone()),
+            entity_type: self.executor.graph.node_types.get(&action.target).cloned(),
+            relation: Some(action.relation.clone()),
+            verb_type: Some(format!("{:?}", action.category)),
+        }
+    }
+
+    /// Runs the hooks declared for `event` on `subject`, in declaration order,
+    /// with `payload`. A failing hook is only reported; the first approval
+    /// hook that denies, or cannot be asked, denies the event.
+    async fn fire_hooks(
+        &self,
+        event: HookEvent,
+        subject: &HookSubject,
+        payload: LogEvent,
+    ) -> Decision {
+        let work_dir = self.work_dir.clone().unwrap_or_else(|| PathBuf::from("."));
+        let hooks = self.executor.graph.hooks.iter();
+        for hook in hooks.filter(|hook| hook.matches(event, subject)) {
+            let (decision, outcome) = match hook.run(event, &payload, &work_dir).await {
+                Ok(Decision::Allow) => (Decision::Allow, "ok".to_string()),
+                Ok(Decision::Deny(reason)) => (
+                    Decision::Deny(reason.clone()),
+                    format!("denied: {}", reason),
+                ),
+                Err(e) if hook.approval => (Decision::Deny(e.to_string()), e.to_string()),
+                Err(e) => {
+                    warn!("{}", e);
+                    (Decision::Allow, e.to_string())
+                }
+            };
+            if let Some(ref logger) = self.logger {
+                let _ = logger
+                    .log_hook_run(&hook.name, event.as_str(), &outcome)
+                    .await;
+            }
+            if let Decision::Deny(_) = decision {
+                return decision;
+            }
+        }
+        Decision::Allow
+    }
+
+    /// Asks the approval hooks whether an action may be dispatched, with the
+    /// dispatch event that is logged once they allow it.
+    async fn approve_dispatch(&self, action: &ActionPlan, dispatched: &LogEvent) -> Result<()> {
+        let subject = self.hook_subject(action);
+        match self
+            .fire_hooks(HookEvent::BeforeDispatch, &subject, dispatched.clone())
+            .await
+        {
+            Decision::Allow => Ok(()),
+            Decision::Deny(reason) => Err(anyhow::anyhow!(
+                "{} {} {} was denied by a hook: {}",
+                action.agent,
+                action.relation,
+                action.key(),
+                reason
+            )),
+        }
+    }
+
     pub(crate) async fn ensure_persistence_dirs(&self) -> Result<PathBuf> {
         let work_dir = self.work_dir.as_ref().context("Work directory not set")?;
         let icl_dir = work_dir.join(".infinitecodingloop");
@@ -186,10 +508,19 @@ impl<C: AiCliClient + Clone + Send + Sync + 'static> Orchestrator<C> {
             id: id.clone(),
             name: name.to_string(),
             timestamp,
+            ontology_version: self
+                .ontology_lock
+                .as_ref()
+                .and_then(|lock| lock.version.clone()),
+            ontology_hash: self.ontology_lock.as_ref().map(|lock| lock.hash.clone()),
         };
 
         let iter_folder = iterations_dir.join(&id);
         tokio::fs::create_dir_all(&iter_folder).await?;
+        // The exact ontology the iteration runs with, to detect drift on resume
+        if let Some(lock) = &self.ontology_lock {
+            lock.write(&iter_folder.join(LOCK_FILE))?;
+        }
 
         let iter_json_path = iter_folder.join("iteration.json");
         let content = serde_json::to_string_pretty(&iter_info)?;
@@ -208,6 +539,8 @@ impl<C: AiCliClient + Clone + Send + Sync + 'static> Orchestrator<C> {
         info!("Started new iteration: {} ({})", name, id);
         self.logger = Some(logger);
         self.current_iteration = Some(iter_info);
+        self.prepare_git(&id).await;
+        self.reuse_application_artifacts().await?;
         Ok(())
     }
 
@@ -227,10 +560,85 @@ impl<C: AiCliClient + Clone + Send + Sync + 'static> Orchestrator<C> {
         let logger = IterationLogger::new(&iter_folder).await?;
         logger.log_iteration_resumed(iteration_id).await?;
         self.logger = Some(logger);
+        self.check_ontology_drift(&iter_folder, iteration_id)
+            .await?;
 
-        // Load artifacts from the docs folder
-        let docs_dir = work_dir.join(&self.docs_folder);
-        if docs_dir.exists() {
+        // Prefer the exact checkpoint; fall back to scanning the docs folder for
+        // iterations written before checkpoints existed.
+        if let Some(state) = OrchestratorState::load(&iter_folder).await? {
+            self.restore_state(state);
+            if let Some(ref logger) = self.logger {
+                let _ = logger
+                    .log(crate::logging::LogEvent::info_with_details(
+                        crate::logging::LogEventType::Info,
+                        "Restored orchestrator checkpoint",
+                        serde_json::json!({
+                            "artifacts": self.artifacts.len(),
+                            "actions_executed": self.actions_executed,
+                        }),
+                    ))
+                    .await;
+            }
+        } else {
+            self.load_artifacts_from_docs().await?;
+        }
+        self.prepare_git(iteration_id).await;
+        self.reuse_application_artifacts().await?;
+
+        info!(
+            "Loaded iteration: {} ({})",
+            self.current_iteration.as_ref().unwrap().name,
+            iteration_id
+        );
+        Ok(())
+    }
+
+    /// Compares the ontology on disk with the one the iteration locked: warns
+    /// about the changes, or refuses them when running locked.
+    async fn check_ontology_drift(
+        &self,
+        iter_folder: &std::path::Path,
+        iteration_id: &str,
+    ) -> Result<()> {
+        let (Some(current), Some(locked)) = (
+            &self.ontology_lock,
+            OntologyLock::read(&iter_folder.join(LOCK_FILE))?,
+        ) else {
+            return Ok(());
+        };
+        let changes = current.drift(&locked);
+        if changes.is_empty() {
+            return Ok(());
+        }
+        if self.locked {
+            anyhow::bail!(
+                "The ontology drifted from the one iteration {} ran with:\n  {}\nRestore it, or resume without --locked",
+                iteration_id,
+                changes.join("\n  ")
+            );
+        }
+        warn!(
+            "The ontology drifted from the one iteration {} ran with: {}",
+            iteration_id,
+            changes.join(", ")
+        );
+        if let Some(ref logger) = self.logger {
+            let _ = logger.log_ontology_drift(&locked.hash, &changes).await;
+        }
+        Ok(())
+    }
+
+    async fn load_artifacts_from_docs(&mut self) -> Result<()> {
+        let work_dir = self.work_dir.as_ref().context("Work directory not set")?;
+        // Iterations written before scopes existed kept everything in the docs root
+        let folders = [
+            self.docs_folder.clone(),
+            self.artifact_folder_for(Scope::Feature),
+        ];
+        for docs_dir in folders.iter().map(|folder| work_dir.join(folder)) {
+            if !docs_dir.exists() {
+                continue;
+            }
             let mut entries = tokio::fs::read_dir(&docs_dir).await?;
             while let Some(entry) = entries.next_entry().await? {
                 let path = entry.path();
@@ -255,162 +663,616 @@ impl<C: AiCliClient + Clone + Send + Sync + 'static> Orchestrator<C> {
                 }
             }
         }
-
-        info!(
-            "Loaded iteration: {} ({})",
-            self.current_iteration.as_ref().unwrap().name,
-            iteration_id
-        );
         Ok(())
     }
 
-    pub fn get_execution_status(&self) -> (Vec<String>, Vec<String>) {
-        let mut done = Vec::new();
-        let mut pending = Vec::new();
+    /// Folder, relative to the work directory, that artifacts of `scope` are
+    /// written to: application-scoped artifacts are shared by all iterations in
+    /// `<docs>/application`, feature-scoped ones are kept per iteration in
+    /// `<docs>/features/<iteration id>`.
+    fn artifact_folder_for(&self, scope: Scope) -> String {
+        match (scope, &self.current_iteration) {
+            (Scope::Application, _) => format!("{}/application", self.docs_folder),
+            (Scope::Feature, Some(iteration)) => {
+                format!("{}/features/{}", self.docs_folder, iteration.id)
+            }
+            (Scope::Feature, None) => self.docs_folder.clone(),
+        }
+    }
+
+    /// Folder an artifact, one of its instances, its verification report or
+    /// its clarifications are written to, according to the scope of its entity.
+    pub fn artifact_folder(&self, name: &str) -> String {
+        let kind = name
+            .strip_suffix("_verification")
+            .or_else(|| name.strip_suffix(CLARIFICATIONS_SUFFIX))
+            .unwrap_or(name);
+        let (kind, _) = split_instance(kind);
+        self.artifact_folder_for(self.executor.graph.scope_of(kind))
+    }
+
+    /// Loads the application-scoped artifacts produced by earlier iterations,
+    /// so they are reused instead of created again. Returns the reused nodes.
+    pub async fn reuse_application_artifacts(&mut self) -> Result<Vec<String>> {
+        let work_dir = self.work_dir.clone().context("Work directory not set")?;
+        let folder = self.artifact_folder_for(Scope::Application);
+        let graph = &self.executor.graph;
+        let mut nodes: Vec<String> = graph
+            .scopes
+            .iter()
+            .filter(|(node, scope)| **scope == Scope::Application && !graph.is_agent(node))
+            .map(|(node, _)| node.clone())
+            .collect();
+        nodes.sort();
 
-        for node_idx in self.executor.graph.graph.node_indices() {
-            let kind = &self.executor.graph.graph[node_idx];
-            if self.executor.graph.is_agent(kind) {
+        let mut reused = Vec::new();
+        for node in nodes {
+            if self.artifacts.contains_key(&node) {
                 continue;
             }
-            if kind == "SoftwareApplication" {
+            let relative_path = format!("{}/{}.json", folder, file_stem(&node));
+            let Ok(content) = tokio::fs::read_to_string(work_dir.join(&relative_path)).await else {
                 continue;
-            }
-
-            if self.artifacts.contains_key(kind) {
-                done.push(kind.clone());
-            } else {
-                // Check if it's actionable
-                let mut actionable = false;
-                for edge in self
-                    .executor
-                    .graph
-                    .graph
-                    .edges_directed(node_idx, petgraph::Direction::Incoming)
-                {
-                    let source_idx = edge.source();
-                    let source_kind = &self.executor.graph.graph[source_idx];
-                    let relation = edge.weight();
-                    let edge_key = (
-                        source_kind.to_string(),
-                        relation.to_string(),
-                        kind.to_string(),
+            };
+            let value: serde_json::Value = match serde_json::from_str(&content) {
+                Ok(value) => value,
+                Err(e) => {
+                    warn!(
+                        "Ignoring unparsable application artifact {}: {}",
+                        relative_path, e
                     );
-                    let category = self
-                        .executor
-                        .graph
-                        .edge_categories
-                        .get(&edge_key)
-                        .copied()
-                        .unwrap_or(RelationCategory::Context);
-                    if category == RelationCategory::Creation {
-                        actionable = true;
-                        break;
-                    }
-                }
-                if actionable {
-                    pending.push(kind.clone());
+                    continue;
                 }
+            };
+
+            info!(
+                "Reusing application artifact {} from {}",
+                node, relative_path
+            );
+            self.artifacts.insert(node.clone(), value);
+            self.lifecycle.observe(
+                &node,
+                NodeState::Created,
+                Some("Reused application artifact".to_string()),
+            );
+            if let Some(ref logger) = self.logger {
+                let _ = logger.log_artifact_reused(&node, &relative_path).await;
             }
+            reused.push(node);
         }
+        Ok(reused)
+    }
 
-        (done, pending)
+    /// Captures the full resumable state of the current iteration.
+    pub fn snapshot_state(&self) -> OrchestratorState {
+        OrchestratorState {
+            artifacts: self
+                .artifacts
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+            verification_feedback: self
+                .verification_feedback
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+            node_states: self.lifecycle.to_map(),
+            refinement_attempts: self
+                .refinement_attempts
+                .iter()
+                .map(|(k, v)| (k.clone(), *v))
+                .collect(),
+            provenance: self.provenance.to_map(),
+            failure_attempts: self
+                .failure_attempts
+                .iter()
+                .map(|(k, v)| (k.clone(), *v))
+                .collect(),
+            failure_feedback: self
+                .failure_feedback
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+            actions_executed: self.actions_executed,
+            interrupted_at: self.interrupted_at.clone(),
+            usage: self.usage.clone(),
+            updated_at: chrono::Local::now().format("%Y%m%d_%H%M%S").to_string(),
+        }
     }
 
-    pub(crate) async fn persist_artifact(
-        &self,
-        name: &str,
-        data: &serde_json::Value,
-    ) -> Result<()> {
+    /// Replaces the in-memory state with a previously captured snapshot.
+    pub fn restore_state(&mut self, state: OrchestratorState) {
+        self.artifacts = state.artifacts.into_iter().collect();
+        self.verification_feedback = state.verification_feedback.into_iter().collect();
+        // Actions that were in flight or failed when the checkpoint was written
+        // never completed; forget them so they are planned again.
+        let node_states = state
+            .node_states
+            .into_iter()
+            .filter(|(_, record)| !matches!(record.state, NodeState::Running | NodeState::Failed))
+            .collect();
+        self.lifecycle = NodeLifecycle::from_map(node_states);
+        self.refinement_attempts = state.refinement_attempts.into_iter().collect();
+        self.provenance = Provenance::from_map(state.provenance);
+        self.failure_attempts = state.failure_attempts.into_iter().collect();
+        self.failure_feedback = state.failure_feedback.into_iter().collect();
+        self.actions_executed = state.actions_executed;
+        self.interrupted_at = state.interrupted_at;
+        self.usage = state.usage;
+    }
+
+    /// Persists the current state to `.infinitecodingloop/iterations/<id>/state.json`.
+    pub(crate) async fn checkpoint(&self) -> Result<()> {
         let iteration = self
             .current_iteration
             .as_ref()
-            .context("No active iteration to persist artifact")?;
+            .context("No active iteration to checkpoint")?;
         let work_dir = self.work_dir.as_ref().context("Work directory not set")?;
-
-        // Write artifact to the docs folder
-        let docs_dir = work_dir.join(&self.docs_folder);
-        if !docs_dir.exists() {
-            tokio::fs::create_dir_all(&docs_dir).await?;
-        }
-
-        let filename = format!("{}.json", name.to_lowercase());
-        let artifact_path = docs_dir.join(&filename);
-        let content = serde_json::to_string_pretty(data)?;
-        tokio::fs::write(&artifact_path, content).await?;
-
-        // Record metadata in .infinitecodingloop/iterations/{id}/artifacts.json
         let iter_dir = work_dir
             .join(".infinitecodingloop")
             .join("iterations")
             .join(&iteration.id);
-        if !iter_dir.exists() {
-            tokio::fs::create_dir_all(&iter_dir).await?;
-        }
-
-        let artifacts_meta_path = iter_dir.join("artifacts.json");
-        let mut entries: Vec<serde_json::Value> = if artifacts_meta_path.exists() {
-            let existing = tokio::fs::read_to_string(&artifacts_meta_path).await?;
-            serde_json::from_str(&existing).unwrap_or_default()
-        } else {
-            Vec::new()
-        };
-
-        let relative_path = format!("{}/{}", self.docs_folder, filename);
-        let now = chrono::Local::now().format("%Y%m%d_%H%M%S").to_string();
-        entries.push(serde_json::json!({
-            "name": name,
-            "timestamp": now,
-            "path": relative_path
-        }));
-
-        let meta_content = serde_json::to_string_pretty(&entries)?;
-        tokio::fs::write(artifacts_meta_path, meta_content).await?;
-
+        self.snapshot_state().save(&iter_dir).await?;
         debug!(
-            "Persisted artifact {} to {}/{} (iteration {})",
-            name, self.docs_folder, filename, iteration.id
+            "Checkpointed iteration {} after {} actions",
+            iteration.id, self.actions_executed
         );
-
-        // Log artifact persisted
-        if let Some(ref logger) = self.logger {
-            let _ = logger.log_artifact_persisted(name, &relative_path).await;
-        }
-
         Ok(())
     }
 
-    pub async fn run(&mut self, ui: &impl crate::interaction::UserInteraction) -> Result<()> {
-        ui.log_info("Starting Generic Graph-Driven Orchestration...");
+    pub fn get_execution_status(&self) -> (Vec<String>, Vec<String>) {
+        let mut done = Vec::new();
+        let mut pending = Vec::new();
 
-        // 1. Initial Input (Skip if already exists in resumed iteration)
-        if !self.handle_initial_input(ui).await? {
-            return Ok(());
+        for status in self.node_statuses() {
+            if status.node == "SoftwareApplication" {
+                continue;
+            }
+            if status.state.has_artifact() {
+                done.push(status.node);
+            } else if status.state != NodeState::Pending {
+                // Pending nodes have no creator, so they are not actionable
+                pending.push(status.node);
+            }
         }
 
-        // 2. Generic Execution Loop (State Machine)
-        let mut iterations = 0;
+        (done, pending)
+    }
 
-        while iterations < self.max_iterations {
-            iterations += 1;
-            ui.start_step(&format!(
-                "Iteration {} - Evaluating Graph State...",
-                iterations
-            ));
+    /// Planning view over the current graph and execution state.
+    pub fn planner(&self) -> Planner<'_> {
+        Planner::new(
+            &self.executor.graph,
+            &self.artifacts,
+            &self.lifecycle,
+            &self.refinement_attempts,
+        )
+        .with_provenance(&self.provenance)
+    }
 
-            // Log loop cycle
-            if let Some(ref logger) = self.logger {
-                let _ = logger.log_loop_cycle(iterations).await;
-            }
+    /// Lifecycle status of every non-agent node, in graph order.
+    pub fn node_statuses(&self) -> Vec<NodeStatus> {
+        self.planner().node_statuses()
+    }
 
-            let next_actions = self.identify_next_actions();
-            if next_actions.is_empty() {
-                ui.log_info(&format!(
-                    "No more actions identified. Current artifacts: {:?}",
-                    self.artifacts.keys().collect::<Vec<_>>()
-                ));
-                break;
-            }
+    /// Effective lifecycle status of a node, including why it is not running.
+    pub fn node_status(&self, node: &str) -> NodeStatus {
+        self.planner().node_status(node)
+    }
+
+    /// Artifacts whose verification passed. This used to be a field; it is now
+    /// derived from the lifecycle, where `Verified` is the source of truth.
+    #[deprecated(note = "use `node_status(node).state == NodeState::Verified` instead")]
+    pub fn verified_artifacts(&self) -> HashSet<String> {
+        self.node_statuses()
+            .into_iter()
+            .filter(|status| status.state == NodeState::Verified)
+            .map(|status| status.node)
+            .collect()
+    }
+
+    /// Records the derived state of every node so that the lifecycle (and the
+    /// execution log) reflects artifacts produced outside of an action, such as
+    /// the initial input or artifacts loaded from the docs folder.
+    async fn sync_lifecycle(&mut self) {
+        for status in self.node_statuses() {
+            let recorded = self.lifecycle.record(&status.node);
+            if recorded.map(|r| (r.state, &r.reason)) == Some((status.state, &status.reason)) {
+                continue;
+            }
+            let from = self.lifecycle.state(&status.node);
+            self.lifecycle
+                .observe(&status.node, status.state, status.reason.clone());
+            if from != status.state
+                && let Some(ref logger) = self.logger
+            {
+                let _ = logger
+                    .log_node_state_changed(
+                        &status.node,
+                        &from.to_string(),
+                        &status.state.to_string(),
+                        status.reason.as_deref(),
+                    )
+                    .await;
+            }
+        }
+    }
+
+    /// Moves a node to a new lifecycle state, validating the transition against
+    /// its current effective state and logging it.
+    pub(crate) async fn transition_node(
+        &mut self,
+        node: &str,
+        to: NodeState,
+        reason: Option<String>,
+    ) -> Result<()> {
+        let current = self.node_status(node);
+        if self.lifecycle.state(node) != current.state {
+            self.lifecycle
+                .observe(node, current.state, current.reason.clone());
+        }
+        let from = self.lifecycle.transition(node, to, reason.clone())?;
+        if let Some(ref logger) = self.logger {
+            let _ = logger
+                .log_node_state_changed(node, &from.to_string(), &to.to_string(), reason.as_deref())
+                .await;
+        }
+        Ok(())
+    }
+
+    /// Adopts artifacts that were edited by hand in the docs folder since they
+    /// were produced. An edited artifact is verified again (if it has a
+    /// Verification edge) and everything produced from it becomes stale.
+    /// Returns the edited artifacts.
+    pub async fn adopt_docs_edits(&mut self) -> Result<Vec<String>> {
+        let Some(work_dir) = self.work_dir.clone() else {
+            return Ok(Vec::new());
+        };
+
+        let mut names: Vec<String> = self.artifacts.keys().cloned().collect();
+        names.sort();
+        let mut edited = Vec::new();
+        for name in names {
+            let relative_path =
+                format!("{}/{}.json", self.artifact_folder(&name), file_stem(&name));
+            let path = work_dir.join(&relative_path);
+            let Ok(content) = tokio::fs::read_to_string(&path).await else {
+                continue;
+            };
+            let value: serde_json::Value = match serde_json::from_str(&content) {
+                Ok(value) => value,
+                Err(e) => {
+                    warn!("Ignoring unparsable edit of {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            if self.artifacts.get(&name) == Some(&value) {
+                continue;
+            }
+
+            info!("Artifact {} was edited in {}", name, path.display());
+            self.artifacts.insert(name.clone(), value);
+            self.lifecycle.reset(&name);
+            if let Some(ref logger) = self.logger {
+                let _ = logger.log_artifact_edited(&name, &relative_path).await;
+            }
+            edited.push(name);
+        }
+
+        if !edited.is_empty() && self.current_iteration.is_some() {
+            self.checkpoint().await?;
+        }
+        Ok(edited)
+    }
+
+    /// Rolls an iteration back to right after `node` was last produced in it:
+    /// the files changed by the commits made since, artifacts and sources the
+    /// AI CLI wrote alike, are restored from the commit recorded for the node
+    /// (or removed if created later), and the checkpoint (artifacts,
+    /// verification and refinement data, actions executed) is rewound with
+    /// them, so the next `run` continues from there. Uncommitted files,
+    /// execution logs and other iterations are kept, and the rollback is
+    /// committed on top of the history. Returns the SHA rolled back to.
+    pub async fn rollback(&mut self, iteration_id: &str, node: &str) -> Result<String> {
+        self.load_iteration(iteration_id).await?;
+        let work_dir = self.work_dir.clone().context("Work directory not set")?;
+        let iter_dir = work_dir
+            .join(".infinitecodingloop/iterations")
+            .join(iteration_id);
+
+        let mut entries = self.artifact_entries().await?;
+        let sha = entries
+            .iter()
+            .rev()
+            .filter(|e| e["name"] == node)
+            .find_map(|e| e["commit"].as_str())
+            .map(str::to_string)
+            .with_context(|| {
+                format!(
+                    "No commit recorded for '{}' in iteration {}; rolling back needs the commits made after each action",
+                    node, iteration_id
+                )
+            })?;
+        let state = OrchestratorState::load_at_commit(&iter_dir, &sha)
+            .await?
+            .with_context(|| format!("Commit {} has no checkpoint to roll back to", sha))?;
+
+        // The files changed by the commits made since, including those the AI
+        // CLI wrote, and any artifact not committed yet are restored or
+        // removed; the engine's own state is kept
+        let repo = GitRepo::new(work_dir.clone());
+        let mut paths: Vec<String> = repo
+            .changed_since(&sha)
+            .await?
+            .into_iter()
+            .filter(|path| !path.starts_with(".infinitecodingloop/"))
+            .chain(
+                entries
+                    .iter()
+                    .filter_map(|e| e["path"].as_str().map(str::to_string)),
+            )
+            .collect();
+        paths.sort();
+        paths.dedup();
+        repo.restore(&sha, &paths).await?;
+
+        // Forget the artifacts written after that commit
+        let kept = entries
+            .iter()
+            .rposition(|e| e["commit"] == sha.as_str())
+            .map_or(0, |index| index + 1);
+        entries.truncate(kept);
+        let meta_content = serde_json::to_string_pretty(&entries)?;
+        tokio::fs::write(self.artifacts_meta_path()?, meta_content).await?;
+
+        self.restore_state(state);
+        self.in_flight.clear();
+        self.reuse_application_artifacts().await?;
+        self.checkpoint().await?;
+
+        if let Some(ref logger) = self.logger {
+            let _ = logger
+                .log_iteration_rolled_back(node, &sha, self.actions_executed)
+                .await;
+        }
+        let message = CommitMessage::new(format!("Roll back to {}", node))
+            .with_trailer("Pulpo-Iteration", iteration_id)
+            .with_trailer("Pulpo-Rollback", sha.as_str());
+        repo.commit_paths(&message, &paths).await?;
+        info!(
+            "Rolled back iteration {} to {} ({})",
+            iteration_id, node, sha
+        );
+        Ok(sha)
+    }
+
+    /// Marks `node` and everything that transitively depends on it as invalid:
+    /// their artifacts (in memory and in the docs folder), verification reports,
+    /// refinement and failure counters and lifecycle records are cleared, so the
+    /// next `run` regenerates only that part of the graph. Returns the
+    /// invalidated nodes, `node` first.
+    pub async fn invalidate(&mut self, node: &str) -> Result<Vec<String>> {
+        let graph = &self.executor.graph;
+        if !graph.kind_map.contains_key(node) {
+            anyhow::bail!("Unknown entity '{}'", node);
+        }
+        if graph.is_agent(node) {
+            anyhow::bail!("'{}' is an agent, not an artifact", node);
+        }
+
+        let mut invalidated = vec![node.to_string()];
+        invalidated.extend(graph.dependents(node));
+
+        // Instances of fanned-out nodes go together with their node
+        let mut instances: Vec<String> = self
+            .artifacts
+            .keys()
+            .cloned()
+            .chain(self.lifecycle.to_map().into_keys())
+            .filter(|key| {
+                let (kind, id) = split_instance(key);
+                id.is_some() && invalidated.iter().any(|n| n == kind)
+            })
+            .collect();
+        instances.sort();
+        instances.dedup();
+
+        for name in invalidated.iter().chain(&instances) {
+            let verification_name = format!("{}_verification", name);
+            for kind in [name, &verification_name] {
+                self.artifacts.remove(kind);
+                if let Some(ref work_dir) = self.work_dir {
+                    let path = work_dir
+                        .join(self.artifact_folder(kind))
+                        .join(format!("{}.json", file_stem(kind)));
+                    if path.exists() {
+                        tokio::fs::remove_file(&path).await.with_context(|| {
+                            format!("Failed to remove artifact {}", path.display())
+                        })?;
+                    }
+                }
+            }
+            self.verification_feedback.remove(name);
+            self.refinement_attempts.remove(name);
+            self.provenance.remove(name);
+            self.failure_attempts.remove(name);
+            self.failure_feedback.remove(name);
+            self.lifecycle.reset(name);
+        }
+
+        if let Some(ref logger) = self.logger {
+            let _ = logger.log_nodes_invalidated(node, &invalidated).await;
+        }
+        if self.current_iteration.is_some() {
+            self.checkpoint().await?;
+        }
+        info!("Invalidated {}: {:?}", node, invalidated);
+        Ok(invalidated)
+    }
+
+    /// Writes an artifact to the iteration's folder for its type and lists it
+    /// in artifacts.json. Returns the event it logged.
+    pub(crate) async fn persist_artifact(
+        &self,
+        name: &str,
+        data: &serde_json::Value,
+    ) -> Result<LogEvent> {
+        let iteration = self
+            .current_iteration
+            .as_ref()
+            .context("No active iteration to persist artifact")?;
+        let work_dir = self.work_dir.as_ref().context("Work directory not set")?;
+
+        // Write artifact to the docs folder of its scope
+        let folder = self.artifact_folder(name);
+        let docs_dir = work_dir.join(&folder);
+        if !docs_dir.exists() {
+            tokio::fs::create_dir_all(&docs_dir).await?;
+        }
+
+        let filename = format!("{}.json", file_stem(name));
+        let artifact_path = docs_dir.join(&filename);
+        let content = serde_json::to_string_pretty(data)?;
+        tokio::fs::write(&artifact_path, content).await?;
+
+        // Record metadata in .infinitecodingloop/iterations/{id}/artifacts.json
+        let iter_dir = work_dir
+            .join(".infinitecodingloop")
+            .join("iterations")
+            .join(&iteration.id);
+        if !iter_dir.exists() {
+            tokio::fs::create_dir_all(&iter_dir).await?;
+        }
+
+        let artifacts_meta_path = iter_dir.join("artifacts.json");
+        let mut entries: Vec<serde_json::Value> = if artifacts_meta_path.exists() {
+            let existing = tokio::fs::read_to_string(&artifacts_meta_path).await?;
+            serde_json::from_str(&existing).unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+
+        let relative_path = format!("{}/{}", folder, filename);
+        let now = chrono::Local::now().format("%Y%m%d_%H%M%S").to_string();
+        entries.push(serde_json::json!({
+            "name": name,
+            "timestamp": now,
+            "path": relative_path,
+            "hash": content_hash(data),
+            "inputs": self.provenance.inputs(name),
+        }));
+
+        let meta_content = serde_json::to_string_pretty(&entries)?;
+        tokio::fs::write(artifacts_meta_path, meta_content).await?;
+
+        debug!(
+            "Persisted artifact {} to {} (iteration {})",
+            name, relative_path, iteration.id
+        );
+
+        // Log artifact persisted
+        let persisted = LogEvent::artifact_persisted(name, &relative_path);
+        if let Some(ref logger) = self.logger {
+            let _ = logger.log(persisted.clone()).await;
+        }
+
+        Ok(persisted)
+    }
+
+    /// Path of the iteration's artifacts.json, which lists every artifact
+    /// file the engine wrote, in order.
+    fn artifacts_meta_path(&self) -> Result<PathBuf> {
+        let iteration = self
+            .current_iteration
+            .as_ref()
+            .context("No active iteration")?;
+        let work_dir = self.work_dir.as_ref().context("Work directory not set")?;
+        Ok(work_dir
+            .join(".infinitecodingloop")
+            .join("iterations")
+            .join(&iteration.id)
+            .join("artifacts.json"))
+    }
+
+    async fn artifact_entries(&self) -> Result<Vec<serde_json::Value>> {
+        let path = self.artifacts_meta_path()?;
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let existing = tokio::fs::read_to_string(&path).await?;
+        Ok(serde_json::from_str(&existing)?)
+    }
+
+    /// Artifact files written since the last commit.
+    async fn uncommitted_paths(&self) -> Result<Vec<String>> {
+        let mut paths: Vec<String> = Vec::new();
+        for entry in self.artifact_entries().await? {
+            if entry.get("commit").is_none()
+                && let Some(path) = entry["path"].as_str()
+                && !paths.iter().any(|p| p == path)
+            {
+                paths.push(path.to_string());
+            }
+        }
+        Ok(paths)
+    }
+
+    /// Adds the SHA of a commit to the entries in artifacts.json it captured,
+    /// and keeps the current checkpoint as the state right after it.
+    async fn record_commit(&self, sha: &str) -> Result<()> {
+        let artifacts_meta_path = self.artifacts_meta_path()?;
+        let mut entries = self.artifact_entries().await?;
+        for entry in entries.iter_mut().filter(|e| e.get("commit").is_none()) {
+            entry["commit"] = serde_json::Value::String(sha.to_string());
+        }
+        let meta_content = serde_json::to_string_pretty(&entries)?;
+        tokio::fs::write(&artifacts_meta_path, meta_content).await?;
+
+        let iter_dir = artifacts_meta_path
+            .parent()
+            .context("artifacts.json has no parent")?;
+        self.snapshot_state().save_at_commit(iter_dir, sha).await
+    }
+
+    pub async fn run(&mut self, ui: &impl crate::interaction::UserInteraction) -> Result<()> {
+        ui.log_info("Starting Generic Graph-Driven Orchestration...");
+        self.run_started = Some(Instant::now());
+        for name in self.adopt_docs_edits().await? {
+            ui.log_info(&format!("Picked up manual edits to {}", name));
+        }
+        if let Some(at) = self.interrupted_at.take() {
+            ui.log_info(&format!("Resuming iteration interrupted at {}", at));
+        }
+
+        // 1. Initial Input (Skip if already exists in resumed iteration)
+        if !self.handle_initial_input(ui).await? {
+            return Ok(());
+        }
+
+        // 2. Generic Execution Loop (State Machine)
+        let mut iterations = 0;
+
+        while iterations < self.max_iterations {
+            iterations += 1;
+            ui.start_step(&format!(
+                "Iteration {} - Evaluating Graph State...",
+                iterations
+            ));
+
+            // Log loop cycle
+            if let Some(ref logger) = self.logger {
+                let _ = logger.log_loop_cycle(iterations).await;
+            }
+
+            self.sync_lifecycle().await;
+            let next_actions = self.identify_next_actions();
+            if next_actions.is_empty() {
+                ui.log_info(&format!(
+                    "No more actions identified. Current artifacts: {:?}",
+                    self.artifacts.keys().collect::<Vec<_>>()
+                ));
+                break;
+            }
 
             // Log all identified actions
             if let Some(ref logger) = self.logger {
@@ -419,32 +1281,69 @@ impl<C: AiCliClient + Clone + Send + Sync + 'static> Orchestrator<C> {
                         .log_action_identified(
                             &action.agent,
                             &action.relation,
-                            &action.target,
+                            &action.key(),
                             &format!("{:?}", action.category),
                         )
                         .await;
                 }
             }
 
-            if !self.process_next_actions(next_actions, ui).await? {
-                return Ok(());
+            let cancel = self.cancel.clone();
+            let result = tokio::select! {
+                result = self.process_next_actions(next_actions, ui) => result,
+                _ = cancelled(cancel) => {
+                    // Dropping the actions above kills their AI CLI processes
+                    self.interrupt(ui).await?;
+                    self.end_iteration("interrupted", None).await;
+                    return Ok(());
+                }
+            };
+            match result {
+                Ok(true) => {}
+                Ok(false) => {
+                    self.end_iteration("paused", None).await;
+                    return Ok(());
+                }
+                Err(e) => {
+                    self.end_iteration("stopped", Some(&e)).await;
+                    return Err(e);
+                }
             }
         }
 
-        // Log iteration end
-        if let Some(ref logger) = self.logger {
-            let _ = logger
-                .log(crate::logging::LogEvent::info(
-                    crate::logging::LogEventType::IterationEnd,
-                    "Iteration completed",
-                ))
-                .await;
-        }
-
+        self.end_iteration("completed", None).await;
         ui.end_step("Goal achieved or max iterations reached.");
         Ok(())
     }
 
+    /// Logs the usage summary and the end of the run, and fires the
+    /// `iterationEnd` hooks. `outcome` is how the run ended: completed,
+    /// paused, interrupted or stopped (by `error`).
+    async fn end_iteration(&self, outcome: &str, error: Option<&anyhow::Error>) {
+        let level = if outcome == "completed" {
+            LogLevel::Info
+        } else {
+            LogLevel::Warn
+        };
+        let end = LogEvent::new(
+            LogEventType::IterationEnd,
+            level,
+            format!("Iteration {}", outcome),
+            Some(serde_json::json!({
+                "iteration": self.current_iteration.as_ref().map(|i| i.id.clone()),
+                "actions_executed": self.actions_executed,
+                "outcome": outcome,
+                "error": error.map(|e| e.to_string()),
+            })),
+        );
+        if let Some(ref logger) = self.logger {
+            let _ = logger.log_usage_summary(&self.usage).await;
+            let _ = logger.log(end.clone()).await;
+        }
+        self.fire_hooks(HookEvent::IterationEnd, &HookSubject::default(), end)
+            .await;
+    }
+
     #[allow(clippy::map_entry)]
     pub(crate) async fn handle_initial_input(
         &mut self,
@@ -469,6 +1368,7 @@ impl<C: AiCliClient + Clone + Send + Sync + 'static> Orchestrator<C> {
                 .insert("SoftwareApplication".to_string(), initial_app.clone());
             self.persist_artifact("SoftwareApplication", &initial_app)
                 .await?;
+            self.checkpoint().await?;
         }
         Ok(true)
     }
@@ -478,23 +1378,51 @@ impl<C: AiCliClient + Clone + Send + Sync + 'static> Orchestrator<C> {
         next_actions: Vec<ActionPlan>,
         ui: &impl crate::interaction::UserInteraction,
     ) -> Result<bool> {
+        let concurrent = self.concurrency > 1;
+        let mut batch = Vec::new();
+        let mut claimed_targets = HashSet::new();
+
         for action in next_actions {
+            // Two actions on the same target cannot safely run side by side;
+            // the later one is picked up again in the next cycle.
+            if concurrent && !claimed_targets.insert(action.key()) {
+                continue;
+            }
+
+            if let Some(reason) = self.budget_exceeded() {
+                return self.stop_for_budget(&reason, ui).await;
+            }
+            // Nothing in the batch has been dispatched yet, so it counts
+            // against what is left of the dispatch budget.
+            if concurrent
+                && self
+                    .budget
+                    .remaining_dispatches(&self.usage.iteration)
+                    .is_some_and(|left| batch.len() >= left)
+            {
+                break;
+            }
+
             ui.log_info(&format!(
                 "Next Action: {} {} {}",
-                action.agent, action.relation, action.target
+                action.agent,
+                action.relation,
+                action.key()
             ));
 
             if !ui
                 .confirm(&format!(
                     "Execute: {} {} {}?",
-                    action.agent, action.relation, action.target
+                    action.agent,
+                    action.relation,
+                    action.key()
                 ))
                 .await?
             {
                 // Log action skipped
                 if let Some(ref logger) = self.logger {
                     let _ = logger
-                        .log_action_skipped(&action.agent, &action.relation, &action.target)
+                        .log_action_skipped(&action.agent, &action.relation, &action.key())
                         .await;
                 }
                 ui.log_info("Action skipped by user. Pausing iteration.");
@@ -502,161 +1430,267 @@ impl<C: AiCliClient + Clone + Send + Sync + 'static> Orchestrator<C> {
                 return Ok(false);
             }
 
-            self.execute_action(action, ui).await?;
+            if concurrent {
+                batch.push(action);
+            } else if !self.execute_action(action, ui).await? {
+                return Ok(false);
+            }
+        }
+
+        if !batch.is_empty() {
+            return self.execute_actions_concurrently(batch, ui).await;
         }
         Ok(true)
     }
 
     pub fn identify_next_actions(&self) -> Vec<ActionPlan> {
-        let mut plans = Vec::new();
-
-        for edge_idx in self.executor.graph.graph.edge_indices() {
-            let (source_idx, target_idx) =
-                self.executor.graph.graph.edge_endpoints(edge_idx).unwrap();
-            let source_kind = &self.executor.graph.graph[source_idx];
-            let target_kind = &self.executor.graph.graph[target_idx];
-            let relation = self.executor.graph.graph.edge_weight(edge_idx).unwrap();
-            let edge_key = (
-                source_kind.to_string(),
-                relation.to_string(),
-                target_kind.to_string(),
-            );
-            let category = self
-                .executor
-                .graph
-                .edge_categories
-                .get(&edge_key)
-                .copied()
-                .unwrap_or(RelationCategory::Context);
-
-            if self.executor.graph.is_agent(source_kind) {
-                // Check if this action is actionable based on dependencies
-                // An artifact creation/verification is only actionable if all its Dependency edges are met.
-                let mut dependencies_met = true;
-                for target_edge in self
-                    .executor
-                    .graph
-                    .graph
-                    .edges_directed(target_idx, petgraph::Direction::Outgoing)
-                {
-                    let dep_target_idx = target_edge.target();
-                    let dep_kind = &self.executor.graph.graph[dep_target_idx];
-                    let dep_relation = target_edge.weight();
-                    let dep_edge_key = (
-                        target_kind.to_string(),
-                        dep_relation.to_string(),
-                        dep_kind.to_string(),
-                    );
-                    let dep_category = self
-                        .executor
-                        .graph
-                        .edge_categories
-                        .get(&dep_edge_key)
-                        .copied()
-                        .unwrap_or(RelationCategory::Context);
-                    if dep_category == RelationCategory::Dependency
-                        && !self.artifacts.contains_key(dep_kind)
-                    {
-                        debug!(
-                            "Action {} {} {} is blocked by missing dependency: {}",
-                            source_kind, relation, target_kind, dep_kind
-                        );
-                        dependencies_met = false;
-                        break;
-                    }
-                }
-
-                if !dependencies_met {
-                    continue;
-                }
+        // Return all logical actions. The orchestrator loop executes them sequentially,
+        // or in parallel batches when a concurrency limit above 1 is configured.
+        self.planner().next_actions()
+    }
 
-                match category {
-                    RelationCategory::Creation => {
-                        // Create if it doesn't exist
-                        if !self.artifacts.contains_key(target_kind) {
-                            plans.push(ActionPlan {
-                                agent: source_kind.clone(),
-                                target: target_kind.clone(),
-                                relation: relation.clone(),
-                                category,
-                            });
-                        }
-                    }
-                    RelationCategory::Verification => {
-                        // Verify if artifact exists but isn't already verified-perfect
-                        if self.artifacts.contains_key(target_kind)
-                            && !self.verified_artifacts.contains(target_kind)
-                            && !self.verification_feedback.contains_key(target_kind)
-                        {
-                            plans.push(ActionPlan {
-                                agent: source_kind.clone(),
-                                target: target_kind.clone(),
-                                relation: relation.clone(),
-                                category,
-                            });
-                        }
-                    }
-                    RelationCategory::Refinement => {
-                        // Refine if artifact exists AND we have feedback (indicating it needs work)
-                        // Gate on max retries from LoopConfig
-                        if self.artifacts.contains_key(target_kind)
-                            && self.verification_feedback.contains_key(target_kind)
-                        {
-                            let max_retries = self
-                                .executor
-                                .graph
-                                .loop_configs
-                                .get(&edge_key)
-                                .map(|lc| lc.max_retries)
-                                .unwrap_or(3);
-                            let attempts = self.refinement_attempts.get(target_kind).unwrap_or(&0);
-                            if *attempts < max_retries {
-                                plans.push(ActionPlan {
-                                    agent: source_kind.clone(),
-                                    target: target_kind.clone(),
-                                    relation: relation.clone(),
-                                    category,
-                                });
-                            } else {
-                                warn!(
-                                    "Max retries ({}) reached for refining {}. Skipping.",
-                                    max_retries, target_kind
-                                );
-                            }
-                        }
-                    }
-                    _ => {}
-                }
-            }
+    /// Predicts the rest of the run without dispatching any agent: the order of
+    /// actions, the artifacts each one receives as context and its rendered
+    /// prompt. Creations are assumed to succeed and verifications to pass.
+    /// `goal` seeds the SoftwareApplication when the iteration has none yet.
+    pub fn dry_run(&self, goal: &str) -> Vec<DryRunStep> {
+        let mut simulation = Simulation {
+            artifacts: self.artifacts.clone(),
+            lifecycle: self.lifecycle.clone(),
+            refinement_attempts: self.refinement_attempts.clone(),
+            provenance: self.provenance.clone(),
+        };
+        if !simulation.artifacts.contains_key("SoftwareApplication") {
+            simulation.provide(
+                "SoftwareApplication",
+                serde_json::json!({ "name": self.app_name.clone(), "goal": goal }),
+            );
         }
 
-        // Return all logical actions. The orchestrator loop will execute them sequentially.
-        plans
+        let mut steps = Vec::new();
+        simulation.predict_with(
+            &self.executor.graph,
+            self.max_iterations,
+            self.concurrency,
+            |state, planned| {
+                steps.push(DryRunStep {
+                    step: planned.step,
+                    action: planned.action.clone(),
+                    context: planned.context.clone(),
+                    prompt: self.render_prompt(&planned.action, &state.artifacts),
+                });
+            },
+        );
+        steps
     }
 
     async fn execute_action(
         &mut self,
         action: ActionPlan,
         ui: &impl crate::interaction::UserInteraction,
-    ) -> Result<()> {
-        let agent_role = AgentRole::from(action.agent.as_str());
+    ) -> Result<bool> {
+        self.mark_running(&action).await?;
+        let task = match self.prepare_action(&action).await {
+            Ok(task) => task,
+            Err(e) => return self.complete_action(action, Err(e), ui).await,
+        };
+        let role = self.dispatch_role(&action);
+        let (result, usage) = dispatch_measured(&self.executor, role, task).await;
+        self.record_usage(&action, &usage).await;
+        self.complete_action(action, result, ui).await
+    }
 
-        // Log action dispatched
-        if let Some(ref logger) = self.logger {
-            let _ = logger
-                .log_action_dispatched(
-                    &action.agent,
-                    &action.relation,
-                    &action.target,
-                    &format!("{:?}", action.category),
-                )
-                .await;
+    /// Dispatches a batch of ready actions in parallel (bounded by `concurrency`)
+    /// and merges their results back in plan order, so the resulting state does
+    /// not depend on which agent happened to answer first. Returns `false` when a
+    /// failure policy paused the iteration.
+    async fn execute_actions_concurrently(
+        &mut self,
+        actions: Vec<ActionPlan>,
+        ui: &impl crate::interaction::UserInteraction,
+    ) -> Result<bool> {
+        let mut tasks = Vec::with_capacity(actions.len());
+        for action in &actions {
+            self.mark_running(action).await?;
+            // A denied action is not dispatched; it fails like one that was
+            tasks.push(self.prepare_action(action).await);
         }
 
-        let context = self.build_action_context(&action);
-
-        let prompt_template = self
-            .executor
+        let executor = &self.executor;
+        let results: Vec<(Result<serde_json::Value>, DispatchUsage)> =
+            futures::stream::iter(actions.iter().zip(tasks).map(|(action, task)| {
+                let role = self.dispatch_role(action);
+                async move {
+                    match task {
+                        Ok(task) => dispatch_measured(executor, role, task).await,
+                        Err(e) => (Err(e), DispatchUsage::default()),
+                    }
+                }
+            }))
+            .buffered(self.concurrency)
+            .collect()
+            .await;
+
+        // Merge every result before surfacing the first failure so that
+        // successful siblings are not lost.
+        let mut first_error = None;
+        let mut proceed = true;
+        for (action, (result, usage)) in actions.into_iter().zip(results) {
+            self.record_usage(&action, &usage).await;
+            match self.complete_action(action, result, ui).await {
+                Ok(keep_going) => proceed &= keep_going,
+                Err(e) if first_error.is_none() => first_error = Some(e),
+                Err(_) => {}
+            }
+        }
+
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(proceed),
+        }
+    }
+
+    /// Adds a dispatch to the usage totals and logs what it cost.
+    async fn record_usage(&mut self, action: &ActionPlan, usage: &DispatchUsage) {
+        self.usage
+            .record(&action.agent, &action.relation, &action.target, usage);
+        if let Some(ref logger) = self.logger {
+            let _ = logger
+                .log_dispatch_usage(&action.agent, &action.relation, &action.target, usage)
+                .await;
+        }
+    }
+
+    /// Why the budget is exhausted, if it is. Wall time counts the current run.
+    fn budget_exceeded(&self) -> Option<String> {
+        let wall_secs = self
+            .run_started
+            .map(|started| started.elapsed().as_secs())
+            .unwrap_or(0);
+        self.budget.exceeded(&self.usage.iteration, wall_secs)
+    }
+
+    /// Checkpoints the iteration once its budget is exhausted. Pausing returns
+    /// `false` so the run ends cleanly; stopping fails the run.
+    async fn stop_for_budget(
+        &mut self,
+        reason: &str,
+        ui: &impl crate::interaction::UserInteraction,
+    ) -> Result<bool> {
+        let action = match self.budget.on_exceeded {
+            BudgetAction::Pause => "pause",
+            BudgetAction::Stop => "stop",
+        };
+        if let Some(ref logger) = self.logger {
+            let _ = logger.log_budget_exceeded(reason, action).await;
+        }
+        self.checkpoint().await?;
+
+        match self.budget.on_exceeded {
+            BudgetAction::Pause => {
+                ui.log_info(&format!("Budget exceeded: {}. Pausing iteration.", reason));
+                ui.end_step("Iteration paused: budget exceeded.");
+                Ok(false)
+            }
+            BudgetAction::Stop => {
+                ui.end_step("Iteration stopped: budget exceeded.");
+                Err(anyhow::anyhow!("Budget exceeded: {}", reason))
+            }
+        }
+    }
+
+    async fn mark_running(&mut self, action: &ActionPlan) -> Result<()> {
+        if self.in_flight.is_empty() {
+            self.work_tree = self.dirty_files().await;
+        }
+        self.in_flight.push(action.clone());
+        let key = action.key();
+        self.transition_node(
+            &key,
+            NodeState::Running,
+            Some(format!("{} {} {}", action.agent, action.relation, key)),
+        )
+        .await
+    }
+
+    /// Builds the task for an action (context, prompt, execution options) and
+    /// logs the dispatch, once the approval hooks allowed it.
+    async fn prepare_action(&self, action: &ActionPlan) -> Result<Task> {
+        let key = action.key();
+        let (mut options, resolution) = self.resolve_action_options(action);
+        let timeout = self.timeout_for(action);
+        options.timeout_secs = timeout.seconds;
+        options.stall_timeout_secs = timeout.stall_seconds;
+
+        // Log action dispatched
+        let dispatched = LogEvent::action_dispatched(
+            &action.agent,
+            &action.relation,
+            &key,
+            &format!("{:?}", action.category),
+            &options,
+            &resolution,
+        );
+        self.approve_dispatch(action, &dispatched).await?;
+        if let Some(ref logger) = self.logger {
+            let _ = logger.log(dispatched).await;
+        }
+
+        let enhanced_prompt = self.render_prompt(action, &self.artifacts);
+
+        // Log prompt sent
+        if let Some(ref logger) = self.logger {
+            let _ = logger
+                .log_prompt_sent(&action.agent, &key, &enhanced_prompt)
+                .await;
+        }
+
+        Ok(Task {
+            id: format!("task_{}_{}", action.relation, key),
+            description: format!("{} {}", action.relation, key),
+            inputs: vec![],
+            prompt: Some(enhanced_prompt),
+            options,
+        })
+    }
+
+    /// Agent that runs an action: the command runner for a verification
+    /// scored by a command, otherwise the action's own agent.
+    fn dispatch_role<'a>(&self, action: &'a ActionPlan) -> &'a str {
+        if self.verifier_for(action).is_some() {
+            COMMAND_RUNNER
+        } else {
+            &action.agent
+        }
+    }
+
+    fn verifier_for(&self, action: &ActionPlan) -> Option<&crate::graph::Verifier> {
+        self.executor
+            .graph
+            .verifier(&action.agent, &action.relation, &action.target)
+    }
+
+    /// Renders the full prompt for an action against the given artifacts. The
+    /// "prompt" of a Command run, or of a verification scored by a command, is
+    /// the command line.
+    fn render_prompt(
+        &self,
+        action: &ActionPlan,
+        artifacts: &HashMap<String, serde_json::Value>,
+    ) -> String {
+        if action.agent == COMMAND_RUNNER
+            && let Some(command_line) = self.executor.graph.command_line(&action.target)
+        {
+            return command_line.to_string();
+        }
+        if let Some(verifier) = self.verifier_for(action) {
+            return verifier.command.clone();
+        }
+
+        let context = self.build_action_context(action, artifacts);
+
+        let prompt_template = self
+            .executor
             .graph
             .get_prompt_template(&action.agent, &action.relation, &action.target)
             .unwrap_or_else(|| {
@@ -680,10 +1714,11 @@ impl<C: AiCliClient + Clone + Send + Sync + 'static> Orchestrator<C> {
             final_prompt = format!("{}\n\n### Context / Input:\n{}", final_prompt, context);
         }
 
+        let stem = file_stem(&action.key());
         let filename = if action.category == RelationCategory::Verification {
-            format!("{}_verification.json", action.target.to_lowercase())
+            format!("{}_verification.json", stem)
         } else {
-            format!("{}.json", action.target.to_lowercase())
+            format!("{}.json", stem)
         };
 
         let entity_type = self
@@ -692,46 +1727,116 @@ impl<C: AiCliClient + Clone + Send + Sync + 'static> Orchestrator<C> {
             .node_types
             .get(&action.target)
             .map(|s| s.as_str());
-        let enhanced_prompt =
-            self.enhance_prompt(final_prompt, &action.target, &filename, entity_type);
-
-        // Log prompt sent
-        if let Some(ref logger) = self.logger {
-            let _ = logger
-                .log_prompt_sent(&action.agent, &action.target, &enhanced_prompt)
-                .await;
+        let mut prompt = self.enhance_prompt(final_prompt, &action.target, &filename, entity_type);
+        if self.max_clarification_rounds > 0
+            && matches!(
+                action.category,
+                RelationCategory::Creation | RelationCategory::Refinement
+            )
+        {
+            prompt.push_str(clarification::INSTRUCTIONS);
         }
+        prompt
+    }
 
-        // Map the execution options
-        let options = self
-            .executor
-            .graph
-            .node_configs
-            .get(&action.target)
-            .map(|config| crate::graph::executor::ExecutionOptions {
-                model_type: config.model_type.clone(),
-                model: config.model.clone(),
-                ai_cli: config.ai_cli.clone(),
-            })
-            .unwrap_or_default();
+    /// Resolves the model and AI CLI for a node: the node's own override, then
+    /// the category default for its `modelType`, then the client default
+    /// (left unset). Returns the options together with the reason for the choice.
+    pub fn resolve_execution_options(&self, node: &str) -> (ExecutionOptions, String) {
+        let config = self.executor.graph.node_configs.get(node);
+        let model_type = config.and_then(|c| c.model_type.clone());
+        let category = model_type
+            .as_ref()
+            .and_then(|t| self.category_defaults.get(t));
 
-        let task = Task {
-            id: format!("task_{}_{}", action.relation, action.target),
-            description: format!("{} {}", action.relation, action.target),
-            inputs: vec![],
-            prompt: Some(enhanced_prompt),
-            options,
+        let pick = |own: Option<&String>, default: Option<&String>| match (own, default) {
+            (Some(v), _) => (Some(v.clone()), "node override".to_string()),
+            (None, Some(v)) => (
+                Some(v.clone()),
+                format!(
+                    "category default for '{}'",
+                    model_type.as_deref().unwrap_or_default()
+                ),
+            ),
+            (None, None) => (None, "client default".to_string()),
+        };
+        let (model, model_reason) = pick(
+            config.and_then(|c| c.model.as_ref()),
+            category.and_then(|c| c.model.as_ref()),
+        );
+        let (ai_cli, cli_reason) = pick(
+            config.and_then(|c| c.ai_cli.as_ref()),
+            category.and_then(|c| c.ai_cli.as_ref()),
+        );
+
+        let reason = if model_reason == cli_reason {
+            model_reason
+        } else {
+            format!("model from {}, CLI from {}", model_reason, cli_reason)
         };
 
-        let result = match self.executor.dispatch_agent(agent_role, task).await {
+        (
+            ExecutionOptions {
+                model_type,
+                model,
+                ai_cli,
+                ..Default::default()
+            },
+            reason,
+        )
+    }
+
+    /// Resolves the model and AI CLI for an action: those of its target, or the
+    /// (possibly inherited) defaults of its agent when the target leaves both
+    /// to the client default.
+    pub fn resolve_action_options(&self, action: &ActionPlan) -> (ExecutionOptions, String) {
+        let (mut options, reason) = self.resolve_execution_options(&action.target);
+        if options.model.is_some() || options.ai_cli.is_some() {
+            return (options, reason);
+        }
+        let (agent_options, _) = self.resolve_execution_options(&action.agent);
+        if agent_options.model.is_none() && agent_options.ai_cli.is_none() {
+            return (options, reason);
+        }
+        options.model = agent_options.model;
+        options.ai_cli = agent_options.ai_cli;
+        options.model_type = options.model_type.or(agent_options.model_type);
+        (options, format!("agent default for '{}'", action.agent))
+    }
+
+    /// Logs the agent's answer and applies it to the orchestrator state. Failures
+    /// are resolved through the edge's failure policy; returns `false` when the
+    /// iteration should pause.
+    async fn complete_action(
+        &mut self,
+        action: ActionPlan,
+        result: Result<serde_json::Value>,
+        ui: &impl crate::interaction::UserInteraction,
+    ) -> Result<bool> {
+        let key = action.key();
+        self.in_flight.retain(|a| a.key() != key);
+        let result = match result {
             Ok(val) => {
                 // Log response received
                 if let Some(ref logger) = self.logger {
                     let _ = logger
-                        .log_response_received(&action.agent, &action.target, &val)
+                        .log_response_received(&action.agent, &key, &val)
                         .await;
                 }
-                val
+                self.clarify(&action, val, ui).await
+            }
+            Err(e) => Err(e),
+        };
+        let outcome = match result {
+            Ok(Clarified::Answer(val)) => {
+                // Semantic Result Handling
+                self.handle_action_result(&action, val).await
+            }
+            Ok(Clarified::OverBudget(reason)) => {
+                // The answers so far are kept; the action is planned again on resume
+                self.transition_node(&key, replan_state(&action), Some(reason.clone()))
+                    .await?;
+                return self.stop_for_budget(&reason, ui).await;
             }
             Err(e) => {
                 // Log error
@@ -740,42 +1845,280 @@ impl<C: AiCliClient + Clone + Send + Sync + 'static> Orchestrator<C> {
                         .log_error(
                             &format!(
                                 "Agent dispatch failed: {} {} {}",
-                                action.agent, action.relation, action.target
+                                action.agent, action.relation, key
                             ),
                             Some(&format!("{}", e)),
                         )
                         .await;
                 }
-                return Err(e);
+                Err(e)
             }
         };
 
-        // Semantic Result Handling
-        self.handle_action_result(&action, result).await?;
+        let persisted = match outcome {
+            Ok(persisted) => persisted,
+            Err(e) => return self.handle_action_failure(&action, e, ui).await,
+        };
+
+        let attempt = self.failure_attempts.remove(&key).unwrap_or(0) + 1;
+        self.failure_feedback.remove(&key);
+        self.log_attempt(&action, attempt, "succeeded", None).await;
+        self.actions_executed += 1;
+        self.checkpoint().await?;
+        // Committed after the checkpoint, so that the state kept for the
+        // commit is the one right after the action
+        if matches!(
+            action.category,
+            RelationCategory::Creation | RelationCategory::Refinement
+        ) {
+            self.commit_action(&action, &key).await;
+        }
+        if let Some(persisted) = persisted {
+            let subject = self.hook_subject(&action);
+            self.fire_hooks(HookEvent::AfterPersist, &subject, persisted)
+                .await;
+        }
+
+        ui.render_artifact(&key, self.artifacts.get(&key).unwrap());
+
+        Ok(true)
+    }
+
+    /// Answers the questions an agent asked instead of producing its artifact
+    /// and dispatches the action again with the answers in context, until the
+    /// agent stops asking. Fails once `max_clarification_rounds` are used up,
+    /// and stops before dispatching again once the budget is exhausted. The
+    /// answers are kept as the node's `_clarifications` artifact.
+    async fn clarify(
+        &mut self,
+        action: &ActionPlan,
+        mut value: serde_json::Value,
+        ui: &impl crate::interaction::UserInteraction,
+    ) -> Result<Clarified> {
+        if !matches!(
+            action.category,
+            RelationCategory::Creation | RelationCategory::Refinement
+        ) {
+            return Ok(Clarified::Answer(value));
+        }
+        let key = action.key();
+        let name = clarifications_key(&key);
+        let mut round = 0;
+        while let Some(questions) = parse_questions(&value) {
+            if round >= self.max_clarification_rounds {
+                anyhow::bail!(
+                    "{} {} {} still has open questions after {} round(s) of clarification",
+                    action.agent,
+                    action.relation,
+                    key,
+                    round
+                );
+            }
+            round += 1;
+            let answers = clarification::ask(ui, &action.agent, &questions).await?;
+            if let Some(ref logger) = self.logger {
+                let _ = logger.log_clarification(&key, round, &answers).await;
+            }
+
+            let mut recorded = self
+                .artifacts
+                .get(&name)
+                .and_then(|v| v.as_array().cloned())
+                .unwrap_or_default();
+            for answer in &answers {
+                recorded.push(serde_json::to_value(answer)?);
+            }
+            let recorded = serde_json::Value::Array(recorded);
+            self.persist_artifact(&name, &recorded).await?;
+            self.artifacts.insert(name.clone(), recorded);
+
+            if let Some(reason) = self.budget_exceeded() {
+                return Ok(Clarified::OverBudget(reason));
+            }
+            let task = self.prepare_action(action).await?;
+            let (result, usage) =
+                dispatch_measured(&self.executor, self.dispatch_role(action), task).await;
+            self.record_usage(action, &usage).await;
+            value = result?;
+            if let Some(ref logger) = self.logger {
+                let _ = logger
+                    .log_response_received(&action.agent, &key, &value)
+                    .await;
+            }
+        }
+        Ok(Clarified::Answer(value))
+    }
+
+    /// Time limits for an action: the CLI override first, then the edge's
+    /// `timeout` from the ontology.
+    pub fn timeout_for(&self, action: &ActionPlan) -> TimeoutConfig {
+        let edge_key = (
+            action.agent.clone(),
+            action.relation.clone(),
+            action.target.clone(),
+        );
+        let ontology = self
+            .executor
+            .graph
+            .timeouts
+            .get(&edge_key)
+            .copied()
+            .unwrap_or_default();
+        self.timeouts.or(ontology)
+    }
+
+    /// The edge's own `onFailure` policy, or the global default.
+    pub fn failure_policy_for(&self, action: &ActionPlan) -> FailurePolicy {
+        let edge_key = (
+            action.agent.clone(),
+            action.relation.clone(),
+            action.target.clone(),
+        );
+        self.executor
+            .graph
+            .failure_policies
+            .get(&edge_key)
+            .copied()
+            .unwrap_or(self.failure_policy)
+    }
+
+    /// Applies the failure policy to a failed dispatch or rejected artifact.
+    async fn handle_action_failure(
+        &mut self,
+        action: &ActionPlan,
+        error: anyhow::Error,
+        ui: &impl crate::interaction::UserInteraction,
+    ) -> Result<bool> {
+        let key = action.key();
+        let attempt = {
+            let attempts = self.failure_attempts.entry(key.clone()).or_insert(0);
+            *attempts += 1;
+            *attempts
+        };
+        let message = format!("{:#}", error);
 
-        ui.render_artifact(&action.target, self.artifacts.get(&action.target).unwrap());
+        match self.failure_policy_for(action) {
+            FailurePolicy::Retry { max_retries } if attempt <= max_retries => {
+                warn!(
+                    "{} {} {} failed (retry {}/{}): {}",
+                    action.agent, action.relation, key, attempt, max_retries, message
+                );
+                self.failure_feedback.insert(key.clone(), message.clone());
+                self.transition_node(
+                    &key,
+                    replan_state(action),
+                    Some(format!("Retry {}/{} after failure", attempt, max_retries)),
+                )
+                .await?;
+                self.log_attempt(action, attempt, "retrying", Some(&message))
+                    .await;
+                self.checkpoint().await?;
+                Ok(true)
+            }
+            FailurePolicy::Skip => {
+                ui.log_error(&format!(
+                    "{} {} {} failed; skipping: {}",
+                    action.agent, action.relation, key, message
+                ));
+                self.transition_node(&key, NodeState::Skipped, Some(message.clone()))
+                    .await?;
+                self.log_attempt(action, attempt, "skipped", Some(&message))
+                    .await;
+                self.checkpoint().await?;
+                Ok(true)
+            }
+            FailurePolicy::Pause => {
+                ui.log_error(&format!(
+                    "{} {} {} failed: {}",
+                    action.agent, action.relation, key, message
+                ));
+                self.transition_node(&key, NodeState::Failed, Some(message.clone()))
+                    .await?;
+                self.log_attempt(action, attempt, "paused", Some(&message))
+                    .await;
+                self.checkpoint().await?;
+                ui.end_step("Iteration paused after a failure. Resume it to try again.");
+                Ok(false)
+            }
+            FailurePolicy::Retry { .. } | FailurePolicy::Abort => {
+                self.transition_node(&key, NodeState::Failed, Some(message.clone()))
+                    .await?;
+                self.log_attempt(action, attempt, "aborted", Some(&message))
+                    .await;
+                Err(error)
+            }
+        }
+    }
 
+    /// Records a cancelled run: in-flight actions go back to the state they were
+    /// planned from and the checkpoint is marked as interrupted.
+    async fn interrupt(&mut self, ui: &impl crate::interaction::UserInteraction) -> Result<()> {
+        let in_flight = std::mem::take(&mut self.in_flight);
+        for action in &in_flight {
+            self.transition_node(
+                &action.key(),
+                replan_state(action),
+                Some("Interrupted".to_string()),
+            )
+            .await?;
+        }
+        if let Some(ref logger) = self.logger {
+            let targets: Vec<String> = in_flight.iter().map(|a| a.key()).collect();
+            let _ = logger.log_iteration_interrupted(&targets).await;
+        }
+        self.interrupted_at = Some(chrono::Local::now().format("%Y%m%d_%H%M%S").to_string());
+        if self.current_iteration.is_some() {
+            self.checkpoint().await?;
+        }
+        ui.end_step("Iteration interrupted. Resume it to continue.");
         Ok(())
     }
 
+    async fn log_attempt(
+        &self,
+        action: &ActionPlan,
+        attempt: usize,
+        outcome: &str,
+        error: Option<&str>,
+    ) {
+        if let Some(ref logger) = self.logger {
+            let _ = logger
+                .log_action_attempt(
+                    &action.agent,
+                    &action.relation,
+                    &action.key(),
+                    attempt,
+                    outcome,
+                    error,
+                )
+                .await;
+        }
+    }
+
+    /// Applies an agent's answer. Returns the event logged when the action
+    /// created or refined its artifact.
     async fn handle_action_result(
         &mut self,
         action: &ActionPlan,
         result: serde_json::Value,
-    ) -> Result<()> {
+    ) -> Result<Option<LogEvent>> {
         match action.category {
             RelationCategory::Verification => {
                 self.handle_verification_result(action, result).await?;
             }
             RelationCategory::Refinement | RelationCategory::Creation => {
-                self.handle_creation_or_refinement(action, result).await?;
+                return Ok(Some(
+                    self.handle_creation_or_refinement(action, result).await?,
+                ));
             }
             _ => {
-                self.artifacts.insert(action.target.clone(), result.clone());
-                self.persist_artifact(&action.target, &result).await?;
+                let key = action.key();
+                self.artifacts.insert(key.clone(), result.clone());
+                self.persist_artifact(&key, &result).await?;
+                self.transition_node(&key, NodeState::Created, None).await?;
             }
         }
-        Ok(())
+        Ok(None)
     }
 
     async fn handle_verification_result(
@@ -783,6 +2126,12 @@ impl<C: AiCliClient + Clone + Send + Sync + 'static> Orchestrator<C> {
         action: &ActionPlan,
         result: serde_json::Value,
     ) -> Result<()> {
+        let key = action.key();
+        // A verifier's score comes from its command, never from an agent
+        let result = match self.verifier_for(action) {
+            Some(verifier) => verifier.report(&result),
+            None => result,
+        };
         let feedback = result
             .get("feedback")
             .and_then(|v| v.as_str())
@@ -791,42 +2140,61 @@ impl<C: AiCliClient + Clone + Send + Sync + 'static> Orchestrator<C> {
 
         let score = result.get("score").and_then(|v| v.as_f64()).unwrap_or(1.0);
 
-        // Look up pass_threshold from any verification LoopConfig for this target
+        // The threshold is the one of this very edge: a target may be verified
+        // by several edges, each with its own
+        let edge = (
+            action.agent.clone(),
+            action.relation.clone(),
+            action.target.clone(),
+        );
         let pass_threshold = self
             .executor
             .graph
             .loop_configs
-            .iter()
-            .find(|((_, _, t), _)| t == &action.target)
-            .map(|(_, lc)| lc.pass_threshold)
+            .get(&edge)
+            .map(|lc| lc.pass_threshold)
             .unwrap_or(1.0);
 
         // Log verification result
+        let verification = LogEvent::verification(&key, score, pass_threshold, feedback);
         if let Some(ref logger) = self.logger {
-            let _ = logger
-                .log_verification(&action.target, score, pass_threshold, feedback)
-                .await;
+            let _ = logger.log(verification.clone()).await;
         }
 
         if score < pass_threshold {
             warn!(
                 "Verification failed for {} (score: {:.2}, threshold: {:.2}): {}",
-                action.target, score, pass_threshold, feedback
+                key, score, pass_threshold, feedback
             );
+            let subject = self.hook_subject(action);
+            self.fire_hooks(HookEvent::VerificationFailed, &subject, verification)
+                .await;
             self.verification_feedback
-                .insert(action.target.clone(), feedback.to_string());
-            self.verified_artifacts.remove(&action.target);
+                .insert(key.clone(), feedback.to_string());
+            let attempts = self.refinement_attempts.get(&key).copied().unwrap_or(0);
+            let next_state = if attempts >= self.planner().max_refinement_retries(&action.target) {
+                NodeState::Exhausted
+            } else {
+                NodeState::NeedsRefinement
+            };
+            self.transition_node(&key, next_state, Some(feedback.to_string()))
+                .await?;
         } else {
             info!(
                 "Verification passed for {} (score: {:.2} >= {:.2})",
-                action.target, score, pass_threshold
+                key, score, pass_threshold
             );
-            self.verification_feedback.remove(&action.target);
-            self.verified_artifacts.insert(action.target.clone());
+            self.verification_feedback.remove(&key);
+            self.transition_node(
+                &key,
+                NodeState::Verified,
+                Some(format!("score {:.2} >= {:.2}", score, pass_threshold)),
+            )
+            .await?;
         }
 
         // Persist verification report
-        let verification_name = format!("{}_verification", action.target);
+        let verification_name = format!("{}_verification", key);
         self.artifacts
             .insert(verification_name.clone(), result.clone());
         self.persist_artifact(&verification_name, &result).await?;
@@ -834,11 +2202,13 @@ impl<C: AiCliClient + Clone + Send + Sync + 'static> Orchestrator<C> {
         Ok(())
     }
 
+    /// Validates and persists a created or refined artifact. Returns the event
+    /// logged for it.
     async fn handle_creation_or_refinement(
         &mut self,
         action: &ActionPlan,
         result: serde_json::Value,
-    ) -> Result<()> {
+    ) -> Result<LogEvent> {
         let graph = self.executor.graph.clone();
         let target = action.target.clone();
         let result_clone = result.clone();
@@ -883,94 +2253,85 @@ impl<C: AiCliClient + Clone + Send + Sync + 'static> Orchestrator<C> {
             }
         }
 
-        info!("Successfully created/refined artifact: {}", action.target);
-        self.artifacts.insert(action.target.clone(), result.clone());
-        self.persist_artifact(&action.target, &result).await?;
-
-        self.verified_artifacts.remove(&action.target);
-
-        // Instruct AI CLI to commit the changes
-        let commit_prompt = format!(
-            "The agent {} has successfully {} the artifact {}. Please stage all changes (`git add .`) and commit them to the git repository with a descriptive message like '{} {} {}'. Execute the git commands to commit the work.",
-            action.agent,
-            if action.category == RelationCategory::Creation {
-                "created"
-            } else {
-                "refined"
-            },
-            action.target,
-            if action.category == RelationCategory::Creation {
-                "Create"
-            } else {
-                "Refine"
-            },
-            action.target,
-            "artifact"
-        );
+        let key = action.key();
+        info!("Successfully created/refined artifact: {}", key);
+        let was_stale = !self.planner().stale_inputs(&key).is_empty();
+        let upstream = self.planner().upstream(&action.target);
+        self.provenance.record(&key, &upstream, &self.artifacts);
+        self.artifacts.insert(key.clone(), result.clone());
+        let persisted = self.persist_artifact(&key, &result).await?;
+
+        // Once every instance exists, hand the whole collection downstream.
+        // Nodes fanned out over an empty collection are produced as `[]`.
+        let mut fanned_out: Vec<String> = self
+            .executor
+            .graph
+            .for_each
+            .iter()
+            .filter(|(_, source)| **source == key)
+            .map(|(node, _)| node.clone())
+            .collect();
+        fanned_out.sort();
+        for node in std::iter::once(action.target.clone()).chain(fanned_out) {
+            if let Some(collection) = self.planner().aggregate(&node)
+                && self.artifacts.get(&node) != Some(&collection)
+            {
+                self.persist_artifact(&node, &collection).await?;
+                self.artifacts.insert(node, collection);
+            }
+        }
 
-        if let Err(e) = self.client.prompt(&commit_prompt, Default::default()).await {
-            warn!("Failed to commit changes via AI CLI: {}", e);
+        let produced_state = if self
+            .planner()
+            .has_agent_edge(&action.target, RelationCategory::Verification)
+        {
+            NodeState::AwaitingVerification
         } else {
-            info!("Successfully committed changes to git via AI CLI.");
-        }
+            NodeState::Created
+        };
+        self.transition_node(&key, produced_state, None).await?;
 
-        if action.category == RelationCategory::Refinement {
-            self.verification_feedback.remove(&action.target);
+        // Catching up with changed inputs does not use up verification retries
+        if action.category == RelationCategory::Refinement && !was_stale {
+            self.verification_feedback.remove(&key);
             // Track refinement attempts for loop exit
-            let attempts = self
-                .refinement_attempts
-                .entry(action.target.clone())
-                .or_insert(0);
+            let attempts = self.refinement_attempts.entry(key.clone()).or_insert(0);
             *attempts += 1;
-            info!("Refinement attempt {} for {}", attempts, action.target);
+            let attempts = *attempts;
+            info!("Refinement attempt {} for {}", attempts, key);
 
             // Log refinement attempt
             if let Some(ref logger) = self.logger {
-                let max_retries = self
-                    .executor
-                    .graph
-                    .loop_configs
-                    .iter()
-                    .find(|((_, _, t), _)| t == &action.target)
-                    .map(|(_, lc)| lc.max_retries)
-                    .unwrap_or(3);
+                let max_retries = self.planner().max_refinement_retries(&action.target);
                 let _ = logger
-                    .log_refinement_attempt(&action.target, *attempts, max_retries)
+                    .log_refinement_attempt(&key, attempts, max_retries)
                     .await;
             }
         }
-        Ok(())
+        Ok(persisted)
     }
 
-    fn build_action_context(&self, action: &ActionPlan) -> String {
+    fn build_action_context(
+        &self,
+        action: &ActionPlan,
+        artifacts: &HashMap<String, serde_json::Value>,
+    ) -> String {
         // Find Input Context
-        let mut context_map = HashMap::new();
+        let mut context_map = serde_json::Map::new();
         let mut reference_instructions = String::new();
 
-        // Get related artifacts from the graph
-        let mut related_artifacts: HashSet<String> = self
-            .executor
-            .graph
-            .get_related_artifacts(&action.target)
-            .into_iter()
-            .collect();
-
-        // Always include the target itself if we are refining or verifying
-        if matches!(
-            action.category,
-            RelationCategory::Refinement | RelationCategory::Verification
-        ) {
-            related_artifacts.insert(action.target.clone());
-        }
-
-        // Always include SoftwareApplication if available (as global context)
-        related_artifacts.insert("SoftwareApplication".to_string());
-
-        for (kind, val) in &self.artifacts {
-            // Only include if related
-            if !related_artifacts.contains(kind) {
+        // Related artifacts, the target itself when refining or verifying, and SoftwareApplication
+        let planner = Planner::new(
+            &self.executor.graph,
+            artifacts,
+            &self.lifecycle,
+            &self.refinement_attempts,
+        )
+        .with_provenance(&self.provenance);
+        for kind in planner.context_artifacts(action) {
+            let Some(val) = planner.context_value(action, &kind) else {
                 continue;
-            }
+            };
 
             debug!("  [Context] Retrieving: {}", kind);
 
@@ -979,7 +2340,7 @@ impl<C: AiCliClient + Clone + Send + Sync + 'static> Orchestrator<C> {
                 .executor
                 .graph
                 .node_types
-                .get(kind)
+                .get(&kind)
                 .is_some_and(|t| t == "Code");
 
             if is_code {
@@ -1020,11 +2381,39 @@ impl<C: AiCliClient + Clone + Send + Sync + 'static> Orchestrator<C> {
 
         // If refining, inject feedback
         if action.category == RelationCategory::Refinement
-            && let Some(feedback) = self.verification_feedback.get(&action.target)
+            && let Some(feedback) = self.verification_feedback.get(&action.key())
         {
             context = format!("{}\n\n### FEEDBACK FOR REFINEMENT:\n{}", context, feedback);
         }
 
+        // If the artifact is stale, say which inputs it has to catch up with
+        let changed = planner.stale_inputs(&action.key());
+        if action.category != RelationCategory::Verification && !changed.is_empty() {
+            context = format!(
+                "{}\n\n### UPSTREAM CHANGES:\nThese inputs changed since {} was produced: {}. Update it to reflect them.",
+                context,
+                action.key(),
+                changed.join(", ")
+            );
+        }
+
+        // Answers the user gave to the agent's questions
+        if let Some(answers) = artifacts.get(&clarifications_key(&action.key())) {
+            context = format!(
+                "{}\n\n### ANSWERS FROM THE USER:\n{}\nRely on these answers and do not ask these questions again.",
+                context,
+                serde_json::to_string_pretty(answers).unwrap_or_default()
+            );
+        }
+
+        // If retrying after a failure, tell the agent what went wrong
+        if let Some(error) = self.failure_feedback.get(&action.key()) {
+            context = format!(
+                "{}\n\n### ERRORS FROM PREVIOUS ATTEMPT:\n{}\nFix these errors in your response.",
+                context, error
+            );
+        }
+
         context
     }
 
@@ -1041,7 +2430,7 @@ impl<C: AiCliClient + Clone + Send + Sync + 'static> Orchestrator<C> {
         let mut base = format!("{}\n\nPlease generate the {} artifact.", prompt, target);
 
         // Determine the documents path for file writing
-        let docs_path = &self.docs_folder;
+        let docs_path = self.artifact_folder(target);
 
         // For artifacts WITHOUT a schema, instruct the AI CLI to write the file
         // For artifacts WITH a schema, the orchestrator handles persistence via persist_artifact
@@ -1107,269 +2496,1627 @@ impl<C: AiCliClient + Clone + Send + Sync + 'static> Orchestrator<C> {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::agents::cli_client::mocks::MockCliClient;
-    use tempfile::tempdir;
+/// Outcome of answering an agent's questions.
+enum Clarified {
+    /// The agent produced its artifact.
+    Answer(serde_json::Value),
+    /// The budget ran out before the agent could be asked again.
+    OverBudget(String),
+}
 
-    #[tokio::test]
-    async fn test_orchestrator_initialization() -> Result<()> {
-        let temp_dir = tempdir()?;
-        let client = MockCliClient::new();
+/// The state from which an action is planned, so it can be planned again after
+/// an interruption or a failed attempt.
+fn replan_state(action: &ActionPlan) -> NodeState {
+    match action.category {
+        RelationCategory::Verification => NodeState::AwaitingVerification,
+        RelationCategory::Refinement => NodeState::NeedsRefinement,
+        _ => NodeState::Ready,
+    }
+}
+
+/// Dispatches a task and measures what it cost. The response size is taken
+/// from the parsed answer; token counts are only known when the AI client
+/// reports them.
+async fn dispatch_measured(
+    executor: &InMemoryExecutor,
+    agent: &str,
+    task: Task,
+) -> (Result<serde_json::Value>, DispatchUsage) {
+    let prompt_chars = task.prompt.as_ref().map_or(0, |p| p.chars().count());
+    let tokens = task.options.usage.clone();
+    let started = Instant::now();
+    let result = executor.dispatch_agent(AgentRole::from(agent), task).await;
+    let usage = DispatchUsage {
+        prompt_chars,
+        response_chars: result
+            .as_ref()
+            .map_or(0, |value| value.to_string().chars().count()),
+        duration_ms: started.elapsed().as_millis() as u64,
+        tokens: tokens.take(),
+    };
+    (result, usage)
+}
+
+/// Resolves once cancellation is requested; never resolves without a channel.
+async fn cancelled(cancel: Option<tokio::sync::watch::Receiver<bool>>) {
+    match cancel {
+        Some(mut rx) => {
+            if rx.wait_for(|c| *c).await.is_err() {
+                std::future::pending::<()>().await;
+            }
+        }
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::cli_client::mocks::MockCliClient;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_orchestrator_initialization() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let client = MockCliClient::new();
+        let orchestrator = Orchestrator::new(
+            client,
+            "test-app".to_string(),
+            "Test App".to_string(),
+            temp_dir.path().to_path_buf(),
+        )
+        .await?;
+
+        assert_eq!(orchestrator.app_id, "test-app");
+        assert_eq!(orchestrator.app_name, "Test App");
+        assert_eq!(orchestrator.work_dir, Some(temp_dir.path().to_path_buf()));
+        assert_eq!(orchestrator.max_iterations, 100);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_orchestrator_builder_methods() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let client = MockCliClient::new();
         let orchestrator = Orchestrator::new(
             client,
             "test-app".to_string(),
             "Test App".to_string(),
             temp_dir.path().to_path_buf(),
         )
+        .await?
+        .with_max_iterations(50)
+        .with_concurrency(4)
+        .with_docs_folder("custom_docs".to_string());
+
+        assert_eq!(orchestrator.max_iterations, 50);
+        assert_eq!(orchestrator.concurrency, 4);
+        assert_eq!(orchestrator.docs_folder, "custom_docs");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_start_iteration() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let client = MockCliClient::new();
+        let mut orchestrator = Orchestrator::new(
+            client,
+            "test-app".to_string(),
+            "Test App".to_string(),
+            temp_dir.path().to_path_buf(),
+        )
+        .await?;
+
+        orchestrator.start_iteration("Feature Test").await?;
+
+        assert!(orchestrator.current_iteration.is_some());
+        let iter = orchestrator.current_iteration.as_ref().unwrap();
+        assert_eq!(iter.name, "Feature Test");
+
+        let icl_dir = temp_dir.path().join(".infinitecodingloop");
+        let iter_dir = icl_dir.join("iterations").join(&iter.id);
+        assert!(iter_dir.exists());
+        assert!(iter_dir.join("iteration.json").exists());
+        assert!(temp_dir.path().join("spec").exists()); // Default docs folder
+
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_orchestrator_identify_next_actions() -> Result<()> {
+        let client = MockCliClient::new();
+        let temp_dir = tempdir()?;
+        let work_dir = temp_dir.path().to_path_buf();
+
+        let metamodel_json = r#"[
+            {"source": {"name": "SoftwareApplication", "type": "Other"}, "target": {"name": "TestArtifact", "type": "Other"}, "type": {"name": "Creation", "verbType": "Creation"}},
+            {"source": {"name": "Agent", "type": "Agent"}, "target": {"name": "TestArtifact", "type": "Other"}, "type": {"name": "Creation", "verbType": "Creation"}}
+        ]"#;
+
+        let orchestrator = Orchestrator::new_with_metamodel(
+            client,
+            "test_app".to_string(),
+            "Test App".to_string(),
+            work_dir,
+            metamodel_json,
+            None,
+        )
+        .await?;
+
+        let actions = orchestrator.identify_next_actions();
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].agent, "Agent");
+        assert_eq!(actions[0].target, "TestArtifact");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_orchestrator_get_execution_status() -> Result<()> {
+        let client = MockCliClient::new();
+        let temp_dir = tempdir()?;
+        let work_dir = temp_dir.path().to_path_buf();
+
+        let metamodel_json = r#"[
+            {"source": {"name": "Agent", "type": "Agent"}, "target": {"name": "TestArtifact", "type": "Other"}, "type": {"name": "Creation", "verbType": "Creation"}}
+        ]"#;
+
+        let mut orchestrator = Orchestrator::new_with_metamodel(
+            client,
+            "test_app".to_string(),
+            "Test App".to_string(),
+            work_dir,
+            metamodel_json,
+            None,
+        )
+        .await?;
+
+        let (done, pending) = orchestrator.get_execution_status();
+        assert_eq!(done.len(), 0);
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0], "TestArtifact");
+
+        orchestrator.artifacts.insert(
+            "TestArtifact".to_string(),
+            serde_json::json!({"status": "done"}),
+        );
+
+        let (done, pending) = orchestrator.get_execution_status();
+        assert_eq!(done.len(), 1);
+        assert_eq!(done[0], "TestArtifact");
+        assert_eq!(pending.len(), 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_orchestrator_handle_initial_input() -> Result<()> {
+        use crate::interaction::mocks::MockUserInteraction;
+        let client = MockCliClient::new();
+        let temp_dir = tempdir()?;
+        let work_dir = temp_dir.path().to_path_buf();
+
+        let mut orchestrator = Orchestrator::new_with_metamodel(
+            client,
+            "test_app".to_string(),
+            "Test App".to_string(),
+            work_dir,
+            "[]",
+            None,
+        )
+        .await?;
+
+        let ui = MockUserInteraction::new();
+        ui.add_feature_response("Build a login page".to_string());
+
+        let result = orchestrator.handle_initial_input(&ui).await?;
+        assert!(result);
+        assert!(orchestrator.artifacts.contains_key("SoftwareApplication"));
+
+        let app_artifact = orchestrator.artifacts.get("SoftwareApplication").unwrap();
+        assert_eq!(app_artifact["goal"], "Build a login page");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_orchestrator_execute_action() -> Result<()> {
+        use crate::interaction::mocks::MockUserInteraction;
+        let client = MockCliClient::new();
+        let temp_dir = tempdir()?;
+        let work_dir = temp_dir.path().to_path_buf();
+
+        let metamodel_json = r#"[
+            {"source": {"name": "Agent", "type": "Agent"}, "target": {"name": "TestArtifact", "type": "Other"}, "type": {"name": "Creation", "verbType": "Creation"}}
+        ]"#;
+
+        let mut orchestrator = Orchestrator::new_with_metamodel(
+            client.clone(),
+            "test_app".to_string(),
+            "Test App".to_string(),
+            work_dir,
+            metamodel_json,
+            None,
+        )
+        .await?;
+
+        orchestrator.start_iteration("Execution Test").await?;
+
+        // Mock AI Response
+        client.add_response(r#"{"status": "success", "content": "Hello World"}"#.to_string());
+
+        let ui = MockUserInteraction::new();
+        let action = ActionPlan {
+            agent: "Agent".to_string(),
+            relation: "Creation".to_string(),
+            target: "TestArtifact".to_string(),
+            category: RelationCategory::Creation,
+            instance: None,
+        };
+
+        orchestrator.execute_action(action, &ui).await?;
+
+        assert!(orchestrator.artifacts.contains_key("TestArtifact"));
+        let artifact = orchestrator.artifacts.get("TestArtifact").unwrap();
+        assert_eq!(artifact["content"], "Hello World");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resolve_execution_options_chain() -> Result<()> {
+        use crate::interaction::mocks::MockUserInteraction;
+        let client = MockCliClient::new();
+        let temp_dir = tempdir()?;
+        let work_dir = temp_dir.path().to_path_buf();
+
+        let metamodel_json = r#"[
+            {"source": {"name": "Architect", "type": "Agent"}, "target": {"name": "Design", "type": "Other", "modelType": "High Reasoning"}, "type": {"name": "creates", "verbType": "Creation"}},
+            {"source": {"name": "Dev", "type": "Agent"}, "target": {"name": "Code", "type": "Other", "modelType": "High Reasoning", "model": "pinned-model"}, "type": {"name": "creates", "verbType": "Creation"}},
+            {"source": {"name": "Writer", "type": "Agent"}, "target": {"name": "Notes", "type": "Other"}, "type": {"name": "creates", "verbType": "Creation"}}
+        ]"#;
+
+        let mut defaults = HashMap::new();
+        defaults.insert(
+            "High Reasoning".to_string(),
+            ExecutionOptions {
+                model_type: Some("High Reasoning".to_string()),
+                model: Some("smart-model".to_string()),
+                ai_cli: Some("gemini".to_string()),
+                ..Default::default()
+            },
+        );
+        let mut orchestrator = Orchestrator::new_with_metamodel(
+            client.clone(),
+            "test_app".to_string(),
+            "Test App".to_string(),
+            work_dir.clone(),
+            metamodel_json,
+            None,
+        )
+        .await?
+        .with_category_defaults(defaults);
+
+        let (options, reason) = orchestrator.resolve_execution_options("Design");
+        assert_eq!(options.model.as_deref(), Some("smart-model"));
+        assert_eq!(options.ai_cli.as_deref(), Some("gemini"));
+        assert_eq!(reason, "category default for 'High Reasoning'");
+
+        let (options, reason) = orchestrator.resolve_execution_options("Code");
+        assert_eq!(options.model.as_deref(), Some("pinned-model"));
+        assert_eq!(options.ai_cli.as_deref(), Some("gemini"));
+        assert_eq!(
+            reason,
+            "model from node override, CLI from category default for 'High Reasoning'"
+        );
+
+        let (options, reason) = orchestrator.resolve_execution_options("Notes");
+        assert!(options.model.is_none() && options.ai_cli.is_none());
+        assert_eq!(reason, "client default");
+
+        // The choice is recorded on the dispatch event.
+        orchestrator.start_iteration("Resolution Test").await?;
+        client.add_response(r#"{"content": "design"}"#.to_string());
+        let action = ActionPlan {
+            agent: "Architect".to_string(),
+            relation: "creates".to_string(),
+            target: "Design".to_string(),
+            category: RelationCategory::Creation,
+            instance: None,
+        };
+        orchestrator
+            .execute_action(action, &MockUserInteraction::new())
+            .await?;
+
+        let iteration_id = orchestrator.current_iteration.as_ref().unwrap().id.clone();
+        let log = std::fs::read_to_string(
+            work_dir
+                .join(".infinitecodingloop/iterations")
+                .join(iteration_id)
+                .join("logs/execution.jsonl"),
+        )?;
+        let dispatched: serde_json::Value = log
+            .lines()
+            .map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap())
+            .find(|e| e["event_type"] == "action_dispatched")
+            .unwrap();
+        assert_eq!(dispatched["details"]["model"], "smart-model");
+        assert_eq!(dispatched["details"]["ai_cli"], "gemini");
+        assert_eq!(
+            dispatched["details"]["resolution"],
+            "category default for 'High Reasoning'"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_predicts_without_dispatching() -> Result<()> {
+        let client = MockCliClient::new();
+        let temp_dir = tempdir()?;
+
+        let metamodel_json = r#"[
+            {"source": {"name": "Architect", "type": "Agent"}, "target": {"name": "Design", "type": "Other"}, "type": {"name": "creates", "verbType": "Creation"}},
+            {"source": {"name": "Dev", "type": "Agent"}, "target": {"name": "Code", "type": "Other"}, "type": {"name": "creates", "verbType": "Creation"}},
+            {"source": {"name": "Code", "type": "Other"}, "target": {"name": "Design", "type": "Other"}, "type": {"name": "dependsOn", "verbType": "Dependency"}}
+        ]"#;
+
+        let orchestrator = Orchestrator::new_with_metamodel(
+            client.clone(),
+            "test_app".to_string(),
+            "Test App".to_string(),
+            temp_dir.path().to_path_buf(),
+            metamodel_json,
+            None,
+        )
         .await?;
+        client.add_response("unused".to_string());
+
+        let steps = orchestrator.dry_run("Build a todo app");
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].action.target, "Design");
+        assert_eq!(steps[0].context, vec!["SoftwareApplication".to_string()]);
+        assert!(steps[0].prompt.contains("Build a todo app"));
+        assert_eq!(steps[1].action.target, "Code");
+        assert_eq!(
+            steps[1].context,
+            vec!["Design".to_string(), "SoftwareApplication".to_string()]
+        );
+        assert!(steps[1].prompt.contains("<predicted output of Architect>"));
+
+        // Nothing was dispatched and the real state is untouched.
+        assert_eq!(client.responses.lock().unwrap().len(), 1);
+        assert!(orchestrator.artifacts.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_failure_policy_retry_feeds_errors_back() -> Result<()> {
+        use crate::interaction::mocks::MockUserInteraction;
+        let client = MockCliClient::new();
+        let temp_dir = tempdir()?;
+        let work_dir = temp_dir.path().to_path_buf();
+
+        let metamodel_json = r#"[
+            {"source": {"name": "Agent", "type": "Agent"}, "target": {"name": "TestArtifact", "type": "Other"}, "type": {"name": "creates", "verbType": "Creation"}, "onFailure": {"action": "retry", "maxRetries": 1}}
+        ]"#;
+
+        let mut orchestrator = Orchestrator::new_with_metamodel(
+            client.clone(),
+            "test_app".to_string(),
+            "Test App".to_string(),
+            work_dir.clone(),
+            metamodel_json,
+            None,
+        )
+        .await?;
+        orchestrator.start_iteration("Retry Test").await?;
+
+        client.add_action(|_| Err(anyhow::anyhow!("CLI crashed")));
+        client.add_action(|prompt| {
+            assert!(prompt.contains("### ERRORS FROM PREVIOUS ATTEMPT:\nCLI crashed"));
+            Ok(r#"{"content": "second try"}"#.to_string())
+        });
+
+        let ui = MockUserInteraction::new();
+        let action = ActionPlan {
+            agent: "Agent".to_string(),
+            relation: "creates".to_string(),
+            target: "TestArtifact".to_string(),
+            category: RelationCategory::Creation,
+            instance: None,
+        };
+
+        assert!(orchestrator.execute_action(action.clone(), &ui).await?);
+        assert_eq!(
+            orchestrator.node_status("TestArtifact").state,
+            NodeState::Ready
+        );
+        assert_eq!(orchestrator.identify_next_actions().len(), 1);
+
+        assert!(orchestrator.execute_action(action, &ui).await?);
+        assert_eq!(
+            orchestrator.artifacts["TestArtifact"]["content"],
+            "second try"
+        );
+        assert!(orchestrator.failure_attempts.is_empty());
+        assert!(orchestrator.failure_feedback.is_empty());
+
+        let iteration_id = orchestrator.current_iteration.as_ref().unwrap().id.clone();
+        let log = std::fs::read_to_string(
+            work_dir
+                .join(".infinitecodingloop/iterations")
+                .join(iteration_id)
+                .join("logs/execution.jsonl"),
+        )?;
+        let outcomes: Vec<(u64, String)> = log
+            .lines()
+            .map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap())
+            .filter(|e| e["event_type"] == "action_attempt")
+            .map(|e| {
+                (
+                    e["details"]["attempt"].as_u64().unwrap(),
+                    e["details"]["outcome"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![(1, "retrying".to_string()), (2, "succeeded".to_string())]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_failure_policy_skip_pause_and_abort() -> Result<()> {
+        use crate::interaction::mocks::MockUserInteraction;
+        let metamodel_json = r#"[
+            {"source": {"name": "Agent", "type": "Agent"}, "target": {"name": "TestArtifact", "type": "Other"}, "type": {"name": "creates", "verbType": "Creation"}}
+        ]"#;
+        let action = ActionPlan {
+            agent: "Agent".to_string(),
+            relation: "creates".to_string(),
+            target: "TestArtifact".to_string(),
+            category: RelationCategory::Creation,
+            instance: None,
+        };
+        let ui = MockUserInteraction::new();
+
+        for (policy, expected) in [
+            (FailurePolicy::Skip, Some((true, NodeState::Skipped))),
+            (FailurePolicy::Pause, Some((false, NodeState::Failed))),
+            (FailurePolicy::Abort, None),
+        ] {
+            let client = MockCliClient::new();
+            let temp_dir = tempdir()?;
+            let mut orchestrator = Orchestrator::new_with_metamodel(
+                client.clone(),
+                "test_app".to_string(),
+                "Test App".to_string(),
+                temp_dir.path().to_path_buf(),
+                metamodel_json,
+                None,
+            )
+            .await?
+            .with_failure_policy(policy);
+            orchestrator.start_iteration("Policy Test").await?;
+            client.add_action(|_| Err(anyhow::anyhow!("CLI crashed")));
+
+            let result = orchestrator.execute_action(action.clone(), &ui).await;
+            match expected {
+                Some((proceed, state)) => {
+                    assert_eq!(result?, proceed);
+                    assert_eq!(orchestrator.node_status("TestArtifact").state, state);
+                }
+                None => assert!(result.is_err()),
+            }
+            assert!(orchestrator.identify_next_actions().is_empty());
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_timeout_cli_override_wins_over_ontology() -> Result<()> {
+        let temp_dir = tempdir()?;
+        let metamodel_json = r#"[
+            {"source": {"name": "Dev", "type": "Agent"}, "target": {"name": "Code", "type": "Other"}, "type": {"name": "creates", "verbType": "Creation"}, "timeout": {"seconds": 600, "stallSeconds": 120}}
+        ]"#;
+        let action = ActionPlan {
+            agent: "Dev".to_string(),
+            relation: "creates".to_string(),
+            target: "Code".to_string(),
+            category: RelationCategory::Creation,
+            instance: None,
+        };
+
+        let orchestrator = Orchestrator::new_with_metamodel(
+            MockCliClient::new(),
+            "test_app".to_string(),
+            "Test App".to_string(),
+            temp_dir.path().to_path_buf(),
+            metamodel_json,
+            None,
+        )
+        .await?;
+        let timeout = orchestrator.timeout_for(&action);
+        assert_eq!(
+            (timeout.seconds, timeout.stall_seconds),
+            (Some(600), Some(120))
+        );
+
+        let orchestrator = orchestrator.with_timeouts(TimeoutConfig {
+            seconds: Some(30),
+            stall_seconds: None,
+        });
+        let timeout = orchestrator.timeout_for(&action);
+        assert_eq!(
+            (timeout.seconds, timeout.stall_seconds),
+            (Some(30), Some(120))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_orchestrator_handle_verification() -> Result<()> {
+        use crate::interaction::mocks::MockUserInteraction;
+        let client = MockCliClient::new();
+        let temp_dir = tempdir()?;
+        let work_dir = temp_dir.path().to_path_buf();
+
+        let metamodel_json = r#"[
+            {"source": {"name": "Agent", "type": "Agent"}, "target": {"name": "TestArtifact", "type": "Other"}, "type": {"name": "Verification", "verbType": "Verification"}}
+        ]"#;
+
+        let mut orchestrator = Orchestrator::new_with_metamodel(
+            client.clone(),
+            "test_app".to_string(),
+            "Test App".to_string(),
+            work_dir,
+            metamodel_json,
+            None,
+        )
+        .await?;
+
+        orchestrator.start_iteration("Verification Test").await?;
+
+        // Enure target artifact exists before verification
+        orchestrator.artifacts.insert(
+            "TestArtifact".to_string(),
+            serde_json::json!({"content": "to be verified"}),
+        );
+
+        // Mock AI Response for Verification
+        client
+            .add_response(r#"{"score": 0.9, "feedback": "Good job", "passed": true}"#.to_string());
+
+        let ui = MockUserInteraction::new();
+        let action = ActionPlan {
+            agent: "Agent".to_string(),
+            relation: "Verification".to_string(),
+            target: "TestArtifact".to_string(),
+            category: RelationCategory::Verification,
+            instance: None,
+        };
+
+        orchestrator.execute_action(action, &ui).await?;
+
+        // Verification results are stored in a special key or as a separate artifact?
+        // Actually handle_verification_result says:
+        // self.artifacts.insert(format!("{}_verification", action.target), result);
+        assert!(
+            orchestrator
+                .artifacts
+                .contains_key("TestArtifact_verification")
+        );
+        let verif = orchestrator
+            .artifacts
+            .get("TestArtifact_verification")
+            .unwrap();
+        assert_eq!(verif["score"], 0.9);
+        assert_eq!(verif["passed"], true);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_verification_is_scored_against_its_own_edge() -> Result<()> {
+        use crate::interaction::mocks::MockUserInteraction;
+        let client = MockCliClient::new();
+        let temp_dir = tempdir()?;
+
+        let metamodel_json = r#"[
+            {"source": {"name": "Reviewer", "type": "Agent"}, "target": {"name": "Code", "type": "Other"}, "type": {"name": "reviews", "verbType": "Verification"}, "loop": {"maxRetries": 3, "passThreshold": 0.8}},
+            {"source": {"name": "Tester", "type": "Agent"}, "target": {"name": "Code", "type": "Other"}, "type": {"name": "tests", "verbType": "Verification"}, "loop": {"maxRetries": 3, "passThreshold": 1.0}}
+        ]"#;
+
+        let mut orchestrator = Orchestrator::new_with_metamodel(
+            client.clone(),
+            "test_app".to_string(),
+            "Test App".to_string(),
+            temp_dir.path().to_path_buf(),
+            metamodel_json,
+            None,
+        )
+        .await?;
+        orchestrator.start_iteration("Threshold Test").await?;
+        orchestrator.artifacts.insert(
+            "Code".to_string(),
+            serde_json::json!({"content": "fn main() {}"}),
+        );
+
+        let ui = MockUserInteraction::new();
+        for (agent, relation, expected) in [
+            ("Reviewer", "reviews", NodeState::Verified),
+            ("Tester", "tests", NodeState::NeedsRefinement),
+        ] {
+            client.add_response(r#"{"score": 0.9, "feedback": "Mostly fine"}"#.to_string());
+            let action = ActionPlan {
+                agent: agent.to_string(),
+                relation: relation.to_string(),
+                target: "Code".to_string(),
+                category: RelationCategory::Verification,
+                instance: None,
+            };
+            orchestrator.execute_action(action, &ui).await?;
+            assert_eq!(
+                orchestrator.node_status("Code").state,
+                expected,
+                "{}",
+                agent
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_orchestrator_resumes_exact_checkpoint() -> Result<()> {
+        use crate::interaction::mocks::MockUserInteraction;
+        let client = MockCliClient::new();
+        let temp_dir = tempdir()?;
+        let work_dir = temp_dir.path().to_path_buf();
+
+        let metamodel_json = r#"[
+            {"source": {"name": "QA", "type": "Agent"}, "target": {"name": "TestArtifact", "type": "Other"}, "type": {"name": "verifies", "verbType": "Verification"}},
+            {"source": {"name": "Dev", "type": "Agent"}, "target": {"name": "TestArtifact", "type": "Other"}, "type": {"name": "refines", "verbType": "Refinement"}}
+        ]"#;
+
+        let mut orchestrator = Orchestrator::new_with_metamodel(
+            client.clone(),
+            "test_app".to_string(),
+            "Test App".to_string(),
+            work_dir.clone(),
+            metamodel_json,
+            None,
+        )
+        .await?;
+        orchestrator.start_iteration("Checkpoint Test").await?;
+        orchestrator.artifacts.insert(
+            "TestArtifact".to_string(),
+            serde_json::json!({"content": "draft"}),
+        );
+
+        client.add_response(r#"{"score": 0.2, "feedback": "Too vague"}"#.to_string());
+        client.add_response(r#"{"content": "refined"}"#.to_string());
+        client.add_response(r#"{"score": 0.4, "feedback": "Still vague"}"#.to_string());
+
+        let ui = MockUserInteraction::new();
+        let verify = ActionPlan {
+            agent: "QA".to_string(),
+            relation: "verifies".to_string(),
+            target: "TestArtifact".to_string(),
+            category: RelationCategory::Verification,
+            instance: None,
+        };
+        let refine = ActionPlan {
+            agent: "Dev".to_string(),
+            relation: "refines".to_string(),
+            target: "TestArtifact".to_string(),
+            category: RelationCategory::Refinement,
+            instance: None,
+        };
+        orchestrator.execute_action(verify.clone(), &ui).await?;
+        orchestrator.execute_action(refine, &ui).await?;
+        orchestrator.execute_action(verify, &ui).await?;
+        let iteration_id = orchestrator.current_iteration.as_ref().unwrap().id.clone();
+
+        // A fresh process resumes the iteration from the checkpoint.
+        let mut resumed = Orchestrator::new_with_metamodel(
+            MockCliClient::new(),
+            "test_app".to_string(),
+            "Test App".to_string(),
+            work_dir,
+            metamodel_json,
+            None,
+        )
+        .await?;
+        resumed.load_iteration(&iteration_id).await?;
+
+        assert_eq!(resumed.actions_executed, 3);
+        assert_eq!(resumed.artifacts["TestArtifact"]["content"], "refined");
+        assert_eq!(
+            resumed.verification_feedback.get("TestArtifact"),
+            Some(&"Still vague".to_string())
+        );
+        assert_eq!(resumed.refinement_attempts.get("TestArtifact"), Some(&1));
+
+        // The refinement loop continues instead of re-verifying from scratch.
+        let actions = resumed.identify_next_actions();
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].category, RelationCategory::Refinement);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_node_status_reports_blocked_dependencies() -> Result<()> {
+        let client = MockCliClient::new();
+        let temp_dir = tempdir()?;
+
+        let metamodel_json = r#"[
+            {"source": {"name": "Architect", "type": "Agent"}, "target": {"name": "Design", "type": "Other"}, "type": {"name": "creates", "verbType": "Creation"}},
+            {"source": {"name": "Dev", "type": "Agent"}, "target": {"name": "Code", "type": "Other"}, "type": {"name": "creates", "verbType": "Creation"}},
+            {"source": {"name": "Code", "type": "Other"}, "target": {"name": "Design", "type": "Other"}, "type": {"name": "dependsOn", "verbType": "Dependency"}}
+        ]"#;
+
+        let mut orchestrator = Orchestrator::new_with_metamodel(
+            client,
+            "test_app".to_string(),
+            "Test App".to_string(),
+            temp_dir.path().to_path_buf(),
+            metamodel_json,
+            None,
+        )
+        .await?;
+
+        assert_eq!(orchestrator.node_status("Design").state, NodeState::Ready);
+        let code = orchestrator.node_status("Code");
+        assert_eq!(code.state, NodeState::Blocked);
+        assert_eq!(
+            code.reason.as_deref(),
+            Some("Waiting on dependencies: Design")
+        );
+
+        orchestrator
+            .artifacts
+            .insert("Design".to_string(), serde_json::json!({}));
+        assert_eq!(orchestrator.node_status("Design").state, NodeState::Created);
+        assert_eq!(orchestrator.node_status("Code").state, NodeState::Ready);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_verification_failures_exhaust_refinement() -> Result<()> {
+        use crate::interaction::mocks::MockUserInteraction;
+        let client = MockCliClient::new();
+        let temp_dir = tempdir()?;
+
+        let metamodel_json = r#"[
+            {"source": {"name": "QA", "type": "Agent"}, "target": {"name": "TestArtifact", "type": "Other"}, "type": {"name": "verifies", "verbType": "Verification"}},
+            {"source": {"name": "Dev", "type": "Agent"}, "target": {"name": "TestArtifact", "type": "Other"}, "type": {"name": "refines", "verbType": "Refinement"}, "loop": {"maxRetries": 1}}
+        ]"#;
+
+        let mut orchestrator = Orchestrator::new_with_metamodel(
+            client.clone(),
+            "test_app".to_string(),
+            "Test App".to_string(),
+            temp_dir.path().to_path_buf(),
+            metamodel_json,
+            None,
+        )
+        .await?;
+        orchestrator.start_iteration("Lifecycle Test").await?;
+        orchestrator.artifacts.insert(
+            "TestArtifact".to_string(),
+            serde_json::json!({"content": "draft"}),
+        );
+        assert_eq!(
+            orchestrator.node_status("TestArtifact").state,
+            NodeState::AwaitingVerification
+        );
+
+        client.add_response(r#"{"score": 0.2, "feedback": "Too vague"}"#.to_string());
+        client.add_response(r#"{"content": "refined"}"#.to_string());
+        client.add_response(r#"{"score": 0.4, "feedback": "Still vague"}"#.to_string());
+
+        let ui = MockUserInteraction::new();
+        let verify = ActionPlan {
+            agent: "QA".to_string(),
+            relation: "verifies".to_string(),
+            target: "TestArtifact".to_string(),
+            category: RelationCategory::Verification,
+            instance: None,
+        };
+        let refine = ActionPlan {
+            agent: "Dev".to_string(),
+            relation: "refines".to_string(),
+            target: "TestArtifact".to_string(),
+            category: RelationCategory::Refinement,
+            instance: None,
+        };
+
+        orchestrator.execute_action(verify.clone(), &ui).await?;
+        let status = orchestrator.node_status("TestArtifact");
+        assert_eq!(status.state, NodeState::NeedsRefinement);
+        assert_eq!(status.reason.as_deref(), Some("Too vague"));
+
+        orchestrator.execute_action(refine, &ui).await?;
+        assert_eq!(
+            orchestrator.node_status("TestArtifact").state,
+            NodeState::AwaitingVerification
+        );
+
+        orchestrator.execute_action(verify, &ui).await?;
+        assert_eq!(
+            orchestrator.node_status("TestArtifact").state,
+            NodeState::Exhausted
+        );
+        assert!(orchestrator.identify_next_actions().is_empty());
+
+        let (done, pending) = orchestrator.get_execution_status();
+        assert_eq!(done, vec!["TestArtifact".to_string()]);
+        assert!(pending.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_budget_pauses_and_stops_run() -> Result<()> {
+        use crate::interaction::mocks::MockUserInteraction;
+        let client = MockCliClient::new();
+        let temp_dir = tempdir()?;
+
+        let metamodel_json = r#"[
+            {"source": {"name": "Architect", "type": "Agent"}, "target": {"name": "Design", "type": "Other"}, "type": {"name": "creates", "verbType": "Creation"}},
+            {"source": {"name": "Dev", "type": "Agent"}, "target": {"name": "Code", "type": "Other"}, "type": {"name": "creates", "verbType": "Creation"}}
+        ]"#;
+
+        let mut orchestrator = Orchestrator::new_with_metamodel(
+            client.clone(),
+            "test_app".to_string(),
+            "Test App".to_string(),
+            temp_dir.path().to_path_buf(),
+            metamodel_json,
+            None,
+        )
+        .await?
+        .with_budget(Budget {
+            max_dispatches: Some(1),
+            ..Default::default()
+        });
+        orchestrator.start_iteration("Budget Test").await?;
+        client.add_response(r#"{"content": "design"}"#.to_string());
+
+        let ui = MockUserInteraction::new();
+        ui.add_feature_response("Build it".to_string());
+        orchestrator.run(&ui).await?;
+        assert_eq!(orchestrator.usage.iteration.dispatches, 1);
+        assert_eq!(orchestrator.usage.per_agent["Architect"].dispatches, 1);
+        assert!(orchestrator.usage.per_edge["Architect creates Design"].prompt_chars > 0);
+        assert_eq!(orchestrator.node_status("Code").state, NodeState::Ready);
+
+        let iter_dir = temp_dir
+            .path()
+            .join(".infinitecodingloop/iterations")
+            .join(&orchestrator.current_iteration.as_ref().unwrap().id);
+        let state = OrchestratorState::load(&iter_dir).await?.unwrap();
+        assert_eq!(state.usage, orchestrator.usage);
+        let log = std::fs::read_to_string(iter_dir.join("logs/execution.jsonl"))?;
+        assert!(log.contains("\"dispatch_usage\""));
+        assert!(log.contains("\"budget_exceeded\""));
+        assert!(log.contains("\"usage_summary\""));
+
+        // The totals survive the pause, so a stricter run stops immediately.
+        orchestrator.budget.on_exceeded = BudgetAction::Stop;
+        let err = orchestrator.run(&ui).await.unwrap_err();
+        assert!(err.to_string().contains("dispatch budget exhausted"));
+        assert_eq!(orchestrator.usage.iteration.dispatches, 1);
+
+        // Both runs still ended the iteration
+        let log = std::fs::read_to_string(iter_dir.join("logs/execution.jsonl"))?;
+        assert!(log.contains("\"outcome\":\"paused\""));
+        assert!(log.contains("\"outcome\":\"stopped\""));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_batch_is_cut_at_dispatch_budget() -> Result<()> {
+        use crate::interaction::mocks::MockUserInteraction;
+        let client = MockCliClient::new();
+        let temp_dir = tempdir()?;
+
+        let metamodel_json = r#"[
+            {"source": {"name": "Architect", "type": "Agent"}, "target": {"name": "StyleA", "type": "Other"}, "type": {"name": "defines", "verbType": "Creation"}},
+            {"source": {"name": "Architect", "type": "Agent"}, "target": {"name": "StyleB", "type": "Other"}, "type": {"name": "defines", "verbType": "Creation"}},
+            {"source": {"name": "Architect", "type": "Agent"}, "target": {"name": "StyleC", "type": "Other"}, "type": {"name": "defines", "verbType": "Creation"}}
+        ]"#;
+
+        let mut orchestrator = Orchestrator::new_with_metamodel(
+            client.clone(),
+            "test_app".to_string(),
+            "Test App".to_string(),
+            temp_dir.path().to_path_buf(),
+            metamodel_json,
+            None,
+        )
+        .await?
+        .with_concurrency(3)
+        .with_budget(Budget {
+            max_dispatches: Some(2),
+            ..Default::default()
+        });
+        orchestrator
+            .start_iteration("Concurrent Budget Test")
+            .await?;
+        for _ in 0..3 {
+            client.add_response(r#"{"content": "style"}"#.to_string());
+        }
 
-        assert_eq!(orchestrator.app_id, "test-app");
-        assert_eq!(orchestrator.app_name, "Test App");
-        assert_eq!(orchestrator.work_dir, Some(temp_dir.path().to_path_buf()));
-        assert_eq!(orchestrator.max_iterations, 100);
+        let ui = MockUserInteraction::new();
+        ui.add_feature_response("Build it".to_string());
+        orchestrator.run(&ui).await?;
+        assert_eq!(orchestrator.usage.iteration.dispatches, 2);
+        assert_eq!(orchestrator.node_status("StyleC").state, NodeState::Ready);
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_orchestrator_builder_methods() -> Result<()> {
+    async fn test_invalidate_clears_node_and_dependents() -> Result<()> {
         let temp_dir = tempdir()?;
-        let client = MockCliClient::new();
-        let orchestrator = Orchestrator::new(
-            client,
-            "test-app".to_string(),
+        let metamodel_json = r#"[
+            {"source": {"name": "PM", "type": "Agent"}, "target": {"name": "Requirement", "type": "Other"}, "type": {"name": "creates", "verbType": "Creation"}},
+            {"source": {"name": "Architect", "type": "Agent"}, "target": {"name": "DesignSpec", "type": "Other"}, "type": {"name": "creates", "verbType": "Creation"}},
+            {"source": {"name": "Dev", "type": "Agent"}, "target": {"name": "Code", "type": "Other"}, "type": {"name": "creates", "verbType": "Creation"}},
+            {"source": {"name": "QA", "type": "Agent"}, "target": {"name": "Code", "type": "Other"}, "type": {"name": "verifies", "verbType": "Verification"}},
+            {"source": {"name": "DesignSpec", "type": "Other"}, "target": {"name": "Requirement", "type": "Other"}, "type": {"name": "requires", "verbType": "Dependency"}},
+            {"source": {"name": "Code", "type": "Other"}, "target": {"name": "DesignSpec", "type": "Other"}, "type": {"name": "requires", "verbType": "Dependency"}}
+        ]"#;
+
+        let mut orchestrator = Orchestrator::new_with_metamodel(
+            MockCliClient::new(),
+            "test_app".to_string(),
             "Test App".to_string(),
             temp_dir.path().to_path_buf(),
+            metamodel_json,
+            None,
+        )
+        .await?;
+        orchestrator.start_iteration("Invalidation Test").await?;
+        for (name, value) in [
+            ("Requirement", serde_json::json!({"id": "R1"})),
+            ("DesignSpec", serde_json::json!({"design": "wrong"})),
+            ("Code", serde_json::json!({"files": []})),
+            ("Code_verification", serde_json::json!({"score": 0.9})),
+        ] {
+            orchestrator
+                .artifacts
+                .insert(name.to_string(), value.clone());
+            orchestrator.persist_artifact(name, &value).await?;
+        }
+        orchestrator
+            .lifecycle
+            .observe("Code", NodeState::Verified, None);
+        orchestrator
+            .refinement_attempts
+            .insert("Code".to_string(), 2);
+        orchestrator
+            .verification_feedback
+            .insert("Code".to_string(), "Old feedback".to_string());
+        assert!(orchestrator.identify_next_actions().is_empty());
+
+        assert!(orchestrator.invalidate("Architect").await.is_err());
+        assert!(orchestrator.invalidate("Nope").await.is_err());
+
+        let invalidated = orchestrator.invalidate("DesignSpec").await?;
+        assert_eq!(invalidated, vec!["DesignSpec", "Code"]);
+
+        let docs = temp_dir
+            .path()
+            .join(orchestrator.artifact_folder("Requirement"));
+        assert!(docs.join("requirement.json").exists());
+        assert!(!docs.join("designspec.json").exists());
+        assert!(!docs.join("code.json").exists());
+        assert!(!docs.join("code_verification.json").exists());
+        assert!(orchestrator.artifacts.contains_key("Requirement"));
+        assert!(!orchestrator.artifacts.contains_key("Code_verification"));
+        assert!(orchestrator.refinement_attempts.is_empty());
+        assert!(orchestrator.verification_feedback.is_empty());
+
+        // Only the invalidated part of the graph is planned again.
+        let actions = orchestrator.identify_next_actions();
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].target, "DesignSpec");
+        assert_eq!(orchestrator.node_status("Code").state, NodeState::Blocked);
+
+        let state = OrchestratorState::load(
+            &temp_dir
+                .path()
+                .join(".infinitecodingloop/iterations")
+                .join(&orchestrator.current_iteration.as_ref().unwrap().id),
         )
         .await?
-        .with_max_iterations(50)
-        .with_docs_folder("custom_docs".to_string());
-
-        assert_eq!(orchestrator.max_iterations, 50);
-        assert_eq!(orchestrator.docs_folder, "custom_docs");
+        .unwrap();
+        assert!(!state.artifacts.contains_key("DesignSpec"));
+        assert!(!state.node_states.contains_key("Code"));
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_start_iteration() -> Result<()> {
-        let temp_dir = tempdir()?;
+    async fn test_changed_inputs_make_downstream_stale() -> Result<()> {
+        use crate::interaction::mocks::MockUserInteraction;
         let client = MockCliClient::new();
-        let mut orchestrator = Orchestrator::new(
-            client,
-            "test-app".to_string(),
+        let temp_dir = tempdir()?;
+        let metamodel_json = r#"[
+            {"source": {"name": "PM", "type": "Agent"}, "target": {"name": "Requirement", "type": "Other"}, "type": {"name": "creates", "verbType": "Creation"}},
+            {"source": {"name": "Architect", "type": "Agent"}, "target": {"name": "DesignSpec", "type": "Other"}, "type": {"name": "creates", "verbType": "Creation"}},
+            {"source": {"name": "Architect", "type": "Agent"}, "target": {"name": "DesignSpec", "type": "Other"}, "type": {"name": "refines", "verbType": "Refinement"}},
+            {"source": {"name": "Dev", "type": "Agent"}, "target": {"name": "Code", "type": "Other"}, "type": {"name": "creates", "verbType": "Creation"}},
+            {"source": {"name": "DesignSpec", "type": "Other"}, "target": {"name": "Requirement", "type": "Other"}, "type": {"name": "requires", "verbType": "Dependency"}},
+            {"source": {"name": "Code", "type": "Other"}, "target": {"name": "DesignSpec", "type": "Other"}, "type": {"name": "requires", "verbType": "Dependency"}}
+        ]"#;
+
+        let mut orchestrator = Orchestrator::new_with_metamodel(
+            client.clone(),
+            "test_app".to_string(),
             "Test App".to_string(),
             temp_dir.path().to_path_buf(),
+            metamodel_json,
+            None,
         )
         .await?;
+        orchestrator.start_iteration("Staleness Test").await?;
+        let ui = MockUserInteraction::new();
+        let action = |agent: &str, relation: &str, target: &str, category| ActionPlan {
+            agent: agent.to_string(),
+            relation: relation.to_string(),
+            target: target.to_string(),
+            category,
+            instance: None,
+        };
 
-        orchestrator.start_iteration("Feature Test").await?;
-
-        assert!(orchestrator.current_iteration.is_some());
-        let iter = orchestrator.current_iteration.as_ref().unwrap();
-        assert_eq!(iter.name, "Feature Test");
+        for (agent, target, response) in [
+            ("PM", "Requirement", r#"{"text": "login"}"#),
+            ("Architect", "DesignSpec", r#"{"design": "v1"}"#),
+            ("Dev", "Code", r#"{"files": []}"#),
+        ] {
+            client.add_response(response.to_string());
+            orchestrator
+                .execute_action(
+                    action(agent, "creates", target, RelationCategory::Creation),
+                    &ui,
+                )
+                .await?;
+        }
+        assert_eq!(
+            orchestrator.provenance.inputs("Code").map(|i| i.len()),
+            Some(1)
+        );
+        assert!(orchestrator.identify_next_actions().is_empty());
+
+        // A human edits the requirement in the docs folder.
+        std::fs::write(
+            temp_dir
+                .path()
+                .join(orchestrator.artifact_folder("Requirement"))
+                .join("requirement.json"),
+            r#"{"text": "login with SSO"}"#,
+        )?;
+        assert_eq!(orchestrator.adopt_docs_edits().await?, vec!["Requirement"]);
+        let status = orchestrator.node_status("DesignSpec");
+        assert_eq!(status.state, NodeState::Stale);
+        assert_eq!(
+            status.reason.as_deref(),
+            Some("Inputs changed: Requirement")
+        );
+        assert_eq!(orchestrator.node_status("Code").state, NodeState::Created);
 
-        let icl_dir = temp_dir.path().join(".infinitecodingloop");
-        let iter_dir = icl_dir.join("iterations").join(&iter.id);
-        assert!(iter_dir.exists());
-        assert!(iter_dir.join("iteration.json").exists());
-        assert!(temp_dir.path().join("spec").exists()); // Default docs folder
+        let refine = action(
+            "Architect",
+            "refines",
+            "DesignSpec",
+            RelationCategory::Refinement,
+        );
+        assert_eq!(orchestrator.identify_next_actions(), vec![refine.clone()]);
+        assert!(
+            orchestrator
+                .render_prompt(&refine, &orchestrator.artifacts)
+                .contains("These inputs changed since DesignSpec was produced: Requirement")
+        );
 
+        // Refining the design regenerates it, which makes the code stale in turn.
+        client.add_response(r#"{"design": "v2"}"#.to_string());
+        orchestrator.execute_action(refine, &ui).await?;
+        assert!(!orchestrator.refinement_attempts.contains_key("DesignSpec"));
+        assert_eq!(
+            orchestrator.node_status("DesignSpec").state,
+            NodeState::Created
+        );
+        assert_eq!(orchestrator.node_status("Code").state, NodeState::Stale);
+
+        // Code has no Refinement edge, so it is created again.
+        let recreate = action("Dev", "creates", "Code", RelationCategory::Creation);
+        assert_eq!(orchestrator.identify_next_actions(), vec![recreate.clone()]);
+        client.add_response(r#"{"files": ["main.rs"]}"#.to_string());
+        orchestrator.execute_action(recreate, &ui).await?;
+        assert!(orchestrator.identify_next_actions().is_empty());
         Ok(())
     }
+
     #[tokio::test]
-    async fn test_orchestrator_identify_next_actions() -> Result<()> {
+    async fn test_for_each_fans_out_per_item() -> Result<()> {
+        use crate::interaction::mocks::MockUserInteraction;
         let client = MockCliClient::new();
         let temp_dir = tempdir()?;
-        let work_dir = temp_dir.path().to_path_buf();
-
         let metamodel_json = r#"[
-            {"source": {"name": "SoftwareApplication", "type": "Other"}, "target": {"name": "TestArtifact", "type": "Other"}, "type": {"name": "Creation", "verbType": "Creation"}},
-            {"source": {"name": "Agent", "type": "Agent"}, "target": {"name": "TestArtifact", "type": "Other"}, "type": {"name": "Creation", "verbType": "Creation"}}
+            {"source": {"name": "PM", "type": "Agent"}, "target": {"name": "Feature", "type": "Other"}, "type": {"name": "creates", "verbType": "Creation"}},
+            {"source": {"name": "Writer", "type": "Agent"}, "target": {"name": "UserStory", "type": "Other"}, "type": {"name": "creates", "verbType": "Creation"}, "forEach": "Feature"},
+            {"source": {"name": "Dev", "type": "Agent"}, "target": {"name": "Code", "type": "Other"}, "type": {"name": "creates", "verbType": "Creation"}},
+            {"source": {"name": "Code", "type": "Other"}, "target": {"name": "UserStory", "type": "Other"}, "type": {"name": "requires", "verbType": "Dependency"}}
         ]"#;
 
-        let orchestrator = Orchestrator::new_with_metamodel(
-            client,
+        let mut orchestrator = Orchestrator::new_with_metamodel(
+            client.clone(),
             "test_app".to_string(),
             "Test App".to_string(),
-            work_dir,
+            temp_dir.path().to_path_buf(),
             metamodel_json,
             None,
         )
         .await?;
+        orchestrator.start_iteration("Fan-out Test").await?;
+        let ui = MockUserInteraction::new();
+
+        client.add_response(
+            r#"[{"id": "F1", "name": "Login"}, {"id": "F2", "name": "Search"}]"#.to_string(),
+        );
+        let create_features = orchestrator.identify_next_actions().remove(0);
+        orchestrator.execute_action(create_features, &ui).await?;
 
         let actions = orchestrator.identify_next_actions();
-        assert_eq!(actions.len(), 1);
-        assert_eq!(actions[0].agent, "Agent");
-        assert_eq!(actions[0].target, "TestArtifact");
+        let keys: Vec<String> = actions.iter().map(|a| a.key()).collect();
+        assert_eq!(keys, vec!["UserStory[F1]", "UserStory[F2]"]);
+        assert!(
+            orchestrator
+                .render_prompt(&actions[1], &orchestrator.artifacts)
+                .contains("Search")
+        );
+        assert_eq!(orchestrator.node_status("Code").state, NodeState::Blocked);
+
+        client.add_response(r#"{"story": "As a user I can log in"}"#.to_string());
+        orchestrator.execute_action(actions[0].clone(), &ui).await?;
+        assert!(
+            temp_dir
+                .path()
+                .join(orchestrator.artifact_folder("UserStory[F1]"))
+                .join("userstory-f1.json")
+                .exists()
+        );
+        assert_eq!(
+            orchestrator.node_status("UserStory[F1]").state,
+            NodeState::Created
+        );
+        assert_eq!(
+            orchestrator.node_status("UserStory").state,
+            NodeState::Ready
+        );
+        assert!(!orchestrator.artifacts.contains_key("UserStory"));
+
+        client.add_response(r#"{"story": "As a user I can search"}"#.to_string());
+        orchestrator.execute_action(actions[1].clone(), &ui).await?;
+        assert_eq!(
+            orchestrator.artifacts["UserStory"],
+            serde_json::json!([
+                {"story": "As a user I can log in"},
+                {"story": "As a user I can search"}
+            ])
+        );
+        assert_eq!(
+            orchestrator.node_status("UserStory").state,
+            NodeState::Created
+        );
+
+        let next = orchestrator.identify_next_actions();
+        assert_eq!(next.len(), 1);
+        assert_eq!(next[0].target, "Code");
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_orchestrator_get_execution_status() -> Result<()> {
+    async fn test_for_each_over_empty_collection_is_produced() -> Result<()> {
+        use crate::interaction::mocks::MockUserInteraction;
         let client = MockCliClient::new();
         let temp_dir = tempdir()?;
-        let work_dir = temp_dir.path().to_path_buf();
-
         let metamodel_json = r#"[
-            {"source": {"name": "Agent", "type": "Agent"}, "target": {"name": "TestArtifact", "type": "Other"}, "type": {"name": "Creation", "verbType": "Creation"}}
+            {"source": {"name": "PM", "type": "Agent"}, "target": {"name": "Feature", "type": "Other"}, "type": {"name": "creates", "verbType": "Creation"}},
+            {"source": {"name": "Writer", "type": "Agent"}, "target": {"name": "UserStory", "type": "Other"}, "type": {"name": "creates", "verbType": "Creation"}, "forEach": "Feature"},
+            {"source": {"name": "Dev", "type": "Agent"}, "target": {"name": "Code", "type": "Other"}, "type": {"name": "creates", "verbType": "Creation"}},
+            {"source": {"name": "Code", "type": "Other"}, "target": {"name": "UserStory", "type": "Other"}, "type": {"name": "requires", "verbType": "Dependency"}}
         ]"#;
 
         let mut orchestrator = Orchestrator::new_with_metamodel(
-            client,
+            client.clone(),
             "test_app".to_string(),
             "Test App".to_string(),
-            work_dir,
+            temp_dir.path().to_path_buf(),
             metamodel_json,
             None,
         )
         .await?;
+        orchestrator.start_iteration("Empty Fan-out Test").await?;
+        let ui = MockUserInteraction::new();
+        assert_eq!(
+            orchestrator.node_status("UserStory").state,
+            NodeState::Blocked
+        );
 
-        let (done, pending) = orchestrator.get_execution_status();
-        assert_eq!(done.len(), 0);
-        assert_eq!(pending.len(), 1);
-        assert_eq!(pending[0], "TestArtifact");
+        client.add_response("[]".to_string());
+        let create_features = orchestrator.identify_next_actions().remove(0);
+        orchestrator.execute_action(create_features, &ui).await?;
 
-        orchestrator.artifacts.insert(
-            "TestArtifact".to_string(),
-            serde_json::json!({"status": "done"}),
+        assert_eq!(orchestrator.artifacts["UserStory"], serde_json::json!([]));
+        assert!(
+            temp_dir
+                .path()
+                .join(orchestrator.artifact_folder("UserStory"))
+                .join("userstory.json")
+                .exists()
+        );
+        assert_eq!(
+            orchestrator.node_status("UserStory").state,
+            NodeState::Created
         );
 
-        let (done, pending) = orchestrator.get_execution_status();
-        assert_eq!(done.len(), 1);
-        assert_eq!(done[0], "TestArtifact");
-        assert_eq!(pending.len(), 0);
+        let next = orchestrator.identify_next_actions();
+        assert_eq!(next.len(), 1);
+        assert_eq!(next[0].target, "Code");
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_orchestrator_handle_initial_input() -> Result<()> {
+    async fn test_application_artifacts_are_reused_across_iterations() -> Result<()> {
         use crate::interaction::mocks::MockUserInteraction;
         let client = MockCliClient::new();
         let temp_dir = tempdir()?;
-        let work_dir = temp_dir.path().to_path_buf();
+        let metamodel_json = r#"[
+            {"source": {"name": "Engineer", "type": "Agent"}, "target": {"name": "CodingStyle", "type": "Other", "scope": "application"}, "type": {"name": "defines", "verbType": "Creation"}},
+            {"source": {"name": "Engineer", "type": "Agent"}, "target": {"name": "Code", "type": "Other"}, "type": {"name": "implements", "verbType": "Creation"}},
+            {"source": {"name": "Code", "type": "Other"}, "target": {"name": "CodingStyle", "type": "Other"}, "type": {"name": "requires", "verbType": "Dependency"}}
+        ]"#;
+        let new_orchestrator = || {
+            Orchestrator::new_with_metamodel(
+                client.clone(),
+                "test_app".to_string(),
+                "Test App".to_string(),
+                temp_dir.path().to_path_buf(),
+                metamodel_json,
+                None,
+            )
+        };
+        let ui = MockUserInteraction::new();
+
+        let mut first = new_orchestrator().await?;
+        first.start_iteration("First Feature").await?;
+        for _ in 0..2 {
+            client.add_response(r#"{"rules": ["small functions"]}"#.to_string());
+            let action = first.identify_next_actions().remove(0);
+            first.execute_action(action, &ui).await?;
+        }
+        let first_id = first.current_iteration.as_ref().unwrap().id.clone();
+        assert_eq!(first.artifact_folder("CodingStyle"), "spec/application");
+        assert_eq!(
+            first.artifact_folder("Code"),
+            format!("spec/features/{}", first_id)
+        );
+        assert!(
+            temp_dir
+                .path()
+                .join("spec/application/codingstyle.json")
+                .exists()
+        );
+        assert!(
+            temp_dir
+                .path()
+                .join(format!("spec/features/{}/code.json", first_id))
+                .exists()
+        );
+
+        // The next feature starts from the shared coding style.
+        let mut second = new_orchestrator().await?;
+        second.start_iteration("Second Feature").await?;
+        assert!(second.artifacts.contains_key("CodingStyle"));
+        assert!(!second.artifacts.contains_key("Code"));
+        let status = second.node_status("CodingStyle");
+        assert_eq!(status.state, NodeState::Created);
+        assert_eq!(
+            status.reason.as_deref(),
+            Some("Reused application artifact")
+        );
+        let actions = second.identify_next_actions();
+        assert_eq!(actions.len(), 1);
+        assert_eq!(actions[0].target, "Code");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_inheriting_agent_dispatches_with_chained_prompt() -> Result<()> {
+        use crate::interaction::mocks::MockUserInteraction;
+        let client = MockCliClient::new();
+        let temp_dir = tempdir()?;
+        let ontology_dir = temp_dir.path().join("ontology");
+        std::fs::create_dir_all(ontology_dir.join("agent/system_prompt"))?;
+        std::fs::write(
+            ontology_dir.join("agent/system_prompt/Architect.md"),
+            "You design systems.",
+        )?;
+        std::fs::write(
+            ontology_dir.join("agent/system_prompt/SeniorEngineer.md"),
+            "You also write code.",
+        )?;
+        let metamodel_json = r#"[
+            {"source": {"name": "Architect", "type": "Agent", "aiCli": "gemini"}, "target": {"name": "DesignSpec", "type": "Other"}, "type": {"name": "creates", "verbType": "Creation"}},
+            {"source": {"name": "SeniorEngineer", "type": "Agent"}, "target": {"name": "Architect", "type": "Agent"}, "type": {"name": "inherits", "verbType": "Inheritance"}},
+            {"source": {"name": "SeniorEngineer", "type": "Agent"}, "target": {"name": "Code", "type": "Other"}, "type": {"name": "implements", "verbType": "Creation"}},
+            {"source": {"name": "Code", "type": "Other"}, "target": {"name": "DesignSpec", "type": "Other"}, "type": {"name": "requires", "verbType": "Dependency"}}
+        ]"#;
 
         let mut orchestrator = Orchestrator::new_with_metamodel(
-            client,
+            client.clone(),
             "test_app".to_string(),
             "Test App".to_string(),
-            work_dir,
-            "[]",
-            None,
+            temp_dir.path().to_path_buf(),
+            metamodel_json,
+            Some(&ontology_dir),
         )
         .await?;
-
+        orchestrator.start_iteration("Inheritance Test").await?;
         let ui = MockUserInteraction::new();
-        ui.add_feature_response("Build a login page".to_string());
 
-        let result = orchestrator.handle_initial_input(&ui).await?;
-        assert!(result);
-        assert!(orchestrator.artifacts.contains_key("SoftwareApplication"));
+        // Inheriting does not make SeniorEngineer a second producer of DesignSpec
+        let design: Vec<ActionPlan> = orchestrator
+            .identify_next_actions()
+            .into_iter()
+            .filter(|a| a.target == "DesignSpec")
+            .collect();
+        assert_eq!(design.len(), 1);
+        assert_eq!(design[0].agent, "Architect");
+        client.add_response(r#"{"design": "layers"}"#.to_string());
+        orchestrator.execute_action(design[0].clone(), &ui).await?;
 
-        let app_artifact = orchestrator.artifacts.get("SoftwareApplication").unwrap();
-        assert_eq!(app_artifact["goal"], "Build a login page");
+        let action = ActionPlan {
+            agent: "SeniorEngineer".to_string(),
+            relation: "implements".to_string(),
+            target: "Code".to_string(),
+            category: RelationCategory::Creation,
+            instance: None,
+        };
+        assert_eq!(orchestrator.identify_next_actions(), vec![action.clone()]);
+        let (options, reason) = orchestrator.resolve_action_options(&action);
+        assert_eq!(options.ai_cli.as_deref(), Some("gemini"));
+        assert_eq!(reason, "agent default for 'SeniorEngineer'");
+
+        let prompt = std::sync::Arc::new(std::sync::Mutex::new(String::new()));
+        let seen = prompt.clone();
+        client.add_action(move |p| {
+            *seen.lock().unwrap() = p.to_string();
+            Ok(r#"{"files": ["main.rs"]}"#.to_string())
+        });
+        orchestrator.execute_action(action, &ui).await?;
+        assert!(
+            prompt
+                .lock()
+                .unwrap()
+                .starts_with("You design systems.\n\nYou also write code.")
+        );
+        assert!(orchestrator.artifacts.contains_key("Code"));
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_orchestrator_execute_action() -> Result<()> {
+    async fn test_command_nodes_run_without_the_ai() -> Result<()> {
         use crate::interaction::mocks::MockUserInteraction;
         let client = MockCliClient::new();
         let temp_dir = tempdir()?;
-        let work_dir = temp_dir.path().to_path_buf();
-
         let metamodel_json = r#"[
-            {"source": {"name": "Agent", "type": "Agent"}, "target": {"name": "TestArtifact", "type": "Other"}, "type": {"name": "Creation", "verbType": "Creation"}}
+            {"source": {"name": "Dev", "type": "Agent"}, "target": {"name": "Code", "type": "Other"}, "type": {"name": "implements", "verbType": "Creation"}},
+            {"source": {"name": "RunTests", "type": "Command", "command": "echo 2 tests passed"}, "target": {"name": "Code", "type": "Other"}, "type": {"name": "requires", "verbType": "Dependency"}},
+            {"source": {"name": "Reviewer", "type": "Agent"}, "target": {"name": "Review", "type": "Other"}, "type": {"name": "creates", "verbType": "Creation"}},
+            {"source": {"name": "Review", "type": "Other"}, "target": {"name": "RunTests", "type": "Command"}, "type": {"name": "requires", "verbType": "Dependency"}}
         ]"#;
 
         let mut orchestrator = Orchestrator::new_with_metamodel(
             client.clone(),
             "test_app".to_string(),
             "Test App".to_string(),
-            work_dir,
+            temp_dir.path().to_path_buf(),
             metamodel_json,
             None,
         )
         .await?;
-
-        orchestrator.start_iteration("Execution Test").await?;
-
-        // Mock AI Response
-        client.add_response(r#"{"status": "success", "content": "Hello World"}"#.to_string());
-
+        orchestrator.start_iteration("Command Test").await?;
         let ui = MockUserInteraction::new();
-        let action = ActionPlan {
-            agent: "Agent".to_string(),
-            relation: "Creation".to_string(),
-            target: "TestArtifact".to_string(),
-            category: RelationCategory::Creation,
-        };
+        assert_eq!(
+            orchestrator.node_status("RunTests").state,
+            NodeState::Blocked
+        );
 
-        orchestrator.execute_action(action, &ui).await?;
+        client.add_response(r#"{"files": ["lib.rs"]}"#.to_string());
+        let implement = orchestrator.identify_next_actions().remove(0);
+        orchestrator.execute_action(implement, &ui).await?;
+
+        let run = orchestrator.identify_next_actions().remove(0);
+        assert_eq!(
+            (
+                run.agent.as_str(),
+                run.relation.as_str(),
+                run.target.as_str()
+            ),
+            (COMMAND_RUNNER, "runs", "RunTests")
+        );
+        assert_eq!(
+            orchestrator.render_prompt(&run, &orchestrator.artifacts),
+            "echo 2 tests passed"
+        );
+        orchestrator.execute_action(run, &ui).await?;
+        let result = &orchestrator.artifacts["RunTests"];
+        assert_eq!(result["exitCode"], 0);
+        assert_eq!(result["stdout"], "2 tests passed\n");
 
-        assert!(orchestrator.artifacts.contains_key("TestArtifact"));
-        let artifact = orchestrator.artifacts.get("TestArtifact").unwrap();
-        assert_eq!(artifact["content"], "Hello World");
+        let review = orchestrator.identify_next_actions().remove(0);
+        assert_eq!(review.target, "Review");
+        assert!(
+            orchestrator
+                .render_prompt(&review, &orchestrator.artifacts)
+                .contains("2 tests passed")
+        );
         Ok(())
     }
 
     #[tokio::test]
-    async fn test_orchestrator_handle_verification() -> Result<()> {
+    async fn test_verifier_command_scores_the_refinement_loop() -> Result<()> {
         use crate::interaction::mocks::MockUserInteraction;
         let client = MockCliClient::new();
         let temp_dir = tempdir()?;
         let work_dir = temp_dir.path().to_path_buf();
-
         let metamodel_json = r#"[
-            {"source": {"name": "Agent", "type": "Agent"}, "target": {"name": "TestArtifact", "type": "Other"}, "type": {"name": "Verification", "verbType": "Verification"}}
+            {"source": {"name": "QA", "type": "Agent"}, "target": {"name": "Code", "type": "Other"}, "type": {"name": "verifies", "verbType": "Verification"}, "verifier": {"command": "grep -q fixed status.txt || { echo 'test parser ... FAILED'; exit 101; }"}},
+            {"source": {"name": "Dev", "type": "Agent"}, "target": {"name": "Code", "type": "Other"}, "type": {"name": "refines", "verbType": "Refinement"}}
         ]"#;
 
         let mut orchestrator = Orchestrator::new_with_metamodel(
             client.clone(),
             "test_app".to_string(),
             "Test App".to_string(),
-            work_dir,
+            work_dir.clone(),
             metamodel_json,
             None,
         )
         .await?;
+        orchestrator.start_iteration("Verifier Test").await?;
+        orchestrator
+            .artifacts
+            .insert("Code".to_string(), serde_json::json!({"files": []}));
+        std::fs::write(work_dir.join("status.txt"), "broken")?;
+        let ui = MockUserInteraction::new();
 
-        orchestrator.start_iteration("Verification Test").await?;
-
-        // Enure target artifact exists before verification
-        orchestrator.artifacts.insert(
-            "TestArtifact".to_string(),
-            serde_json::json!({"content": "to be verified"}),
+        // The verification runs the command; no agent is asked for a score.
+        let verify = orchestrator.identify_next_actions().remove(0);
+        assert_eq!(verify.category, RelationCategory::Verification);
+        orchestrator.execute_action(verify.clone(), &ui).await?;
+        assert_eq!(
+            orchestrator.node_status("Code").state,
+            NodeState::NeedsRefinement
         );
+        assert_eq!(orchestrator.artifacts["Code_verification"]["exitCode"], 101);
+
+        let status = work_dir.join("status.txt");
+        client.add_action(move |prompt| {
+            assert!(prompt.contains("test parser ... FAILED"));
+            std::fs::write(&status, "fixed")?;
+            Ok(r#"{"files": ["parser.rs"]}"#.to_string())
+        });
+        let refine = orchestrator.identify_next_actions().remove(0);
+        assert_eq!(refine.category, RelationCategory::Refinement);
+        orchestrator.execute_action(refine, &ui).await?;
+
+        orchestrator.execute_action(verify, &ui).await?;
+        assert_eq!(orchestrator.node_status("Code").state, NodeState::Verified);
+        #[allow(deprecated)]
+        let verified = orchestrator.verified_artifacts();
+        assert!(verified.contains("Code"));
+        assert_eq!(orchestrator.artifacts["Code_verification"]["score"], 1.0);
+        Ok(())
+    }
 
-        // Mock AI Response for Verification
-        client
-            .add_response(r#"{"score": 0.9, "feedback": "Good job", "passed": true}"#.to_string());
+    #[tokio::test]
+    async fn test_agent_questions_are_answered_and_redispatched() -> Result<()> {
+        use crate::interaction::mocks::MockUserInteraction;
+        let client = MockCliClient::new();
+        let temp_dir = tempdir()?;
+        let metamodel_json = r#"[
+            {"source": {"name": "Architect", "type": "Agent"}, "target": {"name": "Design", "type": "Other"}, "type": {"name": "creates", "verbType": "Creation"}}
+        ]"#;
 
+        let mut orchestrator = Orchestrator::new_with_metamodel(
+            client.clone(),
+            "test_app".to_string(),
+            "Test App".to_string(),
+            temp_dir.path().to_path_buf(),
+            metamodel_json,
+            None,
+        )
+        .await?;
+        orchestrator.start_iteration("Clarification Test").await?;
         let ui = MockUserInteraction::new();
-        let action = ActionPlan {
-            agent: "Agent".to_string(),
-            relation: "Verification".to_string(),
-            target: "TestArtifact".to_string(),
-            category: RelationCategory::Verification,
-        };
-
-        orchestrator.execute_action(action, &ui).await?;
-
-        // Verification results are stored in a special key or as a separate artifact?
-        // Actually handle_verification_result says:
-        // self.artifacts.insert(format!("{}_verification", action.target), result);
+        ui.add_selection(1);
+        ui.add_user_input("snake_case".to_string());
+
+        client.add_action(|prompt| {
+            assert!(prompt.contains("**Open Questions**"));
+            Ok(r#"{"clarification": {"questions": [
+                {"question": "Which database?", "options": ["Postgres", "SQLite"]},
+                {"question": "Naming rules?"}
+            ]}}"#
+                .to_string())
+        });
+        client.add_action(|prompt| {
+            assert!(prompt.contains("### ANSWERS FROM THE USER"));
+            assert!(prompt.contains("SQLite"));
+            assert!(prompt.contains("snake_case"));
+            Ok(r#"{"database": "SQLite"}"#.to_string())
+        });
+        let create = orchestrator.identify_next_actions().remove(0);
+        orchestrator.execute_action(create.clone(), &ui).await?;
+
+        assert_eq!(orchestrator.artifacts["Design"]["database"], "SQLite");
+        let answers = &orchestrator.artifacts["Design_clarifications"];
+        assert_eq!(answers[0]["answer"], "SQLite");
+        assert_eq!(answers[1]["question"], "Naming rules?");
         assert!(
-            orchestrator
-                .artifacts
-                .contains_key("TestArtifact_verification")
+            temp_dir
+                .path()
+                .join(orchestrator.artifact_folder("Design_clarifications"))
+                .join("design_clarifications.json")
+                .exists()
         );
-        let verif = orchestrator
-            .artifacts
-            .get("TestArtifact_verification")
+        // Both the questions and the artifact are logged as responses
+        let log = std::fs::read_to_string(
+            temp_dir
+                .path()
+                .join(".infinitecodingloop/iterations")
+                .join(&orchestrator.current_iteration.as_ref().unwrap().id)
+                .join("logs/execution.jsonl"),
+        )?;
+        let responses: Vec<serde_json::Value> = log
+            .lines()
+            .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+            .filter(|e| e["event_type"] == "response_received")
+            .collect();
+        assert_eq!(responses.len(), 2);
+        assert!(responses[0]["details"]["response"]["clarification"].is_object());
+
+        // Agents that keep asking fail once the rounds are used up
+        let mut orchestrator = orchestrator.with_max_clarification_rounds(1);
+        for _ in 0..2 {
+            client.add_response(
+                r#"{"clarification": {"questions": [{"question": "Really?"}]}}"#.to_string(),
+            );
+        }
+        let err = orchestrator
+            .execute_action(create.clone(), &ui)
+            .await
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("still has open questions after 1 round(s)"));
+
+        // No further round is dispatched once the budget is used up
+        let dispatches = orchestrator.usage.iteration.dispatches;
+        let mut orchestrator = orchestrator
+            .with_max_clarification_rounds(3)
+            .with_budget(Budget {
+                max_dispatches: Some(dispatches + 1),
+                ..Default::default()
+            });
+        client.add_response(
+            r#"{"clarification": {"questions": [{"question": "Which cache?"}]}}"#.to_string(),
+        );
+        ui.add_user_input("Redis".to_string());
+        assert!(!orchestrator.execute_action(create, &ui).await?);
+        assert_eq!(orchestrator.usage.iteration.dispatches, dispatches + 1);
+        assert_ne!(orchestrator.node_status("Design").state, NodeState::Running);
+        assert_eq!(orchestrator.artifacts["Design"]["database"], "SQLite");
+        let answers = orchestrator.artifacts["Design_clarifications"]
+            .as_array()
             .unwrap();
-        assert_eq!(verif["score"], 0.9);
-        assert_eq!(verif["passed"], true);
+        assert_eq!(answers.last().unwrap()["answer"], "Redis");
         Ok(())
     }
 }
@@ -0,0 +1,656 @@
+use crate::collection::{self, collection_items, instance_key, split_instance};
+use crate::graph::{DependencyGraph, RelationCategory};
+use crate::lifecycle::{NodeLifecycle, NodeState, NodeStatus};
+use crate::provenance::Provenance;
+use petgraph::visit::EdgeRef;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use tracing::{debug, warn};
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ActionPlan {
+    pub agent: String,
+    pub target: String,
+    pub relation: String,
+    pub category: RelationCategory,
+    /// Item ID when the target is produced once per item of a collection.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+}
+
+impl ActionPlan {
+    /// Key the action's state is tracked under: `UserStory[F1]` for one
+    /// instance of a fanned-out node, otherwise the target itself.
+    pub fn key(&self) -> String {
+        match &self.instance {
+            Some(id) => instance_key(&self.target, id),
+            None => self.target.clone(),
+        }
+    }
+}
+
+/// Pure view over the graph and the execution state that decides which
+/// actions run next. It never dispatches anything, so the orchestrator,
+/// dry-runs and `pulpo-tools plan` all share the same decisions.
+pub struct Planner<'a> {
+    graph: &'a DependencyGraph,
+    artifacts: &'a HashMap<String, Value>,
+    lifecycle: &'a NodeLifecycle,
+    refinement_attempts: &'a HashMap<String, usize>,
+    provenance: Option<&'a Provenance>,
+}
+
+impl<'a> Planner<'a> {
+    pub fn new(
+        graph: &'a DependencyGraph,
+        artifacts: &'a HashMap<String, Value>,
+        lifecycle: &'a NodeLifecycle,
+        refinement_attempts: &'a HashMap<String, usize>,
+    ) -> Self {
+        Self {
+            graph,
+            artifacts,
+            lifecycle,
+            refinement_attempts,
+            provenance: None,
+        }
+    }
+
+    /// Takes the recorded upstream hashes into account, so artifacts produced
+    /// from inputs that changed since are reported `Stale`.
+    pub fn with_provenance(mut self, provenance: &'a Provenance) -> Self {
+        self.provenance = Some(provenance);
+        self
+    }
+
+    fn category_of(&self, source: &str, relation: &str, target: &str) -> RelationCategory {
+        let edge_key = (source.to_string(), relation.to_string(), target.to_string());
+        self.graph
+            .edge_categories
+            .get(&edge_key)
+            .copied()
+            .unwrap_or(RelationCategory::Context)
+    }
+
+    /// Lifecycle status of every non-agent node, in graph order. Instances of
+    /// fanned-out nodes follow the node itself.
+    pub fn node_statuses(&self) -> Vec<NodeStatus> {
+        let mut statuses = Vec::new();
+        for idx in self.graph.graph.node_indices() {
+            let kind = &self.graph.graph[idx];
+            if self.graph.is_agent(kind) {
+                continue;
+            }
+            statuses.push(self.node_status(kind));
+            for key in self.instances(kind).unwrap_or_default() {
+                statuses.push(self.node_status(&key));
+            }
+        }
+        statuses
+    }
+
+    /// Instance keys of a node produced once per item of a collection (its
+    /// Creation edge declares `forEach`), one per item produced so far in that
+    /// collection. `None` for ordinary nodes.
+    pub fn instances(&self, node: &str) -> Option<Vec<String>> {
+        let source = self.graph.for_each.get(node)?;
+        let items = self
+            .artifacts
+            .get(source)
+            .map(collection_items)
+            .unwrap_or_default();
+        Some(
+            items
+                .into_iter()
+                .map(|(id, _)| instance_key(node, &id))
+                .collect(),
+        )
+    }
+
+    /// The collection item an instance action is produced for.
+    pub fn instance_item(&self, action: &ActionPlan) -> Option<Value> {
+        let id = action.instance.as_deref()?;
+        let source = self.graph.for_each.get(&action.target)?;
+        collection_items(self.artifacts.get(source)?)
+            .into_iter()
+            .find(|(item_id, _)| item_id == id)
+            .map(|(_, item)| item)
+    }
+
+    /// The aggregated collection of a fanned-out node, once every instance
+    /// has been produced. An empty collection aggregates to `[]`.
+    pub fn aggregate(&self, node: &str) -> Option<Value> {
+        let source = self.graph.for_each.get(node)?;
+        self.artifacts.get(source)?;
+        let keys = self.instances(node)?;
+        let values: Option<Vec<&Value>> = keys.iter().map(|k| self.artifacts.get(k)).collect();
+        values.map(collection::aggregate)
+    }
+
+    /// Status of a fanned-out node as a whole, derived from its instances.
+    fn collection_status(&self, node: &str, instances: &[String]) -> NodeStatus {
+        let missing = self.missing_dependencies(node);
+        let produced = instances
+            .iter()
+            .filter(|k| self.artifacts.contains_key(*k))
+            .count();
+        let (state, reason) = if !missing.is_empty() {
+            (
+                NodeState::Blocked,
+                format!("Waiting on dependencies: {}", missing.join(", ")),
+            )
+        } else if instances.is_empty() {
+            // Nothing to fan out to: the node is produced as an empty collection
+            (NodeState::Created, "Collection is empty".to_string())
+        } else if produced < instances.len() || !self.artifacts.contains_key(node) {
+            (
+                NodeState::Ready,
+                format!("{} of {} instances produced", produced, instances.len()),
+            )
+        } else {
+            (
+                NodeState::Created,
+                format!("{} instances produced", instances.len()),
+            )
+        };
+        NodeStatus {
+            node: node.to_string(),
+            state,
+            reason: Some(reason),
+        }
+    }
+
+    /// Effective lifecycle status of a node, including why it is not running.
+    ///
+    /// Recorded states win as long as they are consistent with the produced
+    /// artifacts; otherwise the state is derived from the graph (creators and
+    /// Dependency edges) and the artifacts produced so far.
+    pub fn node_status(&self, node: &str) -> NodeStatus {
+        // Instances share the graph configuration of their node.
+        let (kind, instance) = split_instance(node);
+        if instance.is_none()
+            && let Some(instances) = self.instances(kind)
+        {
+            return self.collection_status(kind, &instances);
+        }
+
+        let has_artifact = self.artifacts.contains_key(node);
+        let record = self.lifecycle.record(node);
+        if has_artifact && record.is_none_or(|r| r.state != NodeState::Running) {
+            let changed = self.stale_inputs(node);
+            if !changed.is_empty() {
+                return NodeStatus {
+                    node: node.to_string(),
+                    state: NodeState::Stale,
+                    reason: Some(format!("Inputs changed: {}", changed.join(", "))),
+                };
+            }
+        }
+
+        // A Stale record is only trusted while the inputs still differ (above).
+        if let Some(record) = record.filter(|r| r.state != NodeState::Stale) {
+            let consistent = if record.state.has_artifact() {
+                has_artifact
+            } else {
+                !record.state.is_derived()
+            };
+            if consistent {
+                return NodeStatus {
+                    node: node.to_string(),
+                    state: record.state,
+                    reason: record.reason.clone(),
+                };
+            }
+        }
+
+        let (state, reason) = if has_artifact {
+            if self.has_agent_edge(kind, RelationCategory::Verification) {
+                (
+                    NodeState::AwaitingVerification,
+                    Some("Produced; waiting for verification".to_string()),
+                )
+            } else {
+                (NodeState::Created, None)
+            }
+        } else if !self.has_agent_edge(kind, RelationCategory::Creation) {
+            (
+                NodeState::Pending,
+                Some("No agent creates this node".to_string()),
+            )
+        } else {
+            let missing = self.missing_dependencies(kind);
+            if missing.is_empty() {
+                (NodeState::Ready, None)
+            } else {
+                (
+                    NodeState::Blocked,
+                    Some(format!("Waiting on dependencies: {}", missing.join(", "))),
+                )
+            }
+        };
+
+        NodeStatus {
+            node: node.to_string(),
+            state,
+            reason,
+        }
+    }
+
+    /// Targets of the node's Dependency edges (and the collection it is
+    /// produced for each item of) that have not been produced yet.
+    pub fn missing_dependencies(&self, node: &str) -> Vec<String> {
+        let Some(&node_idx) = self.graph.kind_map.get(node) else {
+            return Vec::new();
+        };
+        let mut missing = Vec::new();
+        if let Some(source) = self.graph.for_each.get(node)
+            && !self.artifacts.contains_key(source)
+        {
+            missing.push(source.clone());
+        }
+        for edge in self
+            .graph
+            .graph
+            .edges_directed(node_idx, petgraph::Direction::Outgoing)
+        {
+            let dep_kind = &self.graph.graph[edge.target()];
+            if self.category_of(node, edge.weight(), dep_kind) == RelationCategory::Dependency
+                && !self.artifacts.contains_key(dep_kind)
+                && !missing.contains(dep_kind)
+            {
+                missing.push(dep_kind.clone());
+            }
+        }
+        missing
+    }
+
+    /// Produced targets of the node's Dependency edges (and its `forEach`
+    /// collection): the upstream artifacts it is generated from, whose hashes
+    /// are recorded in its provenance.
+    pub fn upstream(&self, node: &str) -> Vec<String> {
+        let Some(&node_idx) = self.graph.kind_map.get(node) else {
+            return Vec::new();
+        };
+        let mut upstream = Vec::new();
+        if let Some(source) = self.graph.for_each.get(node)
+            && self.artifacts.contains_key(source)
+        {
+            upstream.push(source.clone());
+        }
+        for edge in self
+            .graph
+            .graph
+            .edges_directed(node_idx, petgraph::Direction::Outgoing)
+        {
+            let dep_kind = &self.graph.graph[edge.target()];
+            if self.category_of(node, edge.weight(), dep_kind) == RelationCategory::Dependency
+                && self.artifacts.contains_key(dep_kind)
+                && !upstream.contains(dep_kind)
+            {
+                upstream.push(dep_kind.clone());
+            }
+        }
+        upstream
+    }
+
+    /// Upstream artifacts whose content changed since `node` was produced.
+    pub fn stale_inputs(&self, node: &str) -> Vec<String> {
+        self.provenance
+            .map(|p| p.changed_inputs(node, self.artifacts))
+            .unwrap_or_default()
+    }
+
+    /// Whether an agent targets `node` with an edge of the given category.
+    pub fn has_agent_edge(&self, node: &str, category: RelationCategory) -> bool {
+        let Some(&node_idx) = self.graph.kind_map.get(node) else {
+            return false;
+        };
+        self.graph
+            .graph
+            .edges_directed(node_idx, petgraph::Direction::Incoming)
+            .any(|edge| {
+                let source = &self.graph.graph[edge.source()];
+                self.graph.is_agent(source)
+                    && self.category_of(source, edge.weight(), node) == category
+            })
+    }
+
+    /// Max refinement retries for a target: the Refinement edge's loop config,
+    /// then any loop config on the target, then the default of 3.
+    pub fn max_refinement_retries(&self, target: &str) -> usize {
+        let loop_for = |category: Option<RelationCategory>| {
+            self.graph
+                .loop_configs
+                .iter()
+                .filter(|((_, _, t), _)| t == target)
+                .find(|(key, _)| {
+                    category.is_none_or(|c| self.graph.edge_categories.get(*key) == Some(&c))
+                })
+                .map(|(_, lc)| lc.max_retries)
+        };
+        loop_for(Some(RelationCategory::Refinement))
+            .or_else(|| loop_for(None))
+            .unwrap_or(3)
+    }
+
+    /// Actions that can run now, in graph edge order. Edges targeting a
+    /// fanned-out node yield one action per instance.
+    pub fn next_actions(&self) -> Vec<ActionPlan> {
+        let mut plans = Vec::new();
+        let mut statuses: HashMap<String, NodeStatus> = HashMap::new();
+
+        for edge_idx in self.graph.graph.edge_indices() {
+            let (source_idx, target_idx) = self.graph.graph.edge_endpoints(edge_idx).unwrap();
+            let source_kind = &self.graph.graph[source_idx];
+            let target_kind = &self.graph.graph[target_idx];
+            let relation = &self.graph.graph[edge_idx];
+            let category = self.category_of(source_kind, relation, target_kind);
+
+            if !self.graph.is_agent(source_kind) {
+                continue;
+            }
+
+            // An artifact creation/verification is only actionable if all its Dependency edges are met.
+            let missing = self.missing_dependencies(target_kind);
+            if !missing.is_empty() {
+                debug!(
+                    "Action {} {} {} is blocked by missing dependencies: {:?}",
+                    source_kind, relation, target_kind, missing
+                );
+                continue;
+            }
+
+            let instances: Vec<Option<String>> = match self.instances(target_kind) {
+                Some(keys) => keys
+                    .iter()
+                    .filter_map(|k| split_instance(k).1.map(str::to_string))
+                    .map(Some)
+                    .collect(),
+                None => vec![None],
+            };
+
+            for instance in instances {
+                let plan = ActionPlan {
+                    agent: source_kind.clone(),
+                    target: target_kind.clone(),
+                    relation: relation.clone(),
+                    category,
+                    instance,
+                };
+                let key = plan.key();
+                let state = statuses
+                    .entry(key.clone())
+                    .or_insert_with(|| self.node_status(&key))
+                    .state;
+
+                match (category, state) {
+                    (RelationCategory::Creation, NodeState::Ready)
+                    | (RelationCategory::Verification, NodeState::AwaitingVerification)
+                    | (RelationCategory::Refinement, NodeState::Stale) => {
+                        plans.push(plan);
+                    }
+                    // Without a Refinement edge a stale artifact is regenerated
+                    (RelationCategory::Creation, NodeState::Stale)
+                        if !self.has_agent_edge(target_kind, RelationCategory::Refinement) =>
+                    {
+                        plans.push(plan);
+                    }
+                    // Gate on max retries from LoopConfig
+                    (RelationCategory::Refinement, NodeState::NeedsRefinement) => {
+                        let edge_key = (
+                            source_kind.to_string(),
+                            relation.to_string(),
+                            target_kind.to_string(),
+                        );
+                        let max_retries = self
+                            .graph
+                            .loop_configs
+                            .get(&edge_key)
+                            .map(|lc| lc.max_retries)
+                            .unwrap_or_else(|| self.max_refinement_retries(target_kind));
+                        let attempts = self.refinement_attempts.get(&key).unwrap_or(&0);
+                        if *attempts < max_retries {
+                            plans.push(plan);
+                        } else {
+                            warn!(
+                                "Max retries ({}) reached for refining {}. Skipping.",
+                                max_retries, key
+                            );
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        plans
+    }
+
+    /// Artifacts handed to the agent as context for an action: the target's
+    /// related artifacts, the target itself when verifying or refining, and
+    /// the SoftwareApplication. Only artifacts produced so far are included.
+    /// For an instance action the collection item (`Feature[F1]`) and the
+    /// instance (`UserStory[F1]`) stand in for the whole collections.
+    pub fn context_artifacts(&self, action: &ActionPlan) -> Vec<String> {
+        let mut related: HashSet<String> = self
+            .graph
+            .get_related_artifacts(&action.target)
+            .into_iter()
+            .collect();
+        if matches!(
+            action.category,
+            RelationCategory::Refinement | RelationCategory::Verification
+        ) {
+            related.insert(action.target.clone());
+        }
+        related.insert("SoftwareApplication".to_string());
+
+        if let Some(id) = &action.instance {
+            if related.remove(&action.target) {
+                related.insert(action.key());
+            }
+            if let Some(source) = self.graph.for_each.get(&action.target) {
+                related.remove(source);
+                related.insert(instance_key(source, id));
+            }
+        }
+
+        let mut context: Vec<String> = related
+            .into_iter()
+            .filter(|kind| self.context_value(action, kind).is_some())
+            .collect();
+        context.sort();
+        context
+    }
+
+    /// Value of a context entry returned by [`Planner::context_artifacts`].
+    pub fn context_value(&self, action: &ActionPlan, name: &str) -> Option<Value> {
+        if let Some(id) = &action.instance
+            && let Some(source) = self.graph.for_each.get(&action.target)
+            && name == instance_key(source, id)
+        {
+            return self.instance_item(action);
+        }
+        self.artifacts.get(name).cloned()
+    }
+}
+
+/// A predicted step of a run.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PlannedStep {
+    pub step: usize,
+    pub action: ActionPlan,
+    pub context: Vec<String>,
+}
+
+/// Predicts a run by applying each planned action with its expected outcome
+/// (creations succeed, verifications pass) instead of dispatching an agent.
+#[derive(Debug, Clone, Default)]
+pub struct Simulation {
+    pub artifacts: HashMap<String, Value>,
+    pub lifecycle: NodeLifecycle,
+    pub refinement_attempts: HashMap<String, usize>,
+    pub provenance: Provenance,
+}
+
+impl Simulation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn planner<'a>(&'a self, graph: &'a DependencyGraph) -> Planner<'a> {
+        Planner::new(
+            graph,
+            &self.artifacts,
+            &self.lifecycle,
+            &self.refinement_attempts,
+        )
+        .with_provenance(&self.provenance)
+    }
+
+    /// Marks a node as provided from outside the run (e.g. the initial goal).
+    pub fn provide(&mut self, node: &str, value: Value) {
+        self.artifacts.insert(node.to_string(), value);
+    }
+
+    /// Applies the expected outcome of `action` to the simulated state.
+    pub fn apply(&mut self, graph: &DependencyGraph, action: &ActionPlan) {
+        let key = action.key();
+        let state = match action.category {
+            RelationCategory::Verification => NodeState::Verified,
+            _ => {
+                let was_stale = !self.planner(graph).stale_inputs(&key).is_empty();
+                let upstream = self.planner(graph).upstream(&action.target);
+                self.provenance.record(&key, &upstream, &self.artifacts);
+                self.artifacts.insert(
+                    key.clone(),
+                    Value::String(format!("<predicted output of {}>", action.agent)),
+                );
+                if let Some(collection) = self.planner(graph).aggregate(&action.target) {
+                    self.artifacts.insert(action.target.clone(), collection);
+                }
+                if action.category == RelationCategory::Refinement && !was_stale {
+                    *self.refinement_attempts.entry(key.clone()).or_insert(0) += 1;
+                }
+                if self
+                    .planner(graph)
+                    .has_agent_edge(&action.target, RelationCategory::Verification)
+                {
+                    NodeState::AwaitingVerification
+                } else {
+                    NodeState::Created
+                }
+            }
+        };
+        self.lifecycle.observe(&key, state, None);
+    }
+
+    /// Predicts the order of actions for up to `max_cycles` planning cycles.
+    /// Each cycle runs every identified action, skipping repeated targets when
+    /// actions are dispatched concurrently, exactly like the orchestrator loop.
+    pub fn predict(
+        &mut self,
+        graph: &DependencyGraph,
+        max_cycles: usize,
+        concurrency: usize,
+    ) -> Vec<PlannedStep> {
+        let mut steps = Vec::new();
+        self.predict_with(graph, max_cycles, concurrency, |_, step| {
+            steps.push(step.clone())
+        });
+        steps
+    }
+
+    /// Like [`Simulation::predict`], but hands each step to `on_step` together
+    /// with the state it runs against (before its outcome is applied).
+    pub fn predict_with(
+        &mut self,
+        graph: &DependencyGraph,
+        max_cycles: usize,
+        concurrency: usize,
+        mut on_step: impl FnMut(&Simulation, &PlannedStep),
+    ) {
+        let mut count = 0;
+        for _ in 0..max_cycles {
+            let actions = self.planner(graph).next_actions();
+            if actions.is_empty() {
+                break;
+            }
+            let mut claimed_targets = HashSet::new();
+            for action in actions {
+                if concurrency > 1 && !claimed_targets.insert(action.key()) {
+                    continue;
+                }
+                count += 1;
+                let step = PlannedStep {
+                    step: count,
+                    context: self.planner(graph).context_artifacts(&action),
+                    action,
+                };
+                on_step(self, &step);
+                self.apply(graph, &step.action);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(json: &str) -> DependencyGraph {
+        DependencyGraph::load_from_metamodel(json, None).unwrap()
+    }
+
+    #[test]
+    fn test_predict_respects_dependencies_and_verification() {
+        let graph = load(
+            r#"[
+            {"source": {"name": "Dev", "type": "Agent"}, "target": {"name": "Code", "type": "Other"}, "type": {"name": "creates", "verbType": "Creation"}},
+            {"source": {"name": "Architect", "type": "Agent"}, "target": {"name": "Design", "type": "Other"}, "type": {"name": "creates", "verbType": "Creation"}},
+            {"source": {"name": "Code", "type": "Other"}, "target": {"name": "Design", "type": "Other"}, "type": {"name": "dependsOn", "verbType": "Dependency"}},
+            {"source": {"name": "QA", "type": "Agent"}, "target": {"name": "Design", "type": "Other"}, "type": {"name": "verifies", "verbType": "Verification"}}
+        ]"#,
+        );
+
+        let mut simulation = Simulation::new();
+        let steps = simulation.predict(&graph, 10, 1);
+        let order: Vec<(&str, &str)> = steps
+            .iter()
+            .map(|s| (s.action.agent.as_str(), s.action.target.as_str()))
+            .collect();
+        assert_eq!(
+            order,
+            vec![("Architect", "Design"), ("Dev", "Code"), ("QA", "Design")]
+        );
+        assert_eq!(steps[1].context, vec!["Design".to_string()]);
+        assert_eq!(
+            simulation.planner(&graph).node_status("Design").state,
+            NodeState::Verified
+        );
+    }
+
+    #[test]
+    fn test_refinement_is_planned_for_failed_verification() {
+        let graph = load(
+            r#"[
+            {"source": {"name": "QA", "type": "Agent"}, "target": {"name": "Spec", "type": "Other"}, "type": {"name": "verifies", "verbType": "Verification"}},
+            {"source": {"name": "Dev", "type": "Agent"}, "target": {"name": "Spec", "type": "Other"}, "type": {"name": "refines", "verbType": "Refinement"}}
+        ]"#,
+        );
+
+        let mut simulation = Simulation::new();
+        simulation.provide("Spec", Value::Null);
+        simulation
+            .lifecycle
+            .observe("Spec", NodeState::NeedsRefinement, None);
+
+        let steps = simulation.predict(&graph, 10, 1);
+        let categories: Vec<RelationCategory> = steps.iter().map(|s| s.action.category).collect();
+        assert_eq!(
+            categories,
+            vec![RelationCategory::Refinement, RelationCategory::Verification]
+        );
+        assert_eq!(simulation.refinement_attempts.get("Spec"), Some(&1));
+    }
+}
@@ -0,0 +1,127 @@
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+
+/// Stable content hash of an artifact (SHA-256 of its compact JSON, whose
+/// object keys are always sorted).
+pub fn content_hash(value: &Value) -> String {
+    let digest = Sha256::digest(value.to_string().as_bytes());
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Records, per artifact, the hashes of the upstream artifacts it was produced
+/// from, so that changes upstream (a regenerated artifact or a human edit in the
+/// docs folder) can be detected like a build system detects stale outputs.
+#[derive(Debug, Clone, Default)]
+pub struct Provenance {
+    inputs: HashMap<String, BTreeMap<String, String>>,
+}
+
+impl Provenance {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remembers the current content of `upstream` as the inputs of `node`.
+    pub fn record(&mut self, node: &str, upstream: &[String], artifacts: &HashMap<String, Value>) {
+        let hashes = upstream
+            .iter()
+            .filter_map(|name| {
+                artifacts
+                    .get(name)
+                    .map(|value| (name.clone(), content_hash(value)))
+            })
+            .collect();
+        self.inputs.insert(node.to_string(), hashes);
+    }
+
+    /// Upstream hashes recorded for `node`, if it was produced in this iteration.
+    pub fn inputs(&self, node: &str) -> Option<&BTreeMap<String, String>> {
+        self.inputs.get(node)
+    }
+
+    /// Recorded inputs of `node` whose content no longer matches. Inputs that
+    /// were removed altogether are left to invalidation.
+    pub fn changed_inputs(&self, node: &str, artifacts: &HashMap<String, Value>) -> Vec<String> {
+        let Some(inputs) = self.inputs.get(node) else {
+            return Vec::new();
+        };
+        inputs
+            .iter()
+            .filter(|(name, hash)| {
+                artifacts
+                    .get(*name)
+                    .is_some_and(|value| content_hash(value) != **hash)
+            })
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    pub fn remove(&mut self, node: &str) {
+        self.inputs.remove(node);
+    }
+
+    pub fn to_map(&self) -> BTreeMap<String, BTreeMap<String, String>> {
+        self.inputs
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+
+    pub fn from_map(map: BTreeMap<String, BTreeMap<String, String>>) -> Self {
+        Self {
+            inputs: map.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_inputs_follow_content() {
+        let mut artifacts = HashMap::from([
+            (
+                "Requirement".to_string(),
+                serde_json::json!({"id": "R1", "text": "a"}),
+            ),
+            ("UserStory".to_string(), serde_json::json!({"story": "s"})),
+        ]);
+        let mut provenance = Provenance::new();
+        provenance.record(
+            "DesignSpec",
+            &["Requirement".to_string(), "UserStory".to_string()],
+            &artifacts,
+        );
+        assert!(
+            provenance
+                .changed_inputs("DesignSpec", &artifacts)
+                .is_empty()
+        );
+
+        // Key order does not matter, content does.
+        artifacts.insert(
+            "Requirement".to_string(),
+            serde_json::json!({"text": "a", "id": "R1"}),
+        );
+        assert!(
+            provenance
+                .changed_inputs("DesignSpec", &artifacts)
+                .is_empty()
+        );
+
+        artifacts.insert(
+            "UserStory".to_string(),
+            serde_json::json!({"story": "edited"}),
+        );
+        assert_eq!(
+            provenance.changed_inputs("DesignSpec", &artifacts),
+            vec!["UserStory".to_string()]
+        );
+
+        let restored = Provenance::from_map(provenance.to_map());
+        assert_eq!(restored.inputs("DesignSpec").map(|i| i.len()), Some(2));
+        assert!(restored.changed_inputs("Code", &artifacts).is_empty());
+    }
+}
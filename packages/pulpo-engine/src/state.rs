@@ -0,0 +1,152 @@
+use crate::lifecycle::NodeRecord;
+use crate::usage::UsageReport;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Durable snapshot of the orchestrator state for a single iteration.
+///
+/// Written to `.infinitecodingloop/iterations/<id>/state.json` after every action so
+/// that a resumed iteration continues exactly where it stopped, including pending
+/// verification feedback and refinement retry counters. Ordered collections keep
+/// the file stable between checkpoints.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OrchestratorState {
+    pub artifacts: BTreeMap<String, serde_json::Value>,
+    #[serde(default)]
+    pub verification_feedback: BTreeMap<String, String>,
+    /// Recorded lifecycle state per node (see [`crate::lifecycle::NodeState`]).
+    #[serde(default)]
+    pub node_states: BTreeMap<String, NodeRecord>,
+    #[serde(default)]
+    pub refinement_attempts: BTreeMap<String, usize>,
+    /// Hashes of the upstream artifacts each artifact was produced from.
+    #[serde(default)]
+    pub provenance: BTreeMap<String, BTreeMap<String, String>>,
+    /// Failed attempts per target and the last error, fed into the retry prompt.
+    #[serde(default)]
+    pub failure_attempts: BTreeMap<String, usize>,
+    #[serde(default)]
+    pub failure_feedback: BTreeMap<String, String>,
+    /// Number of actions completed in this iteration so far.
+    #[serde(default)]
+    pub actions_executed: usize,
+    /// When the run was cancelled (e.g. Ctrl-C) before the iteration finished.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interrupted_at: Option<String>,
+    /// Usage totals of the iteration, counted against its budget on resume.
+    #[serde(default)]
+    pub usage: UsageReport,
+    pub updated_at: String,
+}
+
+impl OrchestratorState {
+    pub const FILE_NAME: &'static str = "state.json";
+    /// Folder of the checkpoints taken right after each engine commit, named
+    /// `<sha>.json`. Rolling back to a commit restores its checkpoint.
+    pub const COMMITS_DIR: &'static str = "commits";
+
+    /// Loads the checkpoint of an iteration, if one was written.
+    pub async fn load(iteration_dir: &Path) -> Result<Option<Self>> {
+        Self::load_file(&iteration_dir.join(Self::FILE_NAME)).await
+    }
+
+    /// Loads the checkpoint taken right after commit `sha`, if there is one.
+    pub async fn load_at_commit(iteration_dir: &Path, sha: &str) -> Result<Option<Self>> {
+        Self::load_file(&Self::commit_path(iteration_dir, sha)).await
+    }
+
+    /// Writes the checkpoint atomically (temp file + rename) so that a crash while
+    /// saving never leaves a truncated state behind.
+    pub async fn save(&self, iteration_dir: &Path) -> Result<()> {
+        self.save_file(&iteration_dir.join(Self::FILE_NAME)).await
+    }
+
+    /// Keeps the checkpoint as the state right after commit `sha`.
+    pub async fn save_at_commit(&self, iteration_dir: &Path, sha: &str) -> Result<()> {
+        self.save_file(&Self::commit_path(iteration_dir, sha)).await
+    }
+
+    fn commit_path(iteration_dir: &Path, sha: &str) -> PathBuf {
+        iteration_dir
+            .join(Self::COMMITS_DIR)
+            .join(format!("{}.json", sha))
+    }
+
+    async fn load_file(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Failed to read checkpoint {}", path.display()))?;
+        let state = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse checkpoint {}", path.display()))?;
+        Ok(Some(state))
+    }
+
+    async fn save_file(&self, path: &Path) -> Result<()> {
+        let dir = path.parent().context("Checkpoint path has no parent")?;
+        tokio::fs::create_dir_all(dir).await?;
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        let content = serde_json::to_string_pretty(self)?;
+        tokio::fs::write(&tmp_path, content)
+            .await
+            .context("Failed to write checkpoint")?;
+        tokio::fs::rename(&tmp_path, path)
+            .await
+            .context("Failed to commit checkpoint")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_state_round_trip() {
+        let tmp = tempdir().unwrap();
+        let iter_dir = tmp.path().join("20260214_0001");
+
+        assert!(OrchestratorState::load(&iter_dir).await.unwrap().is_none());
+
+        let mut state = OrchestratorState {
+            actions_executed: 3,
+            updated_at: "20260214_120000".to_string(),
+            ..Default::default()
+        };
+        state.artifacts.insert(
+            "Code".to_string(),
+            serde_json::json!({"files": ["main.rs"]}),
+        );
+        state
+            .verification_feedback
+            .insert("Code".to_string(), "Tests failing".to_string());
+        state.node_states.insert(
+            "Requirement".to_string(),
+            NodeRecord {
+                state: crate::lifecycle::NodeState::Verified,
+                reason: None,
+            },
+        );
+        state.refinement_attempts.insert("Code".to_string(), 2);
+
+        state.save(&iter_dir).await.unwrap();
+        assert!(!iter_dir.join("state.json.tmp").exists());
+
+        let loaded = OrchestratorState::load(&iter_dir).await.unwrap().unwrap();
+        assert_eq!(loaded, state);
+
+        state.save_at_commit(&iter_dir, "abc123").await.unwrap();
+        assert!(iter_dir.join("commits/abc123.json").exists());
+        let at_commit = OrchestratorState::load_at_commit(&iter_dir, "abc123")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(at_commit, state);
+    }
+}
@@ -0,0 +1,296 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+/// Token counts reported by the AI CLI for a single prompt.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    pub input: u64,
+    pub output: u64,
+    pub total: u64,
+}
+
+/// Slot shared between the orchestrator and the AI client for one dispatch.
+///
+/// The client records the token counts it observed (only known when the CLI
+/// reports them, e.g. with `--output-format json`) and the orchestrator takes
+/// them once the dispatch has finished.
+#[derive(Debug, Clone, Default)]
+pub struct UsageSlot(Arc<Mutex<Option<TokenUsage>>>);
+
+impl UsageSlot {
+    pub fn record(&self, tokens: TokenUsage) {
+        if let Ok(mut slot) = self.0.lock() {
+            *slot = Some(tokens);
+        }
+    }
+
+    pub fn take(&self) -> Option<TokenUsage> {
+        self.0.lock().ok().and_then(|mut slot| slot.take())
+    }
+}
+
+/// What a single agent dispatch cost.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DispatchUsage {
+    pub prompt_chars: usize,
+    pub response_chars: usize,
+    pub duration_ms: u64,
+    pub tokens: Option<TokenUsage>,
+}
+
+/// Running totals over any number of dispatches.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageTotals {
+    pub dispatches: usize,
+    pub prompt_chars: usize,
+    pub response_chars: usize,
+    pub duration_ms: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub total_tokens: u64,
+}
+
+impl UsageTotals {
+    pub fn add(&mut self, usage: &DispatchUsage) {
+        self.dispatches += 1;
+        self.prompt_chars += usage.prompt_chars;
+        self.response_chars += usage.response_chars;
+        self.duration_ms += usage.duration_ms;
+        if let Some(tokens) = usage.tokens {
+            self.input_tokens += tokens.input;
+            self.output_tokens += tokens.output;
+            self.total_tokens += tokens.total;
+        }
+    }
+}
+
+/// Usage of an iteration, aggregated per agent, per edge and overall.
+/// Edges are keyed as `"<agent> <relation> <target>"`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageReport {
+    pub iteration: UsageTotals,
+    #[serde(default)]
+    pub per_agent: BTreeMap<String, UsageTotals>,
+    #[serde(default)]
+    pub per_edge: BTreeMap<String, UsageTotals>,
+}
+
+impl UsageReport {
+    pub fn record(&mut self, agent: &str, relation: &str, target: &str, usage: &DispatchUsage) {
+        self.iteration.add(usage);
+        self.per_agent
+            .entry(agent.to_string())
+            .or_default()
+            .add(usage);
+        self.per_edge
+            .entry(format!("{} {} {}", agent, relation, target))
+            .or_default()
+            .add(usage);
+    }
+}
+
+/// What the run loop does once a budget is exceeded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetAction {
+    /// Checkpoint and return; the iteration can be resumed with a larger budget.
+    #[default]
+    Pause,
+    /// Checkpoint and fail the run.
+    Stop,
+}
+
+impl FromStr for BudgetAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pause" => Ok(BudgetAction::Pause),
+            "stop" => Ok(BudgetAction::Stop),
+            other => Err(anyhow::anyhow!(
+                "Unknown budget action '{}' (expected pause or stop)",
+                other
+            )),
+        }
+    }
+}
+
+/// Limits for an iteration. Tokens and dispatches count over the whole
+/// iteration (including resumed runs); wall time counts the current run.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Budget {
+    pub max_tokens: Option<u64>,
+    pub max_wall_secs: Option<u64>,
+    pub max_dispatches: Option<usize>,
+    pub on_exceeded: BudgetAction,
+}
+
+impl Budget {
+    /// Returns why the budget is exhausted, if it is.
+    pub fn exceeded(&self, totals: &UsageTotals, wall_secs: u64) -> Option<String> {
+        if let Some(max) = self.max_tokens
+            && totals.total_tokens >= max
+        {
+            return Some(format!(
+                "token budget exhausted ({} of {} tokens)",
+                totals.total_tokens, max
+            ));
+        }
+        if let Some(max) = self.max_dispatches
+            && totals.dispatches >= max
+        {
+            return Some(format!(
+                "dispatch budget exhausted ({} of {} dispatches)",
+                totals.dispatches, max
+            ));
+        }
+        if let Some(max) = self.max_wall_secs
+            && wall_secs >= max
+        {
+            return Some(format!(
+                "wall time budget exhausted ({}s of {}s)",
+                wall_secs, max
+            ));
+        }
+        None
+    }
+
+    /// Dispatches still allowed in the iteration, if they are limited.
+    pub fn remaining_dispatches(&self, totals: &UsageTotals) -> Option<usize> {
+        self.max_dispatches
+            .map(|max| max.saturating_sub(totals.dispatches))
+    }
+}
+
+/// Unwraps the JSON envelope some AI CLIs print with `--output-format json`,
+/// returning the model's answer and the token counts it reports.
+///
+/// Understands the Gemini CLI shape (`response` plus `stats.models.*.tokens`)
+/// and the Claude CLI shape (`result` plus `usage`). Returns `None` for
+/// anything else so the raw output is used as-is.
+pub fn parse_cli_json_output(output: &str) -> Option<(String, Option<TokenUsage>)> {
+    let value: serde_json::Value = serde_json::from_str(output.trim()).ok()?;
+    let answer = value
+        .get("response")
+        .or_else(|| value.get("result"))?
+        .as_str()?
+        .to_string();
+
+    let count = |v: &serde_json::Value, key: &str| v.get(key).and_then(|n| n.as_u64());
+
+    let tokens = if let Some(models) = value.pointer("/stats/models").and_then(|m| m.as_object()) {
+        let mut tokens = TokenUsage::default();
+        for stats in models.values().filter_map(|m| m.get("tokens")) {
+            let input = count(stats, "prompt").unwrap_or(0);
+            let output = count(stats, "candidates").unwrap_or(0);
+            tokens.input += input;
+            tokens.output += output;
+            tokens.total += count(stats, "total").unwrap_or(input + output);
+        }
+        Some(tokens)
+    } else {
+        value.get("usage").map(|usage| {
+            let input = count(usage, "input_tokens").unwrap_or(0)
+                + count(usage, "cache_creation_input_tokens").unwrap_or(0)
+                + count(usage, "cache_read_input_tokens").unwrap_or(0);
+            let output = count(usage, "output_tokens").unwrap_or(0);
+            TokenUsage {
+                input,
+                output,
+                total: input + output,
+            }
+        })
+    };
+
+    Some((answer, tokens))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cli_json_output() {
+        let gemini = r#"{
+            "response": "{\"name\": \"Design\"}",
+            "stats": { "models": {
+                "gemini-2.5-pro": { "tokens": { "prompt": 120, "candidates": 30, "total": 170, "thoughts": 20 } },
+                "gemini-2.5-flash": { "tokens": { "prompt": 10, "candidates": 5, "total": 15 } }
+            } }
+        }"#;
+        let (answer, tokens) = parse_cli_json_output(gemini).unwrap();
+        assert_eq!(answer, "{\"name\": \"Design\"}");
+        assert_eq!(
+            tokens,
+            Some(TokenUsage {
+                input: 130,
+                output: 35,
+                total: 185
+            })
+        );
+
+        let claude = r#"{"type": "result", "result": "done", "usage": {"input_tokens": 7, "cache_read_input_tokens": 3, "output_tokens": 4}}"#;
+        let (answer, tokens) = parse_cli_json_output(claude).unwrap();
+        assert_eq!(answer, "done");
+        assert_eq!(
+            tokens,
+            Some(TokenUsage {
+                input: 10,
+                output: 4,
+                total: 14
+            })
+        );
+
+        assert!(parse_cli_json_output("plain text answer").is_none());
+        assert!(parse_cli_json_output(r#"{"name": "Design"}"#).is_none());
+    }
+
+    #[test]
+    fn test_budget_exceeded() {
+        let mut report = UsageReport::default();
+        let usage = DispatchUsage {
+            prompt_chars: 100,
+            response_chars: 50,
+            duration_ms: 10,
+            tokens: Some(TokenUsage {
+                input: 60,
+                output: 40,
+                total: 100,
+            }),
+        };
+        report.record("Architect", "creates", "Design", &usage);
+        report.record("Architect", "creates", "Design", &usage);
+        assert_eq!(report.iteration.total_tokens, 200);
+        assert_eq!(report.per_agent["Architect"].dispatches, 2);
+        assert_eq!(
+            report.per_edge["Architect creates Design"].prompt_chars,
+            200
+        );
+
+        let budget = Budget {
+            max_tokens: Some(500),
+            max_dispatches: Some(3),
+            max_wall_secs: Some(60),
+            ..Default::default()
+        };
+        assert_eq!(budget.exceeded(&report.iteration, 5), None);
+        assert!(
+            budget
+                .exceeded(&report.iteration, 60)
+                .unwrap()
+                .contains("wall time")
+        );
+        assert_eq!(budget.remaining_dispatches(&report.iteration), Some(1));
+        report.record("Architect", "creates", "Design", &usage);
+        assert_eq!(budget.remaining_dispatches(&report.iteration), Some(0));
+        assert!(
+            budget
+                .exceeded(&report.iteration, 5)
+                .unwrap()
+                .contains("dispatch budget")
+        );
+        assert_eq!(Budget::default().exceeded(&report.iteration, 1000), None);
+    }
+}
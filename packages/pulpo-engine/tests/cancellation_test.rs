@@ -0,0 +1,94 @@
+mod common;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use pulpo_engine::agents::cli_client::AiCliClient;
+use pulpo_engine::lifecycle::NodeState;
+use pulpo_engine::orchestrator::Orchestrator;
+use pulpo_engine::state::OrchestratorState;
+use std::time::{Duration, Instant};
+use tempfile::tempdir;
+
+use common::mock_ui;
+
+/// Client whose AI never answers.
+#[derive(Clone)]
+struct HangingClient;
+
+#[async_trait]
+impl AiCliClient for HangingClient {
+    async fn prompt(
+        &self,
+        _prompt: &str,
+        _options: pulpo_engine::graph::executor::ExecutionOptions,
+    ) -> Result<String> {
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        Ok("{}".to_string())
+    }
+}
+
+#[tokio::test]
+async fn test_cancelled_run_is_checkpointed_and_resumable() -> Result<()> {
+    let tmp_dir = tempdir()?;
+    let work_dir = tmp_dir.path().to_path_buf();
+
+    let metamodel_json = r#"[
+        { "source": { "name": "Architect", "type": "Agent" }, "target": { "name": "Design", "type": "Other" }, "type": { "name": "creates", "verbType": "Creation" } }
+    ]"#;
+
+    let (cancel_tx, cancel_rx) = tokio::sync::watch::channel(false);
+    let mut orchestrator = Orchestrator::new_with_metamodel(
+        HangingClient,
+        "test-app-id".to_string(),
+        "Test App".to_string(),
+        work_dir.clone(),
+        metamodel_json,
+        None,
+    )
+    .await?
+    .with_cancellation(cancel_rx);
+
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let _ = cancel_tx.send(true);
+    });
+
+    let started = Instant::now();
+    orchestrator.run(&mock_ui()).await?;
+    assert!(
+        started.elapsed() < Duration::from_secs(10),
+        "Cancellation should not wait for the hanging AI"
+    );
+    assert_eq!(orchestrator.node_status("Design").state, NodeState::Ready);
+
+    let iteration_id = orchestrator.current_iteration.as_ref().unwrap().id.clone();
+    let iter_dir = work_dir
+        .join(".infinitecodingloop/iterations")
+        .join(&iteration_id);
+    let state = OrchestratorState::load(&iter_dir).await?.unwrap();
+    assert!(state.interrupted_at.is_some());
+    assert_ne!(
+        state.node_states.get("Design").map(|r| r.state),
+        Some(NodeState::Running)
+    );
+
+    let log = std::fs::read_to_string(iter_dir.join("logs/execution.jsonl"))?;
+    assert!(log.contains("\"iteration_interrupted\""));
+
+    // A fresh process picks the interrupted action up again.
+    let mut resumed = Orchestrator::new_with_metamodel(
+        HangingClient,
+        "test-app-id".to_string(),
+        "Test App".to_string(),
+        work_dir,
+        metamodel_json,
+        None,
+    )
+    .await?;
+    resumed.load_iteration(&iteration_id).await?;
+    assert!(resumed.interrupted_at.is_some());
+    let actions = resumed.identify_next_actions();
+    assert_eq!(actions.len(), 1);
+    assert_eq!(actions[0].target, "Design");
+    Ok(())
+}
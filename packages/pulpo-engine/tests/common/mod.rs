@@ -0,0 +1,22 @@
+//! Helpers shared by the engine integration tests.
+#![allow(dead_code)]
+
+use pulpo_engine::interaction::mocks::MockUserInteraction;
+use std::path::Path;
+
+/// A UI that answers the initial feature prompt and confirms every action.
+pub fn mock_ui() -> MockUserInteraction {
+    let ui = MockUserInteraction::new();
+    ui.add_feature_response("Test Feature".to_string());
+    ui
+}
+
+/// Runs git in `work_dir` and returns its trimmed stdout.
+pub fn git(work_dir: &Path, args: &[&str]) -> String {
+    let output = std::process::Command::new("git")
+        .args(args)
+        .current_dir(work_dir)
+        .output()
+        .expect("git is installed");
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
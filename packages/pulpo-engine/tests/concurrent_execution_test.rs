@@ -0,0 +1,109 @@
+mod common;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use pulpo_engine::agents::cli_client::AiCliClient;
+use pulpo_engine::orchestrator::Orchestrator;
+use serde_json::Value;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tempfile::tempdir;
+
+use common::mock_ui;
+
+/// Slow client that records how many prompts were in flight at the same time.
+#[derive(Clone)]
+struct SlowTrackingClient {
+    in_flight: Arc<AtomicUsize>,
+    max_in_flight: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl AiCliClient for SlowTrackingClient {
+    async fn prompt(
+        &self,
+        prompt: &str,
+        _options: pulpo_engine::graph::executor::ExecutionOptions,
+    ) -> Result<String> {
+        let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_in_flight.fetch_max(now, Ordering::SeqCst);
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+        let target = prompt
+            .rsplit("Please generate the ")
+            .next()
+            .and_then(|rest| rest.split(" artifact.").next())
+            .unwrap_or("unknown")
+            .trim()
+            .to_string();
+        Ok(serde_json::json!({ "produced": target }).to_string())
+    }
+}
+
+#[tokio::test]
+async fn test_ready_actions_run_concurrently_and_merge_in_order() -> Result<()> {
+    let tmp_dir = tempdir()?;
+    let work_dir = tmp_dir.path().to_path_buf();
+
+    let metamodel_json = r#"[
+        { "source": { "name": "Architect", "type": "Agent" }, "target": { "name": "StyleA", "type": "Other" }, "type": { "name": "defines", "verbType": "Creation" } },
+        { "source": { "name": "Architect", "type": "Agent" }, "target": { "name": "StyleB", "type": "Other" }, "type": { "name": "defines", "verbType": "Creation" } },
+        { "source": { "name": "Architect", "type": "Agent" }, "target": { "name": "StyleC", "type": "Other" }, "type": { "name": "defines", "verbType": "Creation" } }
+    ]"#;
+
+    let client = SlowTrackingClient {
+        in_flight: Arc::new(AtomicUsize::new(0)),
+        max_in_flight: Arc::new(AtomicUsize::new(0)),
+    };
+
+    let mut orchestrator = Orchestrator::new_with_metamodel(
+        client.clone(),
+        "test-app-id".to_string(),
+        "Test App".to_string(),
+        work_dir.clone(),
+        metamodel_json,
+        None,
+    )
+    .await?
+    .with_max_iterations(1)
+    .with_concurrency(2);
+
+    orchestrator.run(&mock_ui()).await?;
+
+    assert_eq!(
+        client.max_in_flight.load(Ordering::SeqCst),
+        2,
+        "Dispatches should overlap up to the concurrency limit"
+    );
+    for target in ["StyleA", "StyleB", "StyleC"] {
+        let artifact = orchestrator
+            .artifacts
+            .get(target)
+            .unwrap_or_else(|| panic!("{} should have been produced", target));
+        assert_eq!(artifact["produced"], target);
+        assert!(
+            work_dir
+                .join(orchestrator.artifact_folder(target))
+                .join(format!("{}.json", target.to_lowercase()))
+                .exists()
+        );
+    }
+
+    // Results are merged in plan order, regardless of completion order.
+    let iteration = orchestrator.current_iteration.as_ref().unwrap();
+    let artifacts_meta = std::fs::read_to_string(
+        work_dir
+            .join(".infinitecodingloop/iterations")
+            .join(&iteration.id)
+            .join("artifacts.json"),
+    )?;
+    let entries: Vec<Value> = serde_json::from_str(&artifacts_meta)?;
+    let names: Vec<&str> = entries.iter().filter_map(|e| e["name"].as_str()).collect();
+    assert_eq!(
+        names,
+        vec!["SoftwareApplication", "StyleA", "StyleB", "StyleC"]
+    );
+
+    Ok(())
+}
@@ -1,38 +1,22 @@
+mod common;
+
 use anyhow::Result;
 use async_trait::async_trait;
 use pulpo_engine::agents::cli_client::AiCliClient;
-use pulpo_engine::interaction::UserInteraction;
+use pulpo_engine::git::GitOptions;
 use pulpo_engine::orchestrator::Orchestrator;
 use serde_json::Value;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use tempfile::tempdir;
 
-struct MockUi;
-
-#[async_trait]
-impl UserInteraction for MockUi {
-    async fn ask_user(&self, _prompt: &str) -> Result<String> {
-        Ok("yes".to_string())
-    }
-    async fn ask_for_feature(&self, _prompt: &str) -> Result<String> {
-        Ok("Test Feature".to_string())
-    }
-    async fn confirm(&self, _prompt: &str) -> Result<bool> {
-        Ok(true)
-    }
-    async fn select_option(&self, _prompt: &str, _options: &[String]) -> Result<usize> {
-        Ok(0)
-    }
-    fn start_step(&self, _msg: &str) {}
-    fn end_step(&self, _msg: &str) {}
-    fn render_artifact(&self, _kind: &str, _data: &Value) {}
-    fn log_info(&self, _msg: &str) {}
-    fn log_error(&self, _msg: &str) {}
-}
+use common::{git, mock_ui};
 
 #[derive(Clone)]
 struct TrackingMockCliClient {
     pub prompts_received: Arc<Mutex<Vec<String>>>,
+    // A file the AI CLI writes in the work dir, as a coding agent would
+    pub writes: Option<PathBuf>,
 }
 
 #[async_trait]
@@ -46,12 +30,12 @@ impl AiCliClient for TrackingMockCliClient {
             .lock()
             .unwrap()
             .push(prompt.to_string());
-
-        if prompt.contains("commit") || prompt.contains("git add .") {
-            Ok("Git commit successful".to_string())
-        } else {
-            Ok(r#"{"result": "mocked artifact content", "name": "Feature"}"#.to_string())
+        if let Some(path) = &self.writes {
+            std::fs::create_dir_all(path.parent().unwrap())?;
+            std::fs::write(path, "pub fn feature() {}")?;
         }
+
+        Ok(r#"{"result": "mocked artifact content", "name": "Feature"}"#.to_string())
     }
 }
 
@@ -59,6 +43,8 @@ impl AiCliClient for TrackingMockCliClient {
 async fn test_git_commit_after_action() -> Result<()> {
     let tmp_dir = tempdir()?;
     let work_dir = tmp_dir.path().to_path_buf();
+    git(&work_dir, &["init", "--quiet"]);
+    std::fs::write(work_dir.join("notes.txt"), "not for the engine")?;
 
     let ontology_root = work_dir.join("ontology");
     let schema_dir = ontology_root.join("artifact/schema");
@@ -91,6 +77,7 @@ async fn test_git_commit_after_action() -> Result<()> {
     let prompts_received = Arc::new(Mutex::new(Vec::new()));
     let client = TrackingMockCliClient {
         prompts_received: prompts_received.clone(),
+        writes: Some(work_dir.join("src/lib.rs")),
     };
 
     let mut orchestrator = Orchestrator::new_with_metamodel(
@@ -103,20 +90,104 @@ async fn test_git_commit_after_action() -> Result<()> {
     )
     .await?;
 
-    orchestrator = orchestrator.with_max_iterations(1);
-    orchestrator.run(&MockUi).await?;
+    orchestrator = orchestrator.with_max_iterations(1).with_git(GitOptions {
+        commit: true,
+        branch_per_iteration: true,
+    });
+    orchestrator.run(&mock_ui()).await?;
 
+    // The engine commits by itself; the AI is never asked to.
     let prompts = prompts_received.lock().unwrap();
-
-    let commit_prompt_found = prompts
-        .iter()
-        .any(|p| p.contains("git add .") && p.contains("commit"));
-
     assert!(
-        commit_prompt_found,
-        "Expected AI CLI to receive a prompt containing git commit instructions. Prompts received: {:?}",
+        !prompts.iter().any(|p| p.contains("git add")),
+        "No prompt should ask the AI CLI to commit. Prompts received: {:?}",
         prompts
     );
 
+    let iteration_id = orchestrator.current_iteration.as_ref().unwrap().id.clone();
+    assert_eq!(
+        git(&work_dir, &["branch", "--show-current"]),
+        format!("pulpo/{}", iteration_id)
+    );
+    let sha = git(&work_dir, &["rev-parse", "HEAD"]);
+    assert_eq!(
+        git(&work_dir, &["log", "-1", "--format=%s"]),
+        "ProductManager creates Feature"
+    );
+    let trailers = git(&work_dir, &["log", "-1", "--format=%(trailers)"]);
+    assert!(trailers.contains(&format!("Pulpo-Iteration: {}", iteration_id)));
+    assert!(trailers.contains("Pulpo-Agent: ProductManager"));
+    assert!(trailers.contains("Pulpo-Relation: creates"));
+    assert!(trailers.contains("Pulpo-Target: Feature"));
+    assert!(trailers.contains("Pulpo-Model: "));
+    // The artifacts and the files written by the AI CLI are committed, and
+    // nothing that was there before the action.
+    let tracked = git(&work_dir, &["ls-files"]);
+    assert!(tracked.lines().any(|path| path.starts_with("spec/")));
+    assert!(tracked.lines().any(|path| path == "src/lib.rs"));
+    assert!(
+        tracked
+            .lines()
+            .all(|path| path.starts_with("spec/") || path == "src/lib.rs")
+    );
+
+    // The SHA is recorded in artifacts.json and in the execution log.
+    let iteration_dir = work_dir
+        .join(".infinitecodingloop/iterations")
+        .join(&iteration_id);
+    let entries: Vec<Value> = serde_json::from_str(&std::fs::read_to_string(
+        iteration_dir.join("artifacts.json"),
+    )?)?;
+    let feature = entries
+        .iter()
+        .rev()
+        .find(|e| e["name"] == "Feature")
+        .unwrap();
+    assert_eq!(feature["commit"], sha.as_str());
+
+    let log = std::fs::read_to_string(iteration_dir.join("logs/execution.jsonl"))?;
+    let committed: Value = log
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .find(|e| e["event_type"] == "artifact_committed")
+        .expect("a commit event");
+    assert_eq!(committed["details"]["sha"], sha.as_str());
+    assert_eq!(committed["details"]["name"], "Feature");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_no_commits_outside_a_repository() -> Result<()> {
+    let tmp_dir = tempdir()?;
+    let work_dir = tmp_dir.path().to_path_buf();
+
+    let metamodel_json = r#"[
+        {
+             "source": { "name": "ProductManager", "type": "Agent" },
+             "target": { "name": "Feature", "type": "Other" },
+             "type": { "name": "creates", "verbType": "Creation" }
+        }
+    ]"#;
+    let client = TrackingMockCliClient {
+        prompts_received: Arc::new(Mutex::new(Vec::new())),
+        writes: None,
+    };
+
+    let mut orchestrator = Orchestrator::new_with_metamodel(
+        client,
+        "test-app-id".to_string(),
+        "Test App".to_string(),
+        work_dir.clone(),
+        metamodel_json,
+        None,
+    )
+    .await?
+    .with_max_iterations(1);
+    orchestrator.run(&mock_ui()).await?;
+
+    // The engine never initializes a repository by itself.
+    assert!(orchestrator.artifacts.contains_key("Feature"));
+    assert!(!work_dir.join(".git").exists());
     Ok(())
 }
@@ -0,0 +1,192 @@
+mod common;
+
+use anyhow::Result;
+use pulpo_engine::agents::cli_client::mocks::MockCliClient;
+use pulpo_engine::orchestrator::Orchestrator;
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use tempfile::tempdir;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+use common::mock_ui;
+
+/// Stand-in for a hook endpoint: records every POST as (path, body). `/approve`
+/// denies, any other path accepts with no content.
+async fn serve_hooks() -> Result<(String, Arc<Mutex<Vec<(String, Value)>>>)> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let base_url = format!("http://{}", listener.local_addr()?);
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let recorded = received.clone();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            // Read the headers, then as much body as Content-Length announces
+            let (head, body) = loop {
+                let n = stream.read(&mut buf).await.unwrap_or(0);
+                if n == 0 {
+                    break (String::new(), String::new());
+                }
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request).to_string();
+                if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                    let length = head
+                        .lines()
+                        .find_map(|line| {
+                            let (name, value) = line.split_once(':')?;
+                            name.eq_ignore_ascii_case("content-length")
+                                .then(|| value.trim().parse::<usize>().ok())?
+                        })
+                        .unwrap_or(0);
+                    if body.len() >= length {
+                        break (head.to_string(), body.to_string());
+                    }
+                }
+            };
+            let path = head.split_whitespace().nth(1).unwrap_or("").to_string();
+            let payload = serde_json::from_str(&body).unwrap_or(Value::Null);
+            recorded.lock().unwrap().push((path.clone(), payload));
+
+            let response = if path == "/approve" {
+                let body = r#"{"decision": "deny", "reason": "code freeze"}"#;
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+            } else {
+                "HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n".to_string()
+            };
+            let _ = stream.write_all(response.as_bytes()).await;
+            let _ = stream.shutdown().await;
+        }
+    });
+    Ok((base_url, received))
+}
+
+#[tokio::test]
+async fn test_hooks_observe_and_gate_the_iteration() -> Result<()> {
+    let tmp_dir = tempdir()?;
+    let work_dir = tmp_dir.path().join("work");
+    let ontology_dir = tmp_dir.path().join("ontology");
+    std::fs::create_dir_all(&work_dir)?;
+    std::fs::create_dir_all(&ontology_dir)?;
+
+    let (base_url, received) = serve_hooks().await?;
+    let hooks = serde_json::json!([
+        {
+            "name": "record-design",
+            "on": ["afterPersist"],
+            "match": {"entity": "Design"},
+            "command": "cat > persisted.json"
+        },
+        {
+            "name": "change-board",
+            "on": ["beforeDispatch"],
+            "match": {"relation": "implements", "verbType": "Creation"},
+            "url": format!("{}/approve", base_url),
+            "approval": true
+        },
+        {
+            "name": "notify",
+            "on": ["iterationEnd"],
+            "url": format!("{}/events", base_url)
+        }
+    ]);
+    std::fs::write(
+        ontology_dir.join("hooks.json"),
+        serde_json::to_string_pretty(&hooks)?,
+    )?;
+
+    let metamodel_json = r#"[
+        { "source": { "name": "Architect", "type": "Agent" }, "target": { "name": "Design", "type": "Other" }, "type": { "name": "creates", "verbType": "Creation" } },
+        { "source": { "name": "Dev", "type": "Agent" }, "target": { "name": "Code", "type": "Other" }, "type": { "name": "implements", "verbType": "Creation" }, "onFailure": { "action": "skip" } },
+        { "source": { "name": "Code", "type": "Other" }, "target": { "name": "Design", "type": "Other" }, "type": { "name": "dependsOn", "verbType": "Dependency" } }
+    ]"#;
+
+    let client = MockCliClient::new();
+    client.add_response(r#"{"components": ["parser"]}"#.to_string());
+
+    let mut orchestrator = Orchestrator::new_with_metamodel(
+        client,
+        "test-app-id".to_string(),
+        "Test App".to_string(),
+        work_dir.clone(),
+        metamodel_json,
+        Some(&ontology_dir),
+    )
+    .await?
+    .with_max_iterations(10);
+    orchestrator.run(&mock_ui()).await?;
+
+    // Hooks get the events as they were logged
+    let iteration_id = orchestrator.current_iteration.as_ref().unwrap().id.clone();
+    let iter_dir = work_dir
+        .join(".infinitecodingloop/iterations")
+        .join(&iteration_id);
+    let log = std::fs::read_to_string(iter_dir.join("logs/execution.jsonl"))?;
+    let logged: Vec<Value> = log
+        .lines()
+        .map(serde_json::from_str)
+        .collect::<Result<_, _>>()?;
+
+    // The command hook got the persisted artifact's event on stdin
+    let persisted: Value =
+        serde_json::from_str(&std::fs::read_to_string(work_dir.join("persisted.json"))?)?;
+    assert_eq!(persisted["event_type"], "artifact_persisted");
+    assert_eq!(persisted["details"]["name"], "Design");
+    assert!(logged.contains(&persisted));
+    let design: Value = serde_json::from_str(&std::fs::read_to_string(
+        work_dir.join(persisted["details"]["path"].as_str().unwrap()),
+    )?)?;
+    assert_eq!(design["components"][0], "parser");
+
+    // The approval hook denied the Code dispatch, which the skip policy absorbed
+    assert!(orchestrator.artifacts.contains_key("Design"));
+    assert!(!orchestrator.artifacts.contains_key("Code"));
+    let received = received.lock().unwrap().clone();
+    let paths: Vec<&str> = received.iter().map(|(path, _)| path.as_str()).collect();
+    assert_eq!(paths, vec!["/approve", "/events"]);
+    assert_eq!(received[0].1["event_type"], "action_dispatched");
+    assert_eq!(received[0].1["details"]["target"], "Code");
+    assert_eq!(received[1].1["event_type"], "iteration_end");
+    assert_eq!(received[1].1["details"]["outcome"], "completed");
+    assert!(logged.contains(&received[1].1));
+
+    assert!(log.contains("was denied by a hook: code freeze"));
+    assert!(log.contains("Hook change-board ran on beforeDispatch: denied: code freeze"));
+    assert!(log.contains("Hook notify ran on iterationEnd: ok"));
+    Ok(())
+}
+
+#[test]
+fn test_invalid_hooks_are_rejected_when_the_ontology_loads() {
+    let ontology_dir = tempdir().unwrap();
+    std::fs::write(
+        ontology_dir.path().join("hooks.json"),
+        r#"[{"name": "gate", "on": ["iterationEnd"], "command": "true", "approval": true}]"#,
+    )
+    .unwrap();
+    let metamodel_json = r#"[
+        { "source": { "name": "Architect", "type": "Agent" }, "target": { "name": "Design", "type": "Other" }, "type": { "name": "creates", "verbType": "Creation" } }
+    ]"#;
+    let err = pulpo_engine::graph::DependencyGraph::load_from_metamodel(
+        metamodel_json,
+        Some(ontology_dir.path()),
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("can only fire on beforeDispatch"));
+
+    std::fs::write(
+        ontology_dir.path().join("hooks.json"),
+        r#"[{"name": "both", "on": ["afterPersist"], "command": "true", "url": "http://localhost"}]"#,
+    )
+    .unwrap();
+    let err = pulpo_engine::graph::DependencyGraph::load_from_metamodel(
+        metamodel_json,
+        Some(ontology_dir.path()),
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("hooks.json is invalid"));
+}
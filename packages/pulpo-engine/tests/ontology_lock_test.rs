@@ -0,0 +1,83 @@
+use anyhow::Result;
+use pulpo_engine::agents::cli_client::mocks::MockCliClient;
+use pulpo_engine::lock::{LOCK_FILE, OntologyLock};
+use pulpo_engine::orchestrator::Orchestrator;
+use std::path::Path;
+use tempfile::tempdir;
+
+const METAMODEL: &str = r#"[
+    { "source": { "name": "Architect", "type": "Agent" }, "target": { "name": "Design", "type": "Other" }, "type": { "name": "creates", "verbType": "Creation" } }
+]"#;
+
+async fn orchestrator(work_dir: &Path, ontology_dir: &Path) -> Result<Orchestrator<MockCliClient>> {
+    Orchestrator::new_with_metamodel(
+        MockCliClient::new(),
+        "test-app-id".to_string(),
+        "Test App".to_string(),
+        work_dir.to_path_buf(),
+        METAMODEL,
+        Some(ontology_dir),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_iterations_record_and_check_the_locked_ontology() -> Result<()> {
+    let tmp_dir = tempdir()?;
+    let work_dir = tmp_dir.path().join("work");
+    let ontology_dir = tmp_dir.path().join("ontology");
+    std::fs::create_dir_all(&work_dir)?;
+    std::fs::create_dir_all(ontology_dir.join("agent/system_prompt"))?;
+    std::fs::write(ontology_dir.join("ontology.json"), METAMODEL)?;
+    std::fs::write(ontology_dir.join("package.json"), r#"{"version": "1.4.0"}"#)?;
+    let prompt = ontology_dir.join("agent/system_prompt/Architect.md");
+    std::fs::write(&prompt, "---\nversion: 1.0.0\n---\n\nYou design systems.")?;
+
+    let mut first = orchestrator(&work_dir, &ontology_dir).await?;
+    first.start_iteration("Locked").await?;
+    let lock = OntologyLock::load(&ontology_dir)?.expect("loading writes the lock");
+    assert_eq!(lock.version.as_deref(), Some("1.4.0"));
+    // The front matter versions the agent without reaching its prompt
+    assert_eq!(
+        first.executor.graph.system_prompt("Architect"),
+        "You design systems."
+    );
+
+    let iteration = first.current_iteration.clone().unwrap();
+    assert_eq!(iteration.ontology_version.as_deref(), Some("1.4.0"));
+    assert_eq!(iteration.ontology_hash.as_ref(), Some(&lock.hash));
+    let iter_folder = work_dir
+        .join(".infinitecodingloop/iterations")
+        .join(&iteration.id);
+    assert_eq!(
+        OntologyLock::read(&iter_folder.join(LOCK_FILE))?,
+        Some(lock)
+    );
+
+    // A versioned definition cannot change under the same version
+    std::fs::write(&prompt, "---\nversion: 1.0.0\n---\n\nYou design anything.")?;
+    let err = orchestrator(&work_dir, &ontology_dir)
+        .await
+        .err()
+        .expect("changing a published version is refused");
+    assert!(err.to_string().contains("changed without a version bump"));
+
+    // A bumped one can; resuming the iteration warns about the drift, or
+    // refuses it when locked
+    std::fs::write(&prompt, "---\nversion: 1.1.0\n---\n\nYou design anything.")?;
+    let mut resumed = orchestrator(&work_dir, &ontology_dir).await?;
+    resumed.load_iteration(&iteration.id).await?;
+    let log = std::fs::read_to_string(iter_folder.join("logs/execution.jsonl"))?;
+    assert!(log.contains("ontology_drift"));
+    assert!(log.contains("changed agent/system_prompt/Architect.md (1.0.0 -> 1.1.0)"));
+
+    let mut locked = orchestrator(&work_dir, &ontology_dir)
+        .await?
+        .with_locked(true);
+    let err = locked.load_iteration(&iteration.id).await.unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("The ontology drifted from the one iteration")
+    );
+    Ok(())
+}
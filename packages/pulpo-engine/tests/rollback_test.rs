@@ -0,0 +1,95 @@
+mod common;
+
+use anyhow::Result;
+use pulpo_engine::agents::cli_client::mocks::MockCliClient;
+use pulpo_engine::orchestrator::Orchestrator;
+use tempfile::tempdir;
+
+use common::{git, mock_ui};
+
+#[tokio::test]
+async fn test_rollback_restores_work_tree_and_state() -> Result<()> {
+    let tmp_dir = tempdir()?;
+    let work_dir = tmp_dir.path().to_path_buf();
+    git(&work_dir, &["init", "--quiet"]);
+    std::fs::write(work_dir.join("notes.txt"), "not for the engine")?;
+
+    let metamodel_json = r#"[
+        { "source": { "name": "Architect", "type": "Agent" }, "target": { "name": "Design", "type": "Other" }, "type": { "name": "creates", "verbType": "Creation" } },
+        { "source": { "name": "Dev", "type": "Agent" }, "target": { "name": "Code", "type": "Other" }, "type": { "name": "implements", "verbType": "Creation" } },
+        { "source": { "name": "Code", "type": "Other" }, "target": { "name": "Design", "type": "Other" }, "type": { "name": "dependsOn", "verbType": "Dependency" } }
+    ]"#;
+
+    let client = MockCliClient::new();
+    client.add_response(r#"{"components": ["parser"]}"#.to_string());
+    let source_file = work_dir.join("main.rs");
+    client.add_action(move |_prompt| {
+        std::fs::write(&source_file, "fn main() {}")?;
+        Ok(r#"{"files": ["main.rs"]}"#.to_string())
+    });
+
+    let mut orchestrator = Orchestrator::new_with_metamodel(
+        client,
+        "test-app-id".to_string(),
+        "Test App".to_string(),
+        work_dir.clone(),
+        metamodel_json,
+        None,
+    )
+    .await?
+    .with_max_iterations(10);
+    orchestrator.run(&mock_ui()).await?;
+    assert!(orchestrator.artifacts.contains_key("Code"));
+    assert!(work_dir.join("main.rs").exists());
+    let iteration_id = orchestrator.current_iteration.as_ref().unwrap().id.clone();
+    let code_doc = work_dir
+        .join(orchestrator.artifact_folder("Code"))
+        .join("code.json");
+    assert!(code_doc.exists());
+
+    // A fresh process rolls the iteration back to right after Design.
+    let mut rolled_back = Orchestrator::new_with_metamodel(
+        MockCliClient::new(),
+        "test-app-id".to_string(),
+        "Test App".to_string(),
+        work_dir.clone(),
+        metamodel_json,
+        None,
+    )
+    .await?;
+    let sha = rolled_back.rollback(&iteration_id, "Design").await?;
+    assert_eq!(
+        git(&work_dir, &["log", "-1", "--format=%s", &sha]),
+        "Architect creates Design"
+    );
+
+    assert!(!code_doc.exists());
+    // The source written while implementing Code is gone too; files no
+    // action wrote are left alone.
+    assert!(!work_dir.join("main.rs").exists());
+    assert!(work_dir.join("notes.txt").exists());
+    assert!(rolled_back.artifacts.contains_key("Design"));
+    assert!(!rolled_back.artifacts.contains_key("Code"));
+    assert_eq!(rolled_back.actions_executed, 1);
+    let next = rolled_back.identify_next_actions();
+    assert_eq!(next.len(), 1);
+    assert_eq!(next[0].target, "Code");
+
+    // History and logs are kept; the rollback is a commit of its own.
+    assert_eq!(
+        git(&work_dir, &["log", "-1", "--format=%s"]),
+        "Roll back to Design"
+    );
+    assert!(git(&work_dir, &["log", "--format=%s"]).contains("Dev implements Code"));
+    let log = std::fs::read_to_string(
+        work_dir
+            .join(".infinitecodingloop/iterations")
+            .join(&iteration_id)
+            .join("logs/execution.jsonl"),
+    )?;
+    assert!(log.contains("\"iteration_rolled_back\""));
+    assert!(log.contains("Committed Code"));
+
+    assert!(rolled_back.rollback(&iteration_id, "Review").await.is_err());
+    Ok(())
+}
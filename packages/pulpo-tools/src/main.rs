@@ -1,13 +1,12 @@
 use clap::{Parser, Subcommand};
-use rio_api::formatter::TriplesFormatter;
-use rio_api::model::{NamedNode, Subject, Term, Triple};
-use rio_turtle::{TurtleFormatter, TurtleParser};
-use serde::Deserialize;
+use rio_turtle::TurtleParser;
 use std::collections::HashSet;
 use std::fs::File;
 use std::io::{BufReader, BufWriter};
 use std::path::{Path, PathBuf};
 
+mod rdf;
+
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
@@ -17,7 +16,7 @@ struct Cli {
 
 #[derive(Subcommand)]
 enum Commands {
-    /// Convert JSON Schema metamodel to Turtle ontology
+    /// Convert an ontology between JSON, YAML, the triple syntax and Turtle, by file extension
     Convert {
         #[arg(
             short,
@@ -32,6 +31,17 @@ enum Commands {
         )]
         output: PathBuf,
     },
+    /// Import a Turtle ontology written by `convert` back into JSON, YAML or the triple syntax
+    Import {
+        #[arg(
+            short,
+            long,
+            default_value = "pulpo-ontologies/software-engineering/ontology.ttl"
+        )]
+        input: PathBuf,
+        #[arg(short, long)]
+        output: PathBuf,
+    },
     /// Verify a Turtle file syntax
     Verify {
         #[arg(
@@ -61,21 +71,6 @@ enum Commands {
     },
 }
 
-#[derive(Deserialize, Debug)]
-struct MetaRelationship {
-    source: MetaEntity,
-    target: MetaEntity,
-    #[serde(rename = "type")]
-    rel_type: MetaEntity,
-}
-
-#[derive(Deserialize, Debug)]
-struct MetaEntity {
-    name: String,
-}
-
-const BASE_IRI: &str = "https://pulpo.dev/ontology/";
-
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
@@ -83,6 +78,9 @@ fn main() -> anyhow::Result<()> {
         Commands::Convert { input, output } => {
             convert(&input, &output)?;
         }
+        Commands::Import { input, output } => {
+            import(&input, &output)?;
+        }
         Commands::Verify { input } => {
             verify(&input)?;
         }
@@ -98,112 +96,69 @@ fn main() -> anyhow::Result<()> {
 }
 
 fn convert(input_path: &PathBuf, output_path: &PathBuf) -> anyhow::Result<()> {
-    println!("Reading schema from {:?}", input_path);
-    let file = File::open(input_path)?;
-    let reader = BufReader::new(file);
-    let relationships: Vec<MetaRelationship> = serde_json::from_reader(reader)?;
+    use pulpo_engine::graph::OntologyFormat;
+
+    println!("Reading ontology from {:?}", input_path);
+    let value = if is_turtle(input_path) {
+        read_turtle(input_path)?
+    } else {
+        let content = std::fs::read_to_string(input_path)?;
+        OntologyFormat::of(Some(input_path), &content).parse(&content)?
+    };
 
     println!("Writing ontology to {:?}", output_path);
     if let Some(parent) = output_path.parent() {
         std::fs::create_dir_all(parent)?;
     }
-    let outfile = File::create(output_path)?;
-    let writer = BufWriter::new(outfile);
-    let mut formatter = TurtleFormatter::new(writer);
-
-    // Common Namespaces
-    let rdf_type = NamedNode {
-        iri: "http://www.w3.org/1999/02/22-rdf-syntax-ns#type",
-    };
-    let owl_class = NamedNode {
-        iri: "http://www.w3.org/2002/07/owl#Class",
-    };
-    let owl_obj_prop = NamedNode {
-        iri: "http://www.w3.org/2002/07/owl#ObjectProperty",
-    };
-    let rdfs_domain = NamedNode {
-        iri: "http://www.w3.org/2000/01/rdf-schema#domain",
-    };
-    let rdfs_range = NamedNode {
-        iri: "http://www.w3.org/2000/01/rdf-schema#range",
-    };
-
-    declare_triples(
-        relationships,
-        &mut formatter,
-        rdf_type,
-        owl_class,
-        owl_obj_prop,
-        rdfs_domain,
-        rdfs_range,
-    )?;
-
-    formatter.finish()?;
+    if !is_turtle(output_path) {
+        let format = OntologyFormat::from_path(output_path).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Unknown ontology format of {:?}: use .json, .yaml, .yml, .triples or .ttl",
+                output_path
+            )
+        })?;
+        std::fs::write(output_path, format.render(&value)?)?;
+        println!("Successfully wrote ontology.");
+        return Ok(());
+    }
+    rdf::to_turtle(&value, BufWriter::new(File::create(output_path)?))?;
     println!("Successfully wrote ontology.");
     Ok(())
 }
 
-fn declare_triples(
-    relationships: Vec<MetaRelationship>,
-    formatter: &mut TurtleFormatter<BufWriter<File>>,
-    rdf_type: NamedNode,
-    owl_class: NamedNode,
-    owl_obj_prop: NamedNode,
-    rdfs_domain: NamedNode,
-    rdfs_range: NamedNode,
-) -> anyhow::Result<()> {
-    let mut classes = HashSet::new();
-    let mut properties = HashSet::new();
-
-    for rel in relationships {
-        let s_name = rel.source.name;
-        let t_name = rel.target.name;
-        let r_name = rel.rel_type.name;
-
-        let s_iri = format!("{}{}", BASE_IRI, s_name);
-        let t_iri = format!("{}{}", BASE_IRI, t_name);
-        let r_iri = format!("{}{}", BASE_IRI, r_name);
-
-        // Declare Source Class
-        if classes.insert(s_name.clone()) {
-            formatter.format(&Triple {
-                subject: Subject::NamedNode(NamedNode { iri: &s_iri }),
-                predicate: rdf_type,
-                object: Term::NamedNode(owl_class),
-            })?;
-        }
-
-        // Declare Target Class
-        if classes.insert(t_name.clone()) {
-            formatter.format(&Triple {
-                subject: Subject::NamedNode(NamedNode { iri: &t_iri }),
-                predicate: rdf_type,
-                object: Term::NamedNode(owl_class),
-            })?;
-        }
-
-        // Declare Property
-        if properties.insert(r_name.clone()) {
-            formatter.format(&Triple {
-                subject: Subject::NamedNode(NamedNode { iri: &r_iri }),
-                predicate: rdf_type,
-                object: Term::NamedNode(owl_obj_prop),
-            })?;
-        }
+fn is_turtle(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "ttl")
+}
 
-        // Domain and Range
-        formatter.format(&Triple {
-            subject: Subject::NamedNode(NamedNode { iri: &r_iri }),
-            predicate: rdfs_domain,
-            object: Term::NamedNode(NamedNode { iri: &s_iri }),
-        })?;
+/// Reads the relationships of a Turtle ontology, printing where its OWL view
+/// disagrees with them.
+fn read_turtle(input_path: &Path) -> anyhow::Result<serde_json::Value> {
+    let imported = rdf::from_turtle(BufReader::new(File::open(input_path)?))?;
+    for warning in &imported.warnings {
+        println!(
+            "{}",
+            console::style(format!("warning: {}", warning)).yellow()
+        );
+    }
+    Ok(imported.relationships)
+}
 
-        formatter.format(&Triple {
-            subject: Subject::NamedNode(NamedNode { iri: &r_iri }),
-            predicate: rdfs_range,
-            object: Term::NamedNode(NamedNode { iri: &t_iri }),
-        })?;
+fn import(input_path: &PathBuf, output_path: &PathBuf) -> anyhow::Result<()> {
+    use pulpo_engine::graph::OntologyFormat;
+
+    println!("Reading Turtle from {:?}", input_path);
+    let value = read_turtle(input_path)?;
+    let format = OntologyFormat::from_path(output_path).unwrap_or(OntologyFormat::Json);
+    println!(
+        "Writing {} relationship(s) to {:?}",
+        value.as_array().map_or(0, Vec::len),
+        output_path
+    );
+    if let Some(parent) = output_path.parent() {
+        std::fs::create_dir_all(parent)?;
     }
+    std::fs::write(output_path, format.render(&value)?)?;
+    println!("Successfully wrote ontology.");
     Ok(())
 }
 
@@ -213,7 +168,7 @@ fn verify(input_path: &PathBuf) -> anyhow::Result<()> {
     println!("Verifying syntax of {:?}", input_path);
     let file = File::open(input_path)?;
     let reader = BufReader::new(file);
-    let mut parser = TurtleParser::new(reader, Some(BASE_IRI.parse()?));
+    let mut parser = TurtleParser::new(reader, Some(rdf::BASE_IRI.parse()?));
 
     let mut count = 0;
     while !parser.is_end() {
@@ -271,14 +226,32 @@ fn validate_graph(input_path: &PathBuf) -> anyhow::Result<()> {
 
     println!("Using base path: {:?}", base_path);
 
-    match DependencyGraph::load_from_metamodel(&content, base_path) {
-        Ok(_) => {
-            println!("✅ Graph topology and Schema are VALID.");
-            Ok(())
-        }
-        Err(e) => {
-            eprintln!("❌ Graph validation FAILED: {}", e);
-            std::process::exit(1);
+    let diagnostics = DependencyGraph::diagnose(&content, base_path);
+    print_diagnostics(input_path, &diagnostics);
+    let errors = diagnostics.iter().filter(|d| d.is_error()).count();
+    let warnings = diagnostics.len() - errors;
+    if errors > 0 {
+        eprintln!(
+            "❌ Graph validation FAILED: {} error(s), {} warning(s)",
+            errors, warnings
+        );
+        std::process::exit(1);
+    }
+    println!(
+        "✅ Graph topology and Schema are VALID ({} warning(s)).",
+        warnings
+    );
+    Ok(())
+}
+
+fn print_diagnostics(input_path: &Path, diagnostics: &[pulpo_engine::graph::Diagnostic]) {
+    use console::style;
+    for diagnostic in diagnostics {
+        let line = diagnostic.render(input_path);
+        if diagnostic.is_error() {
+            eprintln!("{}", style(line).red());
+        } else {
+            println!("{}", style(line).yellow());
         }
     }
 }
@@ -286,7 +259,7 @@ fn validate_graph(input_path: &PathBuf) -> anyhow::Result<()> {
 fn simulate_path(input_path: &PathBuf) -> anyhow::Result<()> {
     use console::style;
     use pulpo_engine::graph::DependencyGraph;
-    use std::collections::HashSet;
+    use pulpo_engine::planner::Simulation;
 
     println!("Simulating execution path for {:?}", input_path);
     let content = std::fs::read_to_string(input_path)?;
@@ -294,197 +267,92 @@ fn simulate_path(input_path: &PathBuf) -> anyhow::Result<()> {
     let base_path = infer_base_path(input_path);
     let graph = DependencyGraph::load_from_metamodel(&content, base_path.as_deref())?;
 
-    let mut produced = HashSet::new();
-    produced.insert("SoftwareApplication".to_string());
+    let mut simulation = Simulation::new();
+    simulation.provide("SoftwareApplication", serde_json::Value::Null);
 
-    let missing_creator = detect_missing_creators(&graph);
-    // Add missing creators to produced so simulation can continue
+    let missing_creator = graph.missing_creators();
+    // Treat artifacts without a creator as provided so simulation can continue
     for node in &missing_creator {
-        produced.insert(node.clone());
+        simulation.provide(node, serde_json::Value::Null);
     }
 
-    let mut verified = HashSet::new();
     println!("\n{}", style("PREDICTED EXECUTION PATH:").bold().yellow());
     println!("{} SoftwareApplication (Initial Goal)", style("🏠").green());
 
-    let steps = run_simulation_loop(&graph, &mut produced, &mut verified);
-
-    if steps >= 100 {
-        println!(
-            "\n{}",
-            style("Reached maximum simulation steps. Possible infinite loop in ontology?")
-                .red()
-                .bold()
-        );
-    }
+    print_simulation(&graph, &mut simulation);
 
+    let produced: HashSet<String> = simulation.artifacts.keys().cloned().collect();
     detect_unreachables(&graph, &produced, &missing_creator);
     Ok(())
 }
 
-fn infer_base_path(input_path: &Path) -> Option<PathBuf> {
-    input_path.parent().map(|p| {
-        let mut current = p;
-        if current.ends_with("schema") {
-            current = current.parent().unwrap_or(current);
-        }
-        if current.ends_with("artifact") {
-            current = current.parent().unwrap_or(current);
-        }
-        if current.ends_with("schemas") {
-            current = current.parent().unwrap_or(current);
-        }
-        current.to_path_buf()
-    })
-}
-
-fn detect_missing_creators(graph: &pulpo_engine::graph::DependencyGraph) -> Vec<String> {
-    use petgraph::visit::EdgeRef;
-    use pulpo_engine::graph::RelationCategory;
-    let mut missing_creator = Vec::new();
-    for node_idx in graph.graph.node_indices() {
-        let node_name = &graph.graph[node_idx];
-        if graph.is_agent(node_name) || node_name == "SoftwareApplication" {
-            continue;
-        }
-
-        let is_created_by_agent = graph
-            .graph
-            .edges_directed(node_idx, petgraph::Direction::Incoming)
-            .any(|edge| {
-                let relation = edge.weight();
-                let source_idx = edge.source();
-                let source_name = &graph.graph[source_idx];
-                let edge_key = (
-                    source_name.to_string(),
-                    relation.to_string(),
-                    node_name.to_string(),
-                );
-                let category = graph
-                    .edge_categories
-                    .get(&edge_key)
-                    .copied()
-                    .unwrap_or(RelationCategory::Context);
-
-                category == RelationCategory::Creation && graph.is_agent(source_name)
-            });
-
-        if !is_created_by_agent {
-            missing_creator.push(node_name.clone());
-        }
-    }
-    missing_creator
-}
-
-fn run_simulation_loop(
+fn print_simulation(
     graph: &pulpo_engine::graph::DependencyGraph,
-    produced: &mut HashSet<String>,
-    verified: &mut HashSet<String>,
-) -> usize {
+    simulation: &mut pulpo_engine::planner::Simulation,
+) {
     use console::style;
     use pulpo_engine::graph::RelationCategory;
-    let mut steps = 0;
-    let max_steps = 100;
-
-    while steps < max_steps {
-        let mut actions = Vec::new();
-
-        for edge_idx in graph.graph.edge_indices() {
-            let (source_idx, target_idx) = graph.graph.edge_endpoints(edge_idx).unwrap();
-            let source_kind = &graph.graph[source_idx];
-            let target_kind = &graph.graph[target_idx];
-            let relation = &graph.graph[edge_idx];
-            let edge_key = (
-                source_kind.to_string(),
-                relation.to_string(),
-                target_kind.to_string(),
-            );
-            let category = graph
-                .edge_categories
-                .get(&edge_key)
-                .copied()
-                .unwrap_or(RelationCategory::Context);
-
-            if graph.is_agent(source_kind) {
-                match category {
-                    RelationCategory::Creation => {
-                        if !produced.contains(target_kind) {
-                            actions.push((
-                                source_kind.clone(),
-                                relation.clone(),
-                                target_kind.clone(),
-                                "Creation",
-                            ));
-                        }
-                    }
-                    RelationCategory::Verification => {
-                        if produced.contains(target_kind) && !verified.contains(target_kind) {
-                            actions.push((
-                                source_kind.clone(),
-                                relation.clone(),
-                                target_kind.clone(),
-                                "Verification",
-                            ));
-                        }
-                    }
-                    _ => {}
-                }
-            }
-        }
-
-        if actions.is_empty() {
-            println!(
-                "\n{}",
-                style("Simulation complete. No more actionable nodes found.")
-                    .bold()
-                    .dim()
-            );
-            break;
-        }
-
-        let (agent, relation, target, cat) = &actions[0];
-        steps += 1;
+    let max_cycles = 100;
 
-        let icon = match *cat {
-            "Creation" => style("🪄").cyan(),
-            "Verification" => style("✅").green(),
+    for step in simulation.predict(graph, max_cycles, 1) {
+        let icon = match step.action.category {
+            RelationCategory::Creation => style("🪄").cyan(),
+            RelationCategory::Verification => style("✅").green(),
+            RelationCategory::Refinement => style("🔁").yellow(),
             _ => style("➜").white(),
         };
 
         println!(
             "{:02}. {} {} {} {}",
-            steps,
+            step.step,
             icon,
-            style(agent).bold().blue(),
-            style(relation).dim(),
-            style(target).bold().magenta()
+            style(&step.action.agent).bold().blue(),
+            style(&step.action.relation).dim(),
+            style(&step.action.target).bold().magenta()
         );
 
-        let mut context = graph.get_related_artifacts(target);
-        if *cat == "Verification" || *cat == "Refines" {
-            context.push(target.clone());
-        }
-        context.push("SoftwareApplication".to_string());
-        context.retain(|c| produced.contains(c));
-        context.sort();
-        context.dedup();
-
-        if !context.is_empty() {
-            let context_str = context
+        if !step.context.is_empty() {
+            let context_str = step
+                .context
                 .iter()
                 .map(|c| style(c).dim().to_string())
                 .collect::<Vec<_>>()
                 .join(", ");
             println!("    {} {}", style("Context:").dim(), context_str);
         }
+    }
 
-        if *cat == "Creation" {
-            produced.insert(target.clone());
-        } else if *cat == "Verification" {
-            verified.insert(target.clone());
-        }
+    if simulation.planner(graph).next_actions().is_empty() {
+        println!(
+            "\n{}",
+            style("Simulation complete. No more actionable nodes found.")
+                .bold()
+                .dim()
+        );
+    } else {
+        println!(
+            "\n{}",
+            style("Reached maximum simulation steps. Possible infinite loop in ontology?")
+                .red()
+                .bold()
+        );
     }
-    steps
+}
+
+fn infer_base_path(input_path: &Path) -> Option<PathBuf> {
+    input_path.parent().map(|p| {
+        let mut current = p;
+        if current.ends_with("schema") {
+            current = current.parent().unwrap_or(current);
+        }
+        if current.ends_with("artifact") {
+            current = current.parent().unwrap_or(current);
+        }
+        if current.ends_with("schemas") {
+            current = current.parent().unwrap_or(current);
+        }
+        current.to_path_buf()
+    })
 }
 
 fn detect_unreachables(
@@ -542,3 +410,57 @@ fn detect_unreachables(
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn test_convert_goes_between_every_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let ontology = serde_json::json!([
+            {
+                "source": {"name": "Architect", "type": "Agent", "model": "gemini-2.5-pro"},
+                "target": {"name": "Design", "type": "Document"},
+                "type": {"name": "creates", "verbType": "Creation"},
+                "loop": {"maxRetries": 3, "passThreshold": 0.8}
+            },
+            {
+                "source": {"name": "Tests", "type": "Command", "command": "cargo test"},
+                "target": {"name": "Design", "type": "Document"},
+                "type": {"name": "verifies", "verbType": "Verification"}
+            }
+        ]);
+        let json = dir.path().join("ontology.json");
+        std::fs::write(&json, serde_json::to_string_pretty(&ontology).unwrap()).unwrap();
+
+        // JSON -> YAML -> triples -> Turtle -> JSON
+        let yaml = dir.path().join("out/ontology.yaml");
+        let triples = dir.path().join("ontology.triples");
+        let turtle = dir.path().join("ontology.ttl");
+        let back = dir.path().join("back.json");
+        convert(&json, &yaml).unwrap();
+        convert(&yaml, &triples).unwrap();
+        convert(&triples, &turtle).unwrap();
+        import(&turtle, &back).unwrap();
+        let converted = dir.path().join("converted.json");
+        convert(&turtle, &converted).unwrap();
+
+        assert!(std::fs::read_to_string(&yaml).unwrap().starts_with("- "));
+        assert!(
+            std::fs::read_to_string(&triples)
+                .unwrap()
+                .starts_with("Architect[Agent, model=gemini-2.5-pro] creates(Creation) Design")
+        );
+        let restored: Value =
+            serde_json::from_str(&std::fs::read_to_string(&back).unwrap()).unwrap();
+        assert_eq!(restored, ontology);
+        let converted: Value =
+            serde_json::from_str(&std::fs::read_to_string(&converted).unwrap()).unwrap();
+        assert_eq!(converted, ontology);
+
+        let err = convert(&json, &dir.path().join("ontology.xml")).unwrap_err();
+        assert!(err.to_string().contains("Unknown ontology format"));
+    }
+}
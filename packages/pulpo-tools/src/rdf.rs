@@ -0,0 +1,571 @@
+use anyhow::Result;
+use rio_api::formatter::TriplesFormatter;
+use rio_api::model::{BlankNode, Literal, NamedNode, Subject, Term, Triple};
+use rio_api::parser::TriplesParser;
+use rio_turtle::{TurtleFormatter, TurtleParser};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::io::{BufRead, Write};
+
+/// Namespace of the classes and properties of the ontology's entities.
+pub const BASE_IRI: &str = "https://pulpo.dev/ontology/";
+
+/// Namespace of the vocabulary describing relationships. Every key of the
+/// metamodel (`source`, `verbType`, `loop`, `maxRetries`, `aiCli`...) is the
+/// property of the same name, so the Turtle holds all of `ontology.json`.
+pub const VOCAB_IRI: &str = "https://pulpo.dev/vocab#";
+
+const RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+const RDF_FIRST: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#first";
+const RDF_REST: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#rest";
+const RDF_NIL: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#nil";
+const OWL_CLASS: &str = "http://www.w3.org/2002/07/owl#Class";
+const OWL_OBJECT_PROPERTY: &str = "http://www.w3.org/2002/07/owl#ObjectProperty";
+const RDFS_DOMAIN: &str = "http://www.w3.org/2000/01/rdf-schema#domain";
+const RDFS_RANGE: &str = "http://www.w3.org/2000/01/rdf-schema#range";
+const XSD_INTEGER: &str = "http://www.w3.org/2001/XMLSchema#integer";
+const XSD_DOUBLE: &str = "http://www.w3.org/2001/XMLSchema#double";
+const XSD_BOOLEAN: &str = "http://www.w3.org/2001/XMLSchema#boolean";
+
+// Class of a relationship node, and its position in ontology.json
+const RELATIONSHIP: &str = "Relationship";
+const POSITION: &str = "position";
+
+fn vocab(term: &str) -> String {
+    format!("{}{}", VOCAB_IRI, term)
+}
+
+fn entity_iri(name: &str) -> String {
+    format!("{}{}", BASE_IRI, name)
+}
+
+/// Writes the relationships of an ontology as Turtle. Entities are declared
+/// as OWL classes and relations as object properties with their domain and
+/// range; each relationship is also described in full, as a
+/// `vocab:Relationship` node with one property per key, so that
+/// [`from_turtle`] restores it exactly.
+pub fn to_turtle(relationships: &Value, writer: impl Write) -> Result<()> {
+    let items = relationships
+        .as_array()
+        .ok_or_else(|| anyhow::anyhow!("An ontology is an array of relationships"))?;
+    let mut formatter = TurtleFormatter::new(writer);
+    let mut classes = HashSet::new();
+    let mut properties = HashSet::new();
+    let rdf_type = NamedNode { iri: RDF_TYPE };
+
+    for (position, relationship) in items.iter().enumerate() {
+        let name = |key: &str| relationship[key]["name"].as_str().unwrap_or_default();
+        let (source, relation, target) = (name("source"), name("type"), name("target"));
+        let (s_iri, r_iri, t_iri) = (entity_iri(source), entity_iri(relation), entity_iri(target));
+
+        for (entity, iri) in [(source, &s_iri), (target, &t_iri)] {
+            if classes.insert(entity.to_string()) {
+                formatter.format(&Triple {
+                    subject: Subject::NamedNode(NamedNode { iri }),
+                    predicate: rdf_type,
+                    object: Term::NamedNode(NamedNode { iri: OWL_CLASS }),
+                })?;
+            }
+        }
+        if properties.insert(relation.to_string()) {
+            formatter.format(&Triple {
+                subject: Subject::NamedNode(NamedNode { iri: &r_iri }),
+                predicate: rdf_type,
+                object: Term::NamedNode(NamedNode {
+                    iri: OWL_OBJECT_PROPERTY,
+                }),
+            })?;
+        }
+        for (predicate, iri) in [(RDFS_DOMAIN, &s_iri), (RDFS_RANGE, &t_iri)] {
+            formatter.format(&Triple {
+                subject: Subject::NamedNode(NamedNode { iri: &r_iri }),
+                predicate: NamedNode { iri: predicate },
+                object: Term::NamedNode(NamedNode { iri }),
+            })?;
+        }
+
+        let node = format!("r{}", position);
+        let class = vocab(RELATIONSHIP);
+        formatter.format(&Triple {
+            subject: Subject::BlankNode(BlankNode { id: &node }),
+            predicate: rdf_type,
+            object: Term::NamedNode(NamedNode { iri: &class }),
+        })?;
+        let position = position.to_string();
+        let predicate = vocab(POSITION);
+        formatter.format(&Triple {
+            subject: Subject::BlankNode(BlankNode { id: &node }),
+            predicate: NamedNode { iri: &predicate },
+            object: Term::Literal(Literal::Typed {
+                value: &position,
+                datatype: NamedNode { iri: XSD_INTEGER },
+            }),
+        })?;
+        let object = relationship.as_object().ok_or_else(|| {
+            anyhow::anyhow!("A relationship is an object, found {}", relationship)
+        })?;
+        write_object(&mut formatter, &node, object)?;
+
+        // Occurrences of an entity are instances of its class
+        for (key, iri) in [("source", &s_iri), ("target", &t_iri)] {
+            let occurrence = format!("{}_{}", node, key);
+            formatter.format(&Triple {
+                subject: Subject::BlankNode(BlankNode { id: &occurrence }),
+                predicate: rdf_type,
+                object: Term::NamedNode(NamedNode { iri }),
+            })?;
+        }
+    }
+    formatter.finish()?;
+    Ok(())
+}
+
+/// Describes `object` as the blank node `node`: scalars as typed literals,
+/// nested objects as blank nodes named after their key and arrays as RDF
+/// lists.
+fn write_object<W: Write>(
+    formatter: &mut TurtleFormatter<W>,
+    node: &str,
+    object: &Map<String, Value>,
+) -> Result<()> {
+    for (key, value) in object {
+        let child = format!("{}_{}", node, key);
+        write_value(formatter, node, &vocab(key), &child, value)?;
+    }
+    Ok(())
+}
+
+/// Writes `value` as the object of `node predicate`. Objects and lists are
+/// described from the blank node `child`.
+fn write_value<W: Write>(
+    formatter: &mut TurtleFormatter<W>,
+    node: &str,
+    predicate: &str,
+    child: &str,
+    value: &Value,
+) -> Result<()> {
+    let subject = Subject::BlankNode(BlankNode { id: node });
+    let predicate = NamedNode { iri: predicate };
+    let text = match value {
+        Value::Null => return Ok(()),
+        Value::Object(nested) => {
+            formatter.format(&Triple {
+                subject,
+                predicate,
+                object: Term::BlankNode(BlankNode { id: child }),
+            })?;
+            return write_object(formatter, child, nested);
+        }
+        Value::Array(items) if items.is_empty() => {
+            formatter.format(&Triple {
+                subject,
+                predicate,
+                object: Term::NamedNode(NamedNode { iri: RDF_NIL }),
+            })?;
+            return Ok(());
+        }
+        Value::Array(items) => {
+            formatter.format(&Triple {
+                subject,
+                predicate,
+                object: Term::BlankNode(BlankNode { id: child }),
+            })?;
+            // One cell per item, the first one being `child`
+            let cells: Vec<String> = (0..items.len())
+                .map(|i| match i {
+                    0 => child.to_string(),
+                    i => format!("{}_{}", child, i),
+                })
+                .collect();
+            for (i, item) in items.iter().enumerate() {
+                let cell = &cells[i];
+                write_value(formatter, cell, RDF_FIRST, &format!("{}_item", cell), item)?;
+                let rest = match cells.get(i + 1) {
+                    Some(next) => Term::BlankNode(BlankNode { id: next }),
+                    None => Term::NamedNode(NamedNode { iri: RDF_NIL }),
+                };
+                formatter.format(&Triple {
+                    subject: Subject::BlankNode(BlankNode { id: cell }),
+                    predicate: NamedNode { iri: RDF_REST },
+                    object: rest,
+                })?;
+            }
+            return Ok(());
+        }
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    let literal = match value {
+        Value::Bool(_) => Literal::Typed {
+            value: &text,
+            datatype: NamedNode { iri: XSD_BOOLEAN },
+        },
+        Value::Number(n) if n.is_f64() => Literal::Typed {
+            value: &text,
+            datatype: NamedNode { iri: XSD_DOUBLE },
+        },
+        Value::Number(_) => Literal::Typed {
+            value: &text,
+            datatype: NamedNode { iri: XSD_INTEGER },
+        },
+        _ => Literal::Simple { value: &text },
+    };
+    formatter.format(&Triple {
+        subject,
+        predicate,
+        object: Term::Literal(literal),
+    })?;
+    Ok(())
+}
+
+// Object of a triple, detached from the parser's buffers
+enum Object {
+    Node(String),
+    Literal(Value),
+}
+
+/// Relationships read back from Turtle, and where its OWL view disagrees
+/// with them.
+pub struct Imported {
+    pub relationships: Value,
+    pub warnings: Vec<String>,
+}
+
+// The vocab: description of the relationships and the RDF lists in it
+#[derive(Default)]
+struct Description {
+    nodes: BTreeMap<String, Vec<(String, Object)>>,
+    first: BTreeMap<String, Object>,
+    rest: BTreeMap<String, Object>,
+}
+
+// OWL declarations of the entities and relations
+#[derive(Default, PartialEq)]
+struct OwlView {
+    classes: BTreeSet<String>,
+    properties: BTreeSet<String>,
+    domains: BTreeSet<(String, String)>,
+    ranges: BTreeSet<(String, String)>,
+}
+
+impl OwlView {
+    /// The declarations [`to_turtle`] writes for `relationships`.
+    fn of(relationships: &[Value]) -> Self {
+        let mut view = OwlView::default();
+        for relationship in relationships {
+            let name = |key: &str| {
+                relationship[key]["name"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            let (source, relation, target) = (name("source"), name("type"), name("target"));
+            view.classes.insert(source.clone());
+            view.classes.insert(target.clone());
+            view.properties.insert(relation.clone());
+            view.domains.insert((relation.clone(), source));
+            view.ranges.insert((relation, target));
+        }
+        view
+    }
+
+    fn is_empty(&self) -> bool {
+        *self == OwlView::default()
+    }
+
+    /// Each declaration, in words.
+    fn facts(&self) -> BTreeSet<String> {
+        let classes = self.classes.iter().map(|c| format!("class {}", c));
+        let properties = self.properties.iter().map(|p| format!("relation {}", p));
+        let domains = self
+            .domains
+            .iter()
+            .map(|(p, c)| format!("domain {} of {}", c, p));
+        let ranges = self
+            .ranges
+            .iter()
+            .map(|(p, c)| format!("range {} of {}", c, p));
+        classes
+            .chain(properties)
+            .chain(domains)
+            .chain(ranges)
+            .collect()
+    }
+
+    /// Where this OWL view and the one `relationships` imply disagree.
+    fn differences(&self, relationships: &[Value]) -> Vec<String> {
+        let (declared, described) = (self.facts(), OwlView::of(relationships).facts());
+        let undescribed = declared.difference(&described).map(|fact| {
+            format!(
+                "The OWL view declares the {}, which no vocab:Relationship describes",
+                fact
+            )
+        });
+        let undeclared = described.difference(&declared).map(|fact| {
+            format!(
+                "The vocab:Relationship nodes describe the {}, which the OWL view does not declare",
+                fact
+            )
+        });
+        undescribed.chain(undeclared).collect()
+    }
+}
+
+/// Reads back the relationships written by [`to_turtle`], in their original
+/// order, from their `vocab:` description. Its OWL view is only compared
+/// with them: an entity or relation edited there and not in the description
+/// is reported as a warning. Other triples, such as ones added by other RDF
+/// tools, are ignored.
+pub fn from_turtle(reader: impl BufRead) -> Result<Imported> {
+    let mut parser = TurtleParser::new(reader, Some(BASE_IRI.parse()?));
+    let mut description = Description::default();
+    let mut owl = OwlView::default();
+    let mut relationships = Vec::new();
+    let class = vocab(RELATIONSHIP);
+    parser.parse_all(&mut |triple| -> Result<()> {
+        let subject = match triple.subject {
+            Subject::NamedNode(node) => node.iri.to_string(),
+            Subject::BlankNode(node) => format!("_:{}", node.id),
+            Subject::Triple(_) => return Ok(()),
+        };
+        let object = match triple.object {
+            Term::NamedNode(node) => Object::Node(node.iri.to_string()),
+            Term::BlankNode(node) => Object::Node(format!("_:{}", node.id)),
+            Term::Literal(literal) => Object::Literal(literal_value(literal)),
+            Term::Triple(_) => return Ok(()),
+        };
+        let entity = |iri: &str| iri.strip_prefix(BASE_IRI).map(str::to_string);
+        let owl_object = match &object {
+            Object::Node(iri) => entity(iri),
+            Object::Literal(_) => None,
+        };
+        match (triple.predicate.iri, entity(&subject)) {
+            (RDF_TYPE, _) if matches!(&object, Object::Node(iri) if *iri == class) => {
+                relationships.push(subject);
+            }
+            (RDF_TYPE, Some(name)) => match &object {
+                Object::Node(iri) if iri == OWL_CLASS => {
+                    owl.classes.insert(name);
+                }
+                Object::Node(iri) if iri == OWL_OBJECT_PROPERTY => {
+                    owl.properties.insert(name);
+                }
+                _ => {}
+            },
+            (RDFS_DOMAIN, Some(name)) => owl.domains.extend(owl_object.map(|c| (name, c))),
+            (RDFS_RANGE, Some(name)) => owl.ranges.extend(owl_object.map(|c| (name, c))),
+            (RDF_FIRST, _) => {
+                if description.first.insert(subject.clone(), object).is_some() {
+                    anyhow::bail!("List cell {} has more than one rdf:first", subject);
+                }
+            }
+            (RDF_REST, _) => {
+                if description.rest.insert(subject.clone(), object).is_some() {
+                    anyhow::bail!("List cell {} has more than one rdf:rest", subject);
+                }
+            }
+            (predicate, _) => {
+                if let Some(key) = predicate.strip_prefix(VOCAB_IRI) {
+                    description
+                        .nodes
+                        .entry(subject)
+                        .or_default()
+                        .push((key.to_string(), object));
+                }
+            }
+        }
+        Ok(())
+    })?;
+
+    let mut ordered = Vec::new();
+    for node in relationships {
+        let mut relationship = description.read_object(&node, 0)?;
+        let position = relationship
+            .remove(POSITION)
+            .and_then(|position| position.as_u64())
+            .ok_or_else(|| anyhow::anyhow!("Relationship {} has no {}", node, POSITION))?;
+        ordered.push((position, Value::Object(relationship)));
+    }
+    ordered.sort_by_key(|(position, _)| *position);
+    let relationships: Vec<Value> = ordered.into_iter().map(|(_, value)| value).collect();
+
+    // A Turtle file with no OWL view at all has nothing to disagree with
+    let warnings = if owl.is_empty() {
+        Vec::new()
+    } else {
+        owl.differences(&relationships)
+    };
+    Ok(Imported {
+        relationships: Value::Array(relationships),
+        warnings,
+    })
+}
+
+impl Description {
+    fn read_object(&self, node: &str, depth: usize) -> Result<Map<String, Value>> {
+        if depth > 16 {
+            anyhow::bail!("Node {} nests too deeply", node);
+        }
+        let mut object = Map::new();
+        for (key, value) in self.nodes.get(node).into_iter().flatten() {
+            let value = self.read_value(value, depth)?;
+            if object.insert(key.clone(), value).is_some() {
+                anyhow::bail!("Node {} has vocab:{} more than once", node, key);
+            }
+        }
+        Ok(object)
+    }
+
+    fn read_value(&self, value: &Object, depth: usize) -> Result<Value> {
+        Ok(match value {
+            Object::Literal(value) => value.clone(),
+            Object::Node(iri) if iri == RDF_NIL => Value::Array(Vec::new()),
+            Object::Node(cell) if self.rest.contains_key(cell) => {
+                self.read_list(cell, depth + 1)?
+            }
+            Object::Node(child) => Value::Object(self.read_object(child, depth + 1)?),
+        })
+    }
+
+    fn read_list(&self, head: &str, depth: usize) -> Result<Value> {
+        let mut items = Vec::new();
+        let mut cell = head;
+        while cell != RDF_NIL {
+            if items.len() > 10_000 {
+                anyhow::bail!("List {} does not end", head);
+            }
+            items.push(match self.first.get(cell) {
+                Some(item) => self.read_value(item, depth)?,
+                None => Value::Null,
+            });
+            cell = match self.rest.get(cell) {
+                Some(Object::Node(next)) => next,
+                _ => anyhow::bail!("List cell {} has no rdf:rest", cell),
+            };
+        }
+        Ok(Value::Array(items))
+    }
+}
+
+fn literal_value(literal: Literal) -> Value {
+    let (value, datatype) = match literal {
+        Literal::Simple { value } | Literal::LanguageTaggedString { value, .. } => {
+            return Value::String(value.to_string());
+        }
+        Literal::Typed { value, datatype } => (value, datatype.iri),
+    };
+    let parsed = match datatype {
+        XSD_BOOLEAN => value.parse::<bool>().ok().map(Value::Bool),
+        XSD_INTEGER => value.parse::<i64>().ok().map(Value::from),
+        XSD_DOUBLE => value
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map(Value::Number),
+        _ => None,
+    };
+    parsed.unwrap_or_else(|| Value::String(value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulpo_engine::graph::DependencyGraph;
+
+    fn edges(graph: &DependencyGraph) -> Vec<(String, String, String)> {
+        let mut edges: Vec<_> = graph
+            .graph
+            .edge_indices()
+            .map(|edge| {
+                let (s, t) = graph.graph.edge_endpoints(edge).unwrap();
+                (
+                    graph.graph[s].clone(),
+                    graph.graph[edge].clone(),
+                    graph.graph[t].clone(),
+                )
+            })
+            .collect();
+        edges.sort();
+        edges
+    }
+
+    #[test]
+    fn test_turtle_round_trips_the_whole_metamodel() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../pulpo-ontologies/software-engineering/ontology.json"
+        );
+        let mut ontology: Value =
+            serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        // Cover every optional field of the metamodel
+        ontology.as_array_mut().unwrap().push(serde_json::json!({
+            "source": {"name": "Engineer", "type": "Agent", "modelType": "High Reasoning", "model": "gemini-2.5-pro", "aiCli": "gemini"},
+            "target": {"name": "Coverage", "type": "Command", "command": "cargo tarpaulin --out \"json\"", "scope": "application"},
+            "type": {"name": "verifies", "verbType": "Verification"},
+            "loop": {"maxRetries": 2, "passThreshold": 0.8},
+            "onFailure": {"action": "retry", "maxRetries": 3},
+            "timeout": {"seconds": 600, "stallSeconds": 120},
+            "verifier": {"command": "cargo tarpaulin", "score": {"from": "metric", "label": "coverage:"}, "scale": 100.0}
+        }));
+
+        let mut turtle = Vec::new();
+        to_turtle(&ontology, &mut turtle).unwrap();
+        let imported = from_turtle(turtle.as_slice()).unwrap();
+        assert!(imported.warnings.is_empty(), "{:?}", imported.warnings);
+        let imported = imported.relationships;
+        assert_eq!(imported, ontology);
+
+        let original = DependencyGraph::load_from_metamodel(&ontology.to_string(), None).unwrap();
+        let restored = DependencyGraph::load_from_metamodel(&imported.to_string(), None).unwrap();
+        assert_eq!(edges(&restored), edges(&original));
+        assert_eq!(restored.edge_categories, original.edge_categories);
+        assert_eq!(restored.node_types, original.node_types);
+
+        // The OWL view is still there for RDF tools
+        let text = String::from_utf8(turtle).unwrap();
+        assert!(text.contains(
+            "<https://pulpo.dev/ontology/Architect> <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <http://www.w3.org/2002/07/owl#Class>"
+        ));
+    }
+
+    #[test]
+    fn test_turtle_keeps_arrays_and_reports_what_it_cannot_import() {
+        let ontology = serde_json::json!([{
+            "source": {"name": "Architect", "type": "Agent"},
+            "target": {"name": "Design", "type": "Other"},
+            "type": {"name": "creates", "verbType": "Creation"},
+            "tags": ["fast", {"level": 2}, [], [1, null], true]
+        }]);
+        let mut turtle = Vec::new();
+        to_turtle(&ontology, &mut turtle).unwrap();
+        assert_eq!(
+            from_turtle(turtle.as_slice()).unwrap().relationships,
+            ontology
+        );
+
+        // An entity edited only in the OWL view is reported, not imported
+        let mut edited = turtle.clone();
+        edited.extend_from_slice(
+            b"<https://pulpo.dev/ontology/Reviewer> <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <http://www.w3.org/2002/07/owl#Class> .\n",
+        );
+        let imported = from_turtle(edited.as_slice()).unwrap();
+        assert_eq!(imported.relationships, ontology);
+        assert_eq!(
+            imported.warnings,
+            vec![
+                "The OWL view declares the class Reviewer, which no vocab:Relationship describes"
+                    .to_string()
+            ]
+        );
+
+        // A key given twice is ambiguous
+        let mut duplicated = turtle;
+        duplicated.extend_from_slice(b"_:r0 <https://pulpo.dev/vocab#tags> \"slow\" .\n");
+        let err = from_turtle(duplicated.as_slice()).err().unwrap();
+        assert!(
+            err.to_string().contains("has vocab:tags more than once"),
+            "{}",
+            err
+        );
+    }
+}
@@ -112,7 +112,7 @@ impl AiCliClient for MockSchemaCompliantClient {
 async fn test_small_ontology_workflow() -> Result<()> {
     // Path to repo root
     let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-    let root_dir = manifest_dir.parent().unwrap();
+    let root_dir = manifest_dir.parent().unwrap().parent().unwrap();
 
     // We use ontology-software-engineering as base for prompts/schemas
     let base_ontology_path = root_dir.join("pulpo-ontologies/software-engineering");